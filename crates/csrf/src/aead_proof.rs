@@ -0,0 +1,186 @@
+use aead::generic_array::GenericArray;
+use aead::AeadInPlace;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
+
+use super::CsrfVerifyError;
+
+/// Length of the tag appended by every AEAD cipher of this crate.
+pub(crate) const TAG_LEN: usize = 16;
+
+/// Seal a new random token of `token_size` bytes with `aead`.
+///
+/// The proof is a random nonce of `nonce_len` bytes, the sealed token and the tag.
+pub(crate) fn seal<A: AeadInPlace>(
+    aead: &A,
+    nonce_len: usize,
+    rng: &mut dyn RngCore,
+    token_size: usize,
+    aad: &[u8],
+) -> (String, String) {
+    let mut token = vec![0; token_size];
+    rng.fill_bytes(&mut token);
+    let mut proof = vec![0; nonce_len];
+    rng.fill_bytes(&mut proof);
+    proof.reserve(token.len() + TAG_LEN);
+    proof.extend_from_slice(&token);
+    let (nonce, plain) = proof.split_at_mut(nonce_len);
+    let tag = aead
+        .encrypt_in_place_detached(GenericArray::from_slice(nonce), aad, plain)
+        .expect("encryption failed");
+    proof.extend_from_slice(&tag);
+    (URL_SAFE_NO_PAD.encode(token), URL_SAFE_NO_PAD.encode(proof))
+}
+
+/// Open a proof made by [`seal`] and check it holds `token`.
+pub(crate) fn open<A: AeadInPlace>(
+    aead: &A,
+    nonce_len: usize,
+    token: &str,
+    proof: &str,
+    aad: &[u8],
+) -> Result<(), CsrfVerifyError> {
+    let (Ok(token), Ok(mut proof)) = (
+        URL_SAFE_NO_PAD.decode(token.as_bytes()),
+        URL_SAFE_NO_PAD.decode(proof.as_bytes()),
+    ) else {
+        return Err(CsrfVerifyError::Malformed);
+    };
+    // The proof must be a nonce, the sealed token and a tag, anything else is malformed.
+    if token.len() < 8 || proof.len() != nonce_len + token.len() + TAG_LEN {
+        return Err(CsrfVerifyError::Malformed);
+    }
+    let (nonce, sealed) = proof.split_at_mut(nonce_len);
+    let (plain, tag) = sealed.split_at_mut(sealed.len() - TAG_LEN);
+    let opened = aead
+        .decrypt_in_place_detached(
+            GenericArray::from_slice(nonce),
+            aad,
+            plain,
+            GenericArray::from_slice(tag),
+        )
+        .is_ok();
+    if opened && *plain == token {
+        Ok(())
+    } else {
+        Err(CsrfVerifyError::Invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+
+    use super::TAG_LEN;
+    use crate::{CsrfCipher, CsrfVerifyError};
+
+    /// Every enabled AEAD cipher with `key`, and the length of its nonce.
+    #[allow(clippy::vec_init_then_push)]
+    fn ciphers(key: [u8; 32]) -> Vec<(Box<dyn CsrfCipher>, usize)> {
+        let mut ciphers: Vec<(Box<dyn CsrfCipher>, usize)> = vec![];
+        #[cfg(feature = "aes-gcm-cipher")]
+        ciphers.push((Box::new(crate::AesGcmCipher::new(key)), 12));
+        #[cfg(feature = "aes-gcm-siv-cipher")]
+        ciphers.push((Box::new(crate::AesGcmSivCipher::new(key)), 12));
+        #[cfg(feature = "ccp-cipher")]
+        ciphers.push((Box::new(crate::CcpCipher::new(key)), 12));
+        #[cfg(feature = "xccp-cipher")]
+        ciphers.push((Box::new(crate::XCcpCipher::new(key)), 24));
+        ciphers
+    }
+
+    #[test]
+    fn test_verify_detailed() {
+        for ((cipher, nonce_len), (other_key, _)) in
+            ciphers([0u8; 32]).iter().zip(ciphers([1u8; 32]))
+        {
+            let (token, proof) = cipher.generate();
+            assert_eq!(cipher.verify_detailed(&token, &proof), Ok(()));
+
+            let (other_token, _) = cipher.generate();
+            assert_eq!(
+                cipher.verify_detailed(&other_token, &proof),
+                Err(CsrfVerifyError::Invalid)
+            );
+            let mut tampered = URL_SAFE_NO_PAD.decode(&proof).unwrap();
+            tampered[*nonce_len] ^= 1;
+            assert_eq!(
+                cipher.verify_detailed(&token, &URL_SAFE_NO_PAD.encode(tampered)),
+                Err(CsrfVerifyError::Invalid)
+            );
+            let mut tampered = URL_SAFE_NO_PAD.decode(&proof).unwrap();
+            *tampered.last_mut().unwrap() ^= 1;
+            assert_eq!(
+                cipher.verify_detailed(&token, &URL_SAFE_NO_PAD.encode(tampered)),
+                Err(CsrfVerifyError::Invalid)
+            );
+            assert_eq!(
+                other_key.verify_detailed(&token, &proof),
+                Err(CsrfVerifyError::Invalid)
+            );
+
+            for (token, proof) in [
+                (&token[..], "not base64!"),
+                ("not base64!", &proof[..]),
+                (&token[..], &proof[..proof.len() - 4]),
+                (&token[..token.len() - 4], &proof[..]),
+            ] {
+                assert_eq!(
+                    cipher.verify_detailed(token, proof),
+                    Err(CsrfVerifyError::Malformed)
+                );
+            }
+            assert_eq!(
+                URL_SAFE_NO_PAD.decode(&proof).unwrap().len(),
+                nonce_len + 32 + TAG_LEN
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_malformed_proof() {
+        for (cipher, nonce_len) in ciphers([0u8; 32]) {
+            let (token, proof) = cipher.generate();
+            let proof = URL_SAFE_NO_PAD.decode(&proof).unwrap();
+            for len in [
+                0,
+                1,
+                nonce_len,
+                nonce_len + TAG_LEN - 1,
+                nonce_len + TAG_LEN,
+                proof.len() - 1,
+            ] {
+                assert!(!cipher.verify(&token, &URL_SAFE_NO_PAD.encode(&proof[..len])));
+            }
+            let mut extended = proof.clone();
+            extended.push(0);
+            assert!(!cipher.verify(&token, &URL_SAFE_NO_PAD.encode(&extended)));
+
+            let proof = URL_SAFE_NO_PAD.encode(&proof);
+            assert!(!cipher.verify("", &proof));
+            assert!(!cipher.verify(&URL_SAFE_NO_PAD.encode([0u8; 4]), &proof));
+            assert!(!cipher.verify(&token[..token.len() - 2], &proof));
+        }
+    }
+
+    #[test]
+    fn test_nonces() {
+        for (cipher, nonce_len) in ciphers([0u8; 32]) {
+            let nonces: std::collections::HashSet<_> = (0..64)
+                .map(|_| {
+                    let (_, proof) = cipher.generate();
+                    let proof = URL_SAFE_NO_PAD.decode(proof).unwrap();
+                    assert_eq!(proof.len(), nonce_len + 32 + TAG_LEN);
+                    proof[..nonce_len].to_vec()
+                })
+                .collect();
+            assert_eq!(nonces.len(), 64);
+
+            let bytes = cipher.random_bytes(nonce_len);
+            assert_eq!(bytes.len(), nonce_len);
+            assert_ne!(bytes, cipher.random_bytes(nonce_len));
+        }
+    }
+}
@@ -1,27 +1,53 @@
 use aead::generic_array::GenericArray;
-use aead::{Aead, KeyInit};
+use aead::KeyInit;
 use aes_gcm::Aes256Gcm;
-use base64::engine::general_purpose::URL_SAFE_NO_PAD;
-use base64::Engine;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::ZeroizeOnDrop;
 
-use super::CsrfCipher;
+use super::{aead_proof, CsrfCipher, CsrfVerifyError};
+
+const NONCE_LEN: usize = 12;
 
 /// CSRF protection implementation that uses AES-GCM.
+///
+/// The key material is wiped from memory when the cipher is dropped.
+#[derive(Clone)]
 pub struct AesGcmCipher {
-    aead_key: [u8; 32],
+    aead: Aes256Gcm,
     token_size: usize,
 }
 
+// The inner AEAD wipes its AES key schedule and GHASH key on drop through the `zeroize`
+// features of `aes` and `polyval`, which the `aes-gcm-cipher` feature turns on.
+impl ZeroizeOnDrop for AesGcmCipher {}
+
 impl AesGcmCipher {
     /// Given an aead key, return an `AesGcmCipher` instance.
     #[inline]
     pub fn new(aead_key: [u8; 32]) -> Self {
         Self {
-            aead_key,
+            aead: Aes256Gcm::new(&GenericArray::from(aead_key)),
             token_size: 32,
         }
     }
 
+    /// Create an `AesGcmCipher` with a key derived from `passphrase` and `salt` with HKDF-SHA256.
+    ///
+    /// The passphrase must be a long random secret, e.g. read from an environment variable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `passphrase` is shorter than 32 bytes.
+    #[inline]
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Self {
+        Self::new(crate::kdf::derive_key(
+            passphrase,
+            salt,
+            b"salvo-csrf aes-gcm-cipher",
+        ))
+    }
+
     /// Sets the length of the token.
     #[inline]
     pub fn token_size(mut self, token_size: usize) -> Self {
@@ -29,38 +55,32 @@ impl AesGcmCipher {
         self.token_size = token_size;
         self
     }
-
-    #[inline]
-    fn aead(&self) -> Aes256Gcm {
-        let key = GenericArray::clone_from_slice(&self.aead_key);
-        Aes256Gcm::new(&key)
-    }
 }
 
 impl CsrfCipher for AesGcmCipher {
     fn verify(&self, token: &str, proof: &str) -> bool {
-        if let (Ok(token), Ok(proof)) = (
-            URL_SAFE_NO_PAD.decode(token.as_bytes()),
-            URL_SAFE_NO_PAD.decode(proof.as_bytes()),
-        ) {
-            if token.len() < 8 || proof.len() < 20 {
-                false
-            } else {
-                let nonce = GenericArray::from_slice(&proof[0..12]);
-                let aead = self.aead();
-                aead.decrypt(nonce, &proof[12..]).map(|p| p == token).unwrap_or(false)
-            }
-        } else {
-            false
-        }
+        self.verify_with_aad(token, proof, b"")
+    }
+    fn supports_aad(&self) -> bool {
+        true
+    }
+    fn verify_with_aad(&self, token: &str, proof: &str, aad: &[u8]) -> bool {
+        aead_proof::open(&self.aead, NONCE_LEN, token, proof, aad).is_ok()
+    }
+    fn verify_detailed(&self, token: &str, proof: &str) -> Result<(), CsrfVerifyError> {
+        aead_proof::open(&self.aead, NONCE_LEN, token, proof, b"")
     }
     fn generate(&self) -> (String, String) {
-        let token = self.random_bytes(self.token_size);
-        let aead = self.aead();
-        let mut proof = self.random_bytes(12);
-        let nonce = GenericArray::from_slice(&proof);
-        proof.append(&mut aead.encrypt(nonce, token.as_slice()).expect("encryption failed"));
-        (URL_SAFE_NO_PAD.encode(token), URL_SAFE_NO_PAD.encode(proof))
+        aead_proof::seal(&self.aead, NONCE_LEN, &mut OsRng, self.token_size, b"")
+    }
+    fn generate_with_rng(&self, rng: &mut dyn RngCore) -> (String, String) {
+        aead_proof::seal(&self.aead, NONCE_LEN, rng, self.token_size, b"")
+    }
+    fn generate_with_aad(&self, aad: &[u8]) -> (String, String) {
+        aead_proof::seal(&self.aead, NONCE_LEN, &mut OsRng, self.token_size, aad)
+    }
+    fn random_bytes(&self, len: usize) -> Vec<u8> {
+        self.random_bytes_with_rng(&mut OsRng, len)
     }
 }
 
@@ -86,4 +106,107 @@ mod tests {
         let invalid_token = URL_SAFE_NO_PAD.encode(vec![0u8; token.len()]);
         assert!(!cipher.verify(&invalid_token, &proof));
     }
+
+    #[test]
+    fn test_generate_with_seeded_rng() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let cipher = AesGcmCipher::new([0u8; 32]);
+        let first = cipher.generate_with_rng(&mut StdRng::seed_from_u64(42));
+        let second = cipher.generate_with_rng(&mut StdRng::seed_from_u64(42));
+        assert_eq!(first, second);
+        assert!(cipher.verify(&first.0, &first.1));
+    }
+
+    #[test]
+    fn test_verify_combined_ciphertext() {
+        use aead::generic_array::GenericArray;
+        use aead::{Aead, KeyInit};
+        use aes_gcm::Aes256Gcm;
+
+        let cipher = AesGcmCipher::new([1u8; 32]);
+        let token = [7u8; 32];
+        let mut proof = vec![3u8; 12];
+        let aead = Aes256Gcm::new(&GenericArray::from([1u8; 32]));
+        let mut sealed = aead
+            .encrypt(GenericArray::from_slice(&proof), token.as_slice())
+            .unwrap();
+        proof.append(&mut sealed);
+        assert!(cipher.verify(
+            &URL_SAFE_NO_PAD.encode(token),
+            &URL_SAFE_NO_PAD.encode(proof)
+        ));
+    }
+
+    #[test]
+    fn test_custom_token_size() {
+        let cipher = AesGcmCipher::new([0u8; 32]).token_size(64);
+        let (token, proof) = cipher.generate();
+        assert_eq!(URL_SAFE_NO_PAD.decode(&token).unwrap().len(), 64);
+        assert!(cipher.verify(&token, &proof));
+    }
+
+    #[test]
+    #[should_panic(expected = "length must be larger than 8")]
+    fn test_too_short_token_size() {
+        AesGcmCipher::new([0u8; 32]).token_size(4);
+    }
+
+    #[test]
+    fn test_from_passphrase() {
+        let passphrase = "0123456789abcdef0123456789abcdef";
+        let cipher = AesGcmCipher::from_passphrase(passphrase, b"salt");
+        let (token, proof) = cipher.generate();
+        assert!(AesGcmCipher::from_passphrase(passphrase, b"salt").verify(&token, &proof));
+        assert!(!AesGcmCipher::from_passphrase(passphrase, b"pepper").verify(&token, &proof));
+    }
+
+    #[test]
+    fn test_associated_data() {
+        let cipher = AesGcmCipher::new([0u8; 32]);
+        assert!(cipher.supports_aad());
+
+        let (token, proof) = cipher.generate_with_aad(b"X");
+        assert!(cipher.verify_with_aad(&token, &proof, b"X"));
+        assert!(!cipher.verify_with_aad(&token, &proof, b"Y"));
+        assert!(!cipher.verify(&token, &proof));
+
+        let (token, proof) = cipher.generate();
+        assert!(cipher.verify_with_aad(&token, &proof, b""));
+        assert!(!cipher.verify_with_aad(&token, &proof, b"X"));
+    }
+
+    /// Compares the cached AEAD with one initialized for every call, run with
+    /// `cargo test --release --features aes-gcm-cipher -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "timing comparison"]
+    fn test_cached_aead_timing() {
+        use std::time::Instant;
+
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        const ROUNDS: usize = 100_000;
+        let key = [0u8; 32];
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let cipher = AesGcmCipher::new(key);
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            let (token, proof) = cipher.generate_with_rng(&mut rng);
+            assert!(cipher.verify(&token, &proof));
+        }
+        let cached = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            let (token, proof) = AesGcmCipher::new(key).generate_with_rng(&mut rng);
+            assert!(AesGcmCipher::new(key).verify(&token, &proof));
+        }
+        let initialized = start.elapsed();
+
+        println!("{ROUNDS} rounds: cached {cached:?}, initialized per call {initialized:?}");
+        assert!(cached < initialized);
+    }
 }
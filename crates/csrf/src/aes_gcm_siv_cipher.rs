@@ -0,0 +1,176 @@
+use aead::generic_array::GenericArray;
+use aead::KeyInit;
+use aes_gcm_siv::Aes256GcmSiv;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::ZeroizeOnDrop;
+
+use super::{aead_proof, CsrfCipher, CsrfVerifyError};
+
+const NONCE_LEN: usize = 12;
+
+/// CSRF protection implementation that uses AES-GCM-SIV.
+///
+/// It works like [`AesGcmCipher`](crate::AesGcmCipher), but a repeated nonce only reveals
+/// that the same token was sealed twice instead of breaking the key. Prefer it when a single
+/// key issues a very large number of tokens, where random 96-bit nonces risk colliding,
+/// at the cost of slightly slower sealing.
+///
+/// The key material is wiped from memory when the cipher is dropped.
+#[derive(Clone)]
+pub struct AesGcmSivCipher {
+    aead: Aes256GcmSiv,
+    token_size: usize,
+}
+
+// The inner AEAD wipes its AES key schedule and the per-nonce POLYVAL key on drop through the
+// `zeroize` features of `aes` and `polyval`, which the `aes-gcm-siv-cipher` feature turns on
+// like `aes-gcm-cipher` does. The derived per-nonce key bytes are always wiped.
+impl ZeroizeOnDrop for AesGcmSivCipher {}
+
+impl AesGcmSivCipher {
+    /// Given an aead key, return an `AesGcmSivCipher` instance.
+    #[inline]
+    pub fn new(aead_key: [u8; 32]) -> Self {
+        Self {
+            aead: Aes256GcmSiv::new(&GenericArray::from(aead_key)),
+            token_size: 32,
+        }
+    }
+
+    /// Create an `AesGcmSivCipher` with a key derived from `passphrase` and `salt` with HKDF-SHA256.
+    ///
+    /// The passphrase must be a long random secret, e.g. read from an environment variable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `passphrase` is shorter than 32 bytes.
+    #[inline]
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Self {
+        Self::new(crate::kdf::derive_key(
+            passphrase,
+            salt,
+            b"salvo-csrf aes-gcm-siv-cipher",
+        ))
+    }
+
+    /// Sets the length of the token.
+    #[inline]
+    pub fn token_size(mut self, token_size: usize) -> Self {
+        assert!(token_size >= 8, "length must be larger than 8");
+        self.token_size = token_size;
+        self
+    }
+}
+
+impl CsrfCipher for AesGcmSivCipher {
+    fn verify(&self, token: &str, proof: &str) -> bool {
+        self.verify_with_aad(token, proof, b"")
+    }
+    fn supports_aad(&self) -> bool {
+        true
+    }
+    fn verify_with_aad(&self, token: &str, proof: &str, aad: &[u8]) -> bool {
+        aead_proof::open(&self.aead, NONCE_LEN, token, proof, aad).is_ok()
+    }
+    fn verify_detailed(&self, token: &str, proof: &str) -> Result<(), CsrfVerifyError> {
+        aead_proof::open(&self.aead, NONCE_LEN, token, proof, b"")
+    }
+    fn generate(&self) -> (String, String) {
+        aead_proof::seal(&self.aead, NONCE_LEN, &mut OsRng, self.token_size, b"")
+    }
+    fn generate_with_rng(&self, rng: &mut dyn RngCore) -> (String, String) {
+        aead_proof::seal(&self.aead, NONCE_LEN, rng, self.token_size, b"")
+    }
+    fn generate_with_aad(&self, aad: &[u8]) -> (String, String) {
+        aead_proof::seal(&self.aead, NONCE_LEN, &mut OsRng, self.token_size, aad)
+    }
+    fn random_bytes(&self, len: usize) -> Vec<u8> {
+        self.random_bytes_with_rng(&mut OsRng, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+
+    use super::AesGcmSivCipher;
+    use super::CsrfCipher;
+
+    #[test]
+    fn test_aes_gcm_siv_cipher() {
+        let aead_key = [0u8; 32];
+        let cipher = AesGcmSivCipher::new(aead_key);
+
+        let (token, proof) = cipher.generate();
+        assert!(cipher.verify(&token, &proof));
+
+        let invalid_proof = URL_SAFE_NO_PAD.encode(vec![0u8; proof.len()]);
+        assert!(!cipher.verify(&token, &invalid_proof));
+
+        let invalid_token = URL_SAFE_NO_PAD.encode(vec![0u8; token.len()]);
+        assert!(!cipher.verify(&invalid_token, &proof));
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let (token, proof) = AesGcmSivCipher::new([1u8; 32]).generate();
+        assert!(!AesGcmSivCipher::new([2u8; 32]).verify(&token, &proof));
+    }
+
+    #[test]
+    fn test_generate_with_seeded_rng() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let cipher = AesGcmSivCipher::new([0u8; 32]);
+        let first = cipher.generate_with_rng(&mut StdRng::seed_from_u64(42));
+        let second = cipher.generate_with_rng(&mut StdRng::seed_from_u64(42));
+        assert_eq!(first, second);
+        assert!(cipher.verify(&first.0, &first.1));
+    }
+
+    #[test]
+    fn test_verify_combined_ciphertext() {
+        use aead::generic_array::GenericArray;
+        use aead::{Aead, KeyInit};
+        use aes_gcm_siv::Aes256GcmSiv;
+
+        let cipher = AesGcmSivCipher::new([1u8; 32]);
+        let token = [7u8; 32];
+        let mut proof = vec![3u8; 12];
+        let aead = Aes256GcmSiv::new(&GenericArray::from([1u8; 32]));
+        let mut sealed = aead
+            .encrypt(GenericArray::from_slice(&proof), token.as_slice())
+            .unwrap();
+        proof.append(&mut sealed);
+        assert!(cipher.verify(
+            &URL_SAFE_NO_PAD.encode(token),
+            &URL_SAFE_NO_PAD.encode(proof)
+        ));
+    }
+
+    #[test]
+    fn test_custom_token_size() {
+        let cipher = AesGcmSivCipher::new([0u8; 32]).token_size(64);
+        let (token, proof) = cipher.generate();
+        assert_eq!(URL_SAFE_NO_PAD.decode(&token).unwrap().len(), 64);
+        assert!(cipher.verify(&token, &proof));
+    }
+
+    #[test]
+    #[should_panic(expected = "length must be larger than 8")]
+    fn test_too_short_token_size() {
+        AesGcmSivCipher::new([0u8; 32]).token_size(4);
+    }
+
+    #[test]
+    fn test_from_passphrase() {
+        let passphrase = "0123456789abcdef0123456789abcdef";
+        let cipher = AesGcmSivCipher::from_passphrase(passphrase, b"salt");
+        let (token, proof) = cipher.generate();
+        assert!(AesGcmSivCipher::from_passphrase(passphrase, b"salt").verify(&token, &proof));
+        assert!(!AesGcmSivCipher::from_passphrase(passphrase, b"pepper").verify(&token, &proof));
+    }
+}
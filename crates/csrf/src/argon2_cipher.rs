@@ -0,0 +1,145 @@
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
+
+use super::CsrfCipher;
+
+/// CSRF protection implementation that uses Argon2id.
+///
+/// The proof is the PHC string of the hash, so proofs stay verifiable after the costs change.
+#[derive(Clone)]
+pub struct Argon2Cipher {
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    token_size: usize,
+}
+impl Default for Argon2Cipher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Argon2Cipher {
+    /// Create a new `Argon2Cipher` with the default costs of the `argon2` crate.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+            token_size: 32,
+        }
+    }
+
+    /// Sets the length of the token.
+    #[inline]
+    pub fn token_size(mut self, token_size: usize) -> Self {
+        assert!(token_size >= 8, "length must be larger than 8");
+        self.token_size = token_size;
+        self
+    }
+
+    /// Sets the memory cost in KiB.
+    #[inline]
+    pub fn memory_cost(mut self, m_cost: u32) -> Self {
+        self.m_cost = m_cost;
+        self.params();
+        self
+    }
+
+    /// Sets the number of iterations.
+    #[inline]
+    pub fn time_cost(mut self, t_cost: u32) -> Self {
+        self.t_cost = t_cost;
+        self.params();
+        self
+    }
+
+    /// Sets the degree of parallelism.
+    #[inline]
+    pub fn parallelism(mut self, p_cost: u32) -> Self {
+        self.p_cost = p_cost;
+        self.params();
+        self
+    }
+
+    fn params(&self) -> Params {
+        Params::new(self.m_cost, self.t_cost, self.p_cost, None).expect("invalid argon2 params")
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params())
+    }
+}
+
+impl CsrfCipher for Argon2Cipher {
+    fn verify(&self, token: &str, proof: &str) -> bool {
+        if let (Ok(token), Ok(proof)) = (
+            URL_SAFE_NO_PAD.decode(token.as_bytes()),
+            URL_SAFE_NO_PAD.decode(proof.as_bytes()),
+        ) {
+            let Ok(proof) = String::from_utf8(proof) else {
+                return false;
+            };
+            let Ok(hash) = PasswordHash::new(&proof) else {
+                return false;
+            };
+            self.argon2().verify_password(&token, &hash).is_ok()
+        } else {
+            false
+        }
+    }
+    fn min_token_len(&self) -> usize {
+        base64::encoded_len(self.token_size, false).unwrap_or(usize::MAX)
+    }
+    fn generate(&self) -> (String, String) {
+        self.generate_with_rng(&mut rand::thread_rng())
+    }
+    fn generate_with_rng(&self, rng: &mut dyn RngCore) -> (String, String) {
+        let token = self.random_bytes_with_rng(rng, self.token_size);
+        let salt = SaltString::encode_b64(&self.random_bytes_with_rng(rng, 16))
+            .expect("invalid argon2 salt");
+        let proof = self
+            .argon2()
+            .hash_password(&token, &salt)
+            .expect("argon2 hash failed")
+            .to_string();
+        (URL_SAFE_NO_PAD.encode(token), URL_SAFE_NO_PAD.encode(proof))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher() -> Argon2Cipher {
+        Argon2Cipher::new().memory_cost(64).time_cost(1)
+    }
+
+    #[test]
+    fn test_argon2_cipher() {
+        let cipher = cipher();
+        let (token, proof) = cipher.generate();
+        assert!(cipher.verify(&token, &proof));
+
+        let (other_token, other_proof) = cipher.generate();
+        assert!(!cipher.verify(&token, &other_proof));
+        assert!(!cipher.verify(&other_token, &proof));
+        assert!(!cipher.verify(&token, "invalid"));
+    }
+
+    #[test]
+    fn test_argon2_cipher_verify_after_cost_change() {
+        let (token, proof) = cipher().generate();
+        assert!(cipher().time_cost(2).verify(&token, &proof));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid argon2 params")]
+    fn test_argon2_cipher_invalid_params() {
+        Argon2Cipher::new().time_cost(0);
+    }
+}
@@ -1,9 +1,14 @@
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
+use rand::RngCore;
 
 use super::CsrfCipher;
 
 /// CSRF protection implementation that uses bcrypt.
+///
+/// The proof is the bcrypt hash string encoded in url-safe base64. Proofs generated by earlier
+/// versions, the hash string itself with `/` replaced by `_`, are still verified.
+#[derive(Clone)]
 pub struct BcryptCipher {
     cost: u32,
     token_size: usize,
@@ -27,7 +32,10 @@ impl BcryptCipher {
     /// Sets the length of the token.
     #[inline]
     pub fn token_size(mut self, token_size: usize) -> Self {
-        assert!((1..=72).contains(&token_size), "length must be between 1 and 72");
+        assert!(
+            (1..=72).contains(&token_size),
+            "length must be between 1 and 72"
+        );
         self.token_size = token_size;
         self
     }
@@ -43,18 +51,39 @@ impl BcryptCipher {
 
 impl CsrfCipher for BcryptCipher {
     fn verify(&self, token: &str, proof: &str) -> bool {
-        if let Ok(token) = URL_SAFE_NO_PAD.decode(token.as_bytes()) {
+        let Ok(token) = URL_SAFE_NO_PAD.decode(token.as_bytes()) else {
+            return false;
+        };
+        if proof.starts_with('$') {
             let proof = proof.replace('_', "/").replace('-', "+");
-            bcrypt::verify(token, &proof).unwrap_or(false)
-        } else {
-            false
+            return bcrypt::verify(token, &proof).unwrap_or(false);
+        }
+        match URL_SAFE_NO_PAD.decode(proof.as_bytes()) {
+            Ok(proof) => self.verify_raw(&token, &proof),
+            Err(_) => false,
+        }
+    }
+    fn verify_raw(&self, token: &[u8], proof: &[u8]) -> bool {
+        match std::str::from_utf8(proof) {
+            Ok(proof) => bcrypt::verify(token, proof).unwrap_or(false),
+            Err(_) => false,
         }
     }
+    fn min_token_len(&self) -> usize {
+        base64::encoded_len(self.token_size, false).unwrap_or(usize::MAX)
+    }
     fn generate(&self) -> (String, String) {
-        let token = self.random_bytes(self.token_size);
-        let proof = bcrypt::hash(&token, self.cost).expect("Call bcrypt hash get error result.").replace('+', "/").replace('/', "_");
-
-        (URL_SAFE_NO_PAD.encode(token), proof)
+        self.generate_with_rng(&mut rand::thread_rng())
+    }
+    fn generate_with_rng(&self, rng: &mut dyn RngCore) -> (String, String) {
+        let token = self.random_bytes_with_rng(rng, self.token_size);
+        let mut salt = [0u8; 16];
+        rng.fill_bytes(&mut salt);
+        let proof = bcrypt::hash_with_salt(&token, self.cost, salt)
+            .expect("Call bcrypt hash get error result.")
+            .format_for_version(bcrypt::Version::TwoB);
+
+        (URL_SAFE_NO_PAD.encode(token), URL_SAFE_NO_PAD.encode(proof))
     }
 }
 
@@ -96,6 +125,26 @@ mod tests {
         BcryptCipher::new().cost(32);
     }
 
+    #[test]
+    fn test_bcrypt_cipher_with_max_token_size() {
+        let cipher = BcryptCipher::new().token_size(72);
+        let (token, proof) = cipher.generate();
+        assert_eq!(URL_SAFE_NO_PAD.decode(&token).unwrap().len(), 72);
+        assert!(cipher.verify(&token, &proof));
+    }
+
+    #[test]
+    fn test_bcrypt_cipher_generate_with_seeded_rng() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let cipher = BcryptCipher::new();
+        let first = cipher.generate_with_rng(&mut StdRng::seed_from_u64(42));
+        let second = cipher.generate_with_rng(&mut StdRng::seed_from_u64(42));
+        assert_eq!(first, second);
+        assert!(cipher.verify(&first.0, &first.1));
+    }
+
     #[test]
     fn test_bcrypt_cipher_verify_and_generate() {
         let cipher = BcryptCipher::new();
@@ -103,6 +152,28 @@ mod tests {
         assert!(cipher.verify(&token, &proof));
     }
 
+    #[test]
+    fn test_bcrypt_cipher_verify_raw() {
+        let cipher = BcryptCipher::new().cost(4);
+        let (token, proof) = cipher.generate();
+        let raw_token = URL_SAFE_NO_PAD.decode(&token).unwrap();
+        let raw_proof = URL_SAFE_NO_PAD.decode(&proof).unwrap();
+        assert!(raw_proof.starts_with(b"$2b$04$"));
+        assert!(cipher.verify_raw(&raw_token, &raw_proof));
+        assert!(!cipher.verify_raw(&raw_token[1..], &raw_proof));
+        assert!(!cipher.verify_raw(&raw_token, proof.as_bytes()));
+    }
+
+    #[test]
+    fn test_bcrypt_cipher_verify_legacy_proof() {
+        let cipher = BcryptCipher::new().cost(4);
+        let (token, proof) = cipher.generate();
+        let hash = String::from_utf8(URL_SAFE_NO_PAD.decode(&proof).unwrap()).unwrap();
+        assert!(cipher.verify(&token, &hash.replace('/', "_")));
+        let (other_token, _) = cipher.generate();
+        assert!(!cipher.verify(&other_token, &hash.replace('/', "_")));
+    }
+
     #[test]
     fn test_bcrypt_cipher_verify_invalid_token() {
         let cipher = BcryptCipher::new();
@@ -0,0 +1,138 @@
+#[cfg(feature = "binding")]
+use std::sync::Arc;
+
+#[cfg(feature = "binding")]
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+#[cfg(feature = "binding")]
+use base64::Engine;
+#[cfg(feature = "binding")]
+use hmac::{Hmac, Mac};
+use salvo_core::Depot;
+#[cfg(feature = "binding")]
+use sha2::Sha256;
+
+#[cfg(feature = "binding")]
+use crate::BindingHook;
+
+/// Separates the proof from the binding tag in stored proofs, it never appears in proofs
+/// generated by ciphers.
+const SEPARATOR: char = '~';
+
+/// The hook reading the bound value and the server-held key its tags are computed with.
+#[cfg(feature = "binding")]
+#[derive(Clone)]
+pub(crate) struct Binding {
+    key: Arc<[u8; 32]>,
+    hook: BindingHook,
+}
+
+#[cfg(feature = "binding")]
+impl Binding {
+    pub(crate) fn new(key: [u8; 32], hook: BindingHook) -> Self {
+        Self {
+            key: Arc::new(key),
+            hook,
+        }
+    }
+
+    /// Returns the current bound value.
+    pub(crate) fn value(&self, depot: &Depot) -> Option<Vec<u8>> {
+        (self.hook)(depot)
+    }
+
+    /// Appends the tag binding `token` to `value` to a proof.
+    pub(crate) fn bind_proof(&self, proof: &str, token: &str, value: &[u8]) -> String {
+        format!("{proof}{SEPARATOR}{}", binding_tag(&self.key, token, value))
+    }
+
+    /// Returns whether the tag of a stored proof matches the current bound value.
+    pub(crate) fn matches(&self, tag: Option<&str>, token: &str, value: Option<&[u8]>) -> bool {
+        matches(tag, token, value.map(|value| (&*self.key, value)))
+    }
+}
+
+/// Without the `binding` feature no binding can be configured.
+#[cfg(not(feature = "binding"))]
+#[derive(Clone)]
+pub(crate) enum Binding {}
+
+#[cfg(not(feature = "binding"))]
+impl Binding {
+    pub(crate) fn value(&self, _depot: &Depot) -> Option<Vec<u8>> {
+        match *self {}
+    }
+
+    pub(crate) fn bind_proof(&self, _proof: &str, _token: &str, _value: &[u8]) -> String {
+        match *self {}
+    }
+
+    pub(crate) fn matches(&self, _tag: Option<&str>, _token: &str, _value: Option<&[u8]>) -> bool {
+        match *self {}
+    }
+}
+
+/// Splits a stored proof into the cipher proof and the binding tag.
+pub(crate) fn split_proof(proof: &str) -> (&str, Option<&str>) {
+    match proof.rsplit_once(SEPARATOR) {
+        Some((proof, tag)) => (proof, Some(tag)),
+        None => (proof, None),
+    }
+}
+
+/// Returns whether the tag of a stored proof matches the current key and bound value.
+#[cfg(feature = "binding")]
+fn matches(tag: Option<&str>, token: &str, binding: Option<(&[u8; 32], &[u8])>) -> bool {
+    match (tag, binding) {
+        (None, None) => true,
+        (Some(tag), Some((key, value))) => URL_SAFE_NO_PAD
+            .decode(tag)
+            .is_ok_and(|tag| binding_mac(key, token, value).verify_slice(&tag).is_ok()),
+        _ => false,
+    }
+}
+
+/// Keyed with a server-held key, so the tag of a value can not be computed from the value
+/// alone, even when the store can be written by the client.
+#[cfg(feature = "binding")]
+fn binding_mac(key: &[u8; 32], token: &str, value: &[u8]) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("any key size is valid");
+    mac.update(token.as_bytes());
+    mac.update(&[0]);
+    mac.update(value);
+    mac
+}
+
+#[cfg(feature = "binding")]
+fn binding_tag(key: &[u8; 32], token: &str, value: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(binding_mac(key, token, value).finalize().into_bytes())
+}
+
+#[cfg(all(test, feature = "binding"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind_and_split_proof() {
+        let binding = Binding::new([1; 32], Arc::new(|_: &Depot| None));
+        let proof = binding.bind_proof("$2b$08$abc.def", "token", b"alice");
+        let (cipher_proof, tag) = split_proof(&proof);
+        assert_eq!(cipher_proof, "$2b$08$abc.def");
+        assert!(binding.matches(tag, "token", Some(b"alice")));
+        assert!(!binding.matches(tag, "token", Some(b"bob")));
+        assert!(!binding.matches(tag, "other", Some(b"alice")));
+        assert!(!binding.matches(tag, "token", None));
+        assert!(binding.matches(None, "token", None));
+        assert!(!binding.matches(None, "token", Some(b"alice")));
+        assert!(!binding.matches(Some("not base64!"), "token", Some(b"alice")));
+    }
+
+    #[test]
+    fn test_binding_tag_is_keyed() {
+        let binding = Binding::new([1; 32], Arc::new(|_: &Depot| None));
+        let other_key = Binding::new([2; 32], Arc::new(|_: &Depot| None));
+        let proof = other_key.bind_proof("proof", "token", b"alice");
+        let (_, tag) = split_proof(&proof);
+        assert!(other_key.matches(tag, "token", Some(b"alice")));
+        assert!(!binding.matches(tag, "token", Some(b"alice")));
+    }
+}
@@ -0,0 +1,138 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
+use zeroize::{ZeroizeOnDrop, Zeroizing};
+
+use super::CsrfCipher;
+
+const HASH_LEN: usize = blake3::OUT_LEN;
+
+/// A CSRF protection implementation that uses BLAKE3 keyed hashing.
+///
+/// It works like [`HmacCipher`](crate::HmacCipher), the proof is the keyed hash of the token,
+/// but is faster to compute.
+///
+/// The key is wiped from memory when the cipher is dropped.
+#[derive(Clone)]
+pub struct Blake3Cipher {
+    key: Zeroizing<[u8; 32]>,
+    token_size: usize,
+}
+
+// `key` is wiped on drop by `Zeroizing`.
+impl ZeroizeOnDrop for Blake3Cipher {}
+
+impl Blake3Cipher {
+    /// Given a key, return a `Blake3Cipher` instance.
+    #[inline]
+    pub fn new(key: [u8; 32]) -> Self {
+        Self {
+            key: Zeroizing::new(key),
+            token_size: 32,
+        }
+    }
+
+    /// Create a `Blake3Cipher` with a key derived from `passphrase` and `salt` with HKDF-SHA256.
+    ///
+    /// The passphrase must be a long random secret, e.g. read from an environment variable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `passphrase` is shorter than 32 bytes.
+    #[inline]
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Self {
+        Self::new(crate::kdf::derive_key(
+            passphrase,
+            salt,
+            b"salvo-csrf blake3-cipher",
+        ))
+    }
+
+    /// Sets the length of the token.
+    #[inline]
+    pub fn token_size(mut self, token_size: usize) -> Self {
+        assert!(token_size >= 8, "length must be larger than 8");
+        self.token_size = token_size;
+        self
+    }
+}
+
+impl CsrfCipher for Blake3Cipher {
+    fn verify(&self, token: &str, proof: &str) -> bool {
+        if let (Ok(token), Ok(proof)) = (
+            URL_SAFE_NO_PAD.decode(token.as_bytes()),
+            URL_SAFE_NO_PAD.decode(proof.as_bytes()),
+        ) {
+            self.verify_raw(&token, &proof)
+        } else {
+            false
+        }
+    }
+    fn verify_raw(&self, token: &[u8], proof: &[u8]) -> bool {
+        let Ok(proof) = <[u8; HASH_LEN]>::try_from(proof) else {
+            return false;
+        };
+        // `blake3::Hash` compares in constant time.
+        token.len() == self.token_size
+            && blake3::keyed_hash(&self.key, token) == blake3::Hash::from(proof)
+    }
+    fn generate(&self) -> (String, String) {
+        self.generate_with_rng(&mut rand::thread_rng())
+    }
+    fn generate_with_rng(&self, rng: &mut dyn RngCore) -> (String, String) {
+        let token = self.random_bytes_with_rng(rng, self.token_size);
+        let proof = blake3::keyed_hash(&self.key, &token);
+        (
+            URL_SAFE_NO_PAD.encode(token),
+            URL_SAFE_NO_PAD.encode(proof.as_bytes()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blake3_cipher() {
+        let cipher = Blake3Cipher::new([1u8; 32]);
+        let (token, proof) = cipher.generate();
+        assert!(cipher.verify(&token, &proof));
+
+        let (other_token, other_proof) = cipher.generate();
+        assert!(!cipher.verify(&token, &other_proof));
+        assert!(!cipher.verify(&other_token, &proof));
+    }
+
+    #[test]
+    fn test_blake3_cipher_wrong_key() {
+        let (token, proof) = Blake3Cipher::new([1u8; 32]).generate();
+        assert!(!Blake3Cipher::new([2u8; 32]).verify(&token, &proof));
+    }
+
+    #[test]
+    fn test_rejects_short_input() {
+        let cipher = Blake3Cipher::new([1u8; 32]);
+        let (token, proof) = cipher.generate();
+        assert!(!cipher.verify(&token, &proof[..proof.len() - 2]));
+        assert!(!cipher.verify(&token[..token.len() - 2], &proof));
+        assert!(!cipher.verify("", &proof));
+        assert!(!cipher.verify(&token, ""));
+        assert!(!cipher.verify(&token, "invalid base64!"));
+    }
+
+    #[test]
+    fn test_from_passphrase() {
+        let passphrase = "0123456789abcdef0123456789abcdef";
+        let cipher = Blake3Cipher::from_passphrase(passphrase, b"salt");
+        let (token, proof) = cipher.generate();
+        assert!(Blake3Cipher::from_passphrase(passphrase, b"salt").verify(&token, &proof));
+        assert!(!Blake3Cipher::from_passphrase(passphrase, b"pepper").verify(&token, &proof));
+    }
+
+    #[test]
+    #[should_panic(expected = "length must be larger than 8")]
+    fn test_too_short_token_size() {
+        Blake3Cipher::new([0u8; 32]).token_size(4);
+    }
+}
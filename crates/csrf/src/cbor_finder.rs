@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use salvo_core::{async_trait, Request};
+use serde_json::Value;
+
+use crate::finder::read_body;
+use crate::CsrfTokenFinder;
+
+/// Find token from a CBOR request body, e.g. `{"csrf-token": "..."}` encoded as CBOR.
+///
+/// Only bodies with the `application/cbor` content type or a `+cbor` suffix are read.
+/// Bodies larger than [`max_body_size`](Self::max_body_size) are not read, so the token is
+/// not found and the request is rejected. The body stays available to later handlers.
+#[derive(Clone, Debug)]
+pub struct CborFinder {
+    field_name: String,
+    max_body_size: usize,
+}
+impl CborFinder {
+    /// Create new `CborFinder`.
+    #[inline]
+    pub fn new(field_name: impl Into<String>) -> Self {
+        Self {
+            field_name: field_name.into(),
+            max_body_size: 16 * 1024,
+        }
+    }
+
+    /// Sets the maximum size in bytes of the body read to find the token, defaults to 16 KiB.
+    #[inline]
+    pub fn max_body_size(mut self, size: usize) -> Self {
+        self.max_body_size = size;
+        self
+    }
+}
+#[async_trait]
+impl CsrfTokenFinder for CborFinder {
+    async fn find_token(&self, req: &mut Request) -> Option<String> {
+        let ctype = req.content_type()?;
+        if ctype.subtype() != "cbor" && ctype.suffix().map(|s| s.as_str()) != Some("cbor") {
+            return None;
+        }
+        let payload = read_body(req, self.max_body_size).await?;
+        let data = ciborium::from_reader::<HashMap<String, Value>, _>(payload).ok()?;
+        data.get(&self.field_name)?.as_str().map(ToOwned::to_owned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::http::header::CONTENT_TYPE;
+    use salvo_core::test::TestClient;
+
+    use super::*;
+
+    fn cbor_body(fields: &[(&str, &str)]) -> Vec<u8> {
+        let data: HashMap<_, _> = fields.iter().copied().collect();
+        let mut body = vec![];
+        ciborium::into_writer(&data, &mut body).unwrap();
+        body
+    }
+
+    #[tokio::test]
+    async fn test_cbor_finder() {
+        let finder = CborFinder::new("csrf-token");
+        let mut req = TestClient::post("http://test.com")
+            .add_header(CONTENT_TYPE, "application/cbor", true)
+            .body(cbor_body(&[("csrf-token", "test_token"), ("a", "1")]))
+            .build();
+        let token = finder.find_token(&mut req).await;
+        assert_eq!(token, Some("test_token".to_string()));
+        assert!(!req.payload().await.unwrap().is_empty());
+
+        let mut req = TestClient::post("http://test.com")
+            .add_header(CONTENT_TYPE, "application/cbor", true)
+            .body(cbor_body(&[("a", "1")]))
+            .build();
+        assert_eq!(finder.find_token(&mut req).await, None);
+
+        let mut req = TestClient::post("http://test.com")
+            .add_header(CONTENT_TYPE, "application/octet-stream", true)
+            .body(cbor_body(&[("csrf-token", "test_token")]))
+            .build();
+        assert_eq!(finder.find_token(&mut req).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_cbor_finder_max_body_size() {
+        let finder = CborFinder::new("csrf-token").max_body_size(64);
+        let padding = "a".repeat(1024);
+        let mut req = TestClient::post("http://test.com")
+            .add_header(CONTENT_TYPE, "application/cbor", true)
+            .body(cbor_body(&[
+                ("csrf-token", "test_token"),
+                ("padding", &padding),
+            ]))
+            .build();
+        assert_eq!(finder.find_token(&mut req).await, None);
+    }
+}
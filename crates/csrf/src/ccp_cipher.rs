@@ -1,27 +1,49 @@
 use aead::generic_array::GenericArray;
-use aead::{Aead, KeyInit};
-use base64::engine::general_purpose::URL_SAFE_NO_PAD;
-use base64::Engine;
+use aead::KeyInit;
 use chacha20poly1305::ChaCha20Poly1305;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::ZeroizeOnDrop;
 
-use super::CsrfCipher;
+use super::{aead_proof, CsrfCipher, CsrfVerifyError};
+
+const NONCE_LEN: usize = 12;
 
 /// CcpCipher is a CSRF protection implementation that uses [`ChaCha20Poly1305`](https://datatracker.ietf.org/doc/html/rfc8439).
+///
+/// The key material is wiped from memory when the cipher is dropped.
+#[derive(Clone)]
 pub struct CcpCipher {
-    aead_key: [u8; 32],
+    aead: ChaCha20Poly1305,
     token_size: usize,
 }
 
+// `chacha20poly1305` always wipes its key on drop, the `ccp-cipher` feature needs no extra
+// `zeroize` feature for it.
+impl ZeroizeOnDrop for CcpCipher {}
+
 impl CcpCipher {
     /// Given an aead key, return an `CcpCipher` instance.
     #[inline]
     pub fn new(aead_key: [u8; 32]) -> Self {
         Self {
-            aead_key,
+            aead: ChaCha20Poly1305::new(&GenericArray::from(aead_key)),
             token_size: 32,
         }
     }
 
+    /// Create an `CcpCipher` with a key derived from `passphrase` and `salt` with HKDF-SHA256.
+    ///
+    /// The passphrase must be a long random secret, e.g. read from an environment variable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `passphrase` is shorter than 32 bytes.
+    #[inline]
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Self {
+        Self::new(crate::kdf::derive_key(passphrase, salt, b"salvo-csrf ccp-cipher"))
+    }
+
     /// Sets the length of the token.
     #[inline]
     pub fn token_size(mut self, token_size: usize) -> Self {
@@ -29,37 +51,77 @@ impl CcpCipher {
         self.token_size = token_size;
         self
     }
-
-    #[inline]
-    fn aead(&self) -> ChaCha20Poly1305 {
-        let key = GenericArray::clone_from_slice(&self.aead_key);
-        ChaCha20Poly1305::new(&key)
-    }
 }
 
 impl CsrfCipher for CcpCipher {
     fn verify(&self, token: &str, proof: &str) -> bool {
-        if let (Ok(token), Ok(proof)) = (
-            URL_SAFE_NO_PAD.decode(token.as_bytes()),
-            URL_SAFE_NO_PAD.decode(proof.as_bytes()),
-        ) {
-            if token.len() < 8 || proof.len() < 20 {
-                false
-            } else {
-                let nonce = GenericArray::from_slice(&proof[0..12]);
-                let aead = self.aead();
-                aead.decrypt(nonce, &proof[12..]).map(|p| p == token).unwrap_or(false)
-            }
-        } else {
-            false
-        }
+        self.verify_with_aad(token, proof, b"")
+    }
+    fn supports_aad(&self) -> bool {
+        true
+    }
+    fn verify_with_aad(&self, token: &str, proof: &str, aad: &[u8]) -> bool {
+        aead_proof::open(&self.aead, NONCE_LEN, token, proof, aad).is_ok()
+    }
+    fn verify_detailed(&self, token: &str, proof: &str) -> Result<(), CsrfVerifyError> {
+        aead_proof::open(&self.aead, NONCE_LEN, token, proof, b"")
     }
     fn generate(&self) -> (String, String) {
-        let token = self.random_bytes(self.token_size);
-        let aead = self.aead();
-        let mut proof = self.random_bytes(12);
-        let nonce = GenericArray::from_slice(&proof);
-        proof.append(&mut aead.encrypt(nonce, token.as_slice()).expect("encryption failed"));
-        (URL_SAFE_NO_PAD.encode(token), URL_SAFE_NO_PAD.encode(proof))
+        aead_proof::seal(&self.aead, NONCE_LEN, &mut OsRng, self.token_size, b"")
+    }
+    fn generate_with_rng(&self, rng: &mut dyn RngCore) -> (String, String) {
+        aead_proof::seal(&self.aead, NONCE_LEN, rng, self.token_size, b"")
+    }
+    fn generate_with_aad(&self, aad: &[u8]) -> (String, String) {
+        aead_proof::seal(&self.aead, NONCE_LEN, &mut OsRng, self.token_size, aad)
+    }
+    fn random_bytes(&self, len: usize) -> Vec<u8> {
+        self.random_bytes_with_rng(&mut OsRng, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+
+    use super::*;
+
+    #[test]
+    fn test_custom_token_size() {
+        let cipher = CcpCipher::new([0u8; 32]).token_size(64);
+        let (token, proof) = cipher.generate();
+        assert_eq!(URL_SAFE_NO_PAD.decode(&token).unwrap().len(), 64);
+        assert!(cipher.verify(&token, &proof));
+    }
+
+    #[test]
+    #[should_panic(expected = "length must be larger than 8")]
+    fn test_too_short_token_size() {
+        CcpCipher::new([0u8; 32]).token_size(4);
+    }
+
+    #[test]
+    fn test_from_passphrase() {
+        let passphrase = "0123456789abcdef0123456789abcdef";
+        let cipher = CcpCipher::from_passphrase(passphrase, b"salt");
+        let (token, proof) = cipher.generate();
+        assert!(CcpCipher::from_passphrase(passphrase, b"salt").verify(&token, &proof));
+        assert!(!CcpCipher::from_passphrase(passphrase, b"pepper").verify(&token, &proof));
+    }
+
+    #[test]
+    fn test_associated_data() {
+        let cipher = CcpCipher::new([0u8; 32]);
+        assert!(cipher.supports_aad());
+
+        let (token, proof) = cipher.generate_with_aad(b"X");
+        assert!(cipher.verify_with_aad(&token, &proof, b"X"));
+        assert!(!cipher.verify_with_aad(&token, &proof, b"Y"));
+        assert!(!cipher.verify(&token, &proof));
+
+        let (token, proof) = cipher.generate();
+        assert!(cipher.verify_with_aad(&token, &proof, b""));
+        assert!(!cipher.verify_with_aad(&token, &proof, b"X"));
     }
 }
@@ -0,0 +1,306 @@
+use std::sync::Arc;
+
+use salvo_core::{Depot, Request, Response};
+
+use super::{CsrfCipher, CsrfStore, NamespaceError};
+
+/// Which stores a [`CombinedStore`] saves new proofs to.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SaveTarget {
+    /// Save to the primary store only.
+    #[default]
+    Primary,
+    /// Save to the fallback store only.
+    Fallback,
+    /// Save to both stores.
+    Both,
+}
+
+/// Error type for [`CombinedStore`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum CombinedStoreError<A, B>
+where
+    A: std::error::Error + 'static,
+    B: std::error::Error + 'static,
+{
+    /// Error from the primary store.
+    #[error("primary csrf store error: {0}")]
+    Primary(#[source] A),
+    /// Error from the fallback store.
+    #[error("fallback csrf store error: {0}")]
+    Fallback(#[source] B),
+}
+
+/// A `CsrfStore` that loads from a primary store, then from a fallback store.
+///
+/// This is useful when migrating from one store to another: proofs saved in the old store
+/// keep working, while new proofs are only saved to the new one. With
+/// [`migrate_on_read`](Self::migrate_on_read), proofs read from the old store are also copied
+/// to the new one, so clients move off the old store without getting a new token.
+#[derive(Clone, Debug)]
+pub struct CombinedStore<A, B> {
+    primary: A,
+    fallback: B,
+    save_target: SaveTarget,
+    migrate_on_read: bool,
+    // Shared by the clones, so a proof loaded by one clone is migrated by another.
+    id: Arc<()>,
+}
+
+impl<A: CsrfStore, B: CsrfStore> CombinedStore<A, B> {
+    /// Create a new `CombinedStore`.
+    #[inline]
+    pub fn new(primary: A, fallback: B) -> Self {
+        Self {
+            primary,
+            fallback,
+            save_target: SaveTarget::Primary,
+            migrate_on_read: false,
+            id: Arc::new(()),
+        }
+    }
+
+    /// Sets which stores new proofs are saved to, defaults to [`SaveTarget::Primary`].
+    #[inline]
+    pub fn save_target(mut self, save_target: SaveTarget) -> Self {
+        self.save_target = save_target;
+        self
+    }
+
+    /// Sets whether a proof only found in the fallback store is saved to the primary store,
+    /// defaults to `false`.
+    ///
+    /// The proof is saved once the request is allowed, see [`CsrfStore::persist`].
+    #[inline]
+    pub fn migrate_on_read(mut self, migrate: bool) -> Self {
+        self.migrate_on_read = migrate;
+        self
+    }
+
+    /// Depot key the proof to migrate is kept under until it is persisted.
+    fn migrate_key(&self) -> String {
+        format!("salvo.csrf.migrate.{:p}", Arc::as_ptr(&self.id))
+    }
+}
+
+impl<A: CsrfStore, B: CsrfStore> CsrfStore for CombinedStore<A, B> {
+    type Error = CombinedStoreError<A::Error, B::Error>;
+    async fn load<C: CsrfCipher>(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        cipher: &C,
+    ) -> Option<(String, String)> {
+        if let Some(pair) = self.primary.load(req, depot, cipher).await {
+            return Some(pair);
+        }
+        let pair = self.fallback.load(req, depot, cipher).await?;
+        if self.migrate_on_read {
+            depot.insert(self.migrate_key(), pair.clone());
+        }
+        Some(pair)
+    }
+    fn check(&self, depot: &mut Depot) -> Result<(), Self::Error> {
+        self.primary
+            .check(depot)
+            .map_err(CombinedStoreError::Primary)?;
+        self.fallback
+            .check(depot)
+            .map_err(CombinedStoreError::Fallback)
+    }
+    fn namespace(&mut self, prefix: &str) -> Result<(), NamespaceError> {
+        self.primary.namespace(prefix)?;
+        self.fallback.namespace(prefix)
+    }
+    fn rotation_due(&self, req: &Request, depot: &Depot) -> bool {
+        self.primary.rotation_due(req, depot) || self.fallback.rotation_due(req, depot)
+    }
+    async fn load_all<C: CsrfCipher>(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        cipher: &C,
+    ) -> Vec<(String, String)> {
+        let pairs = self.primary.load_all(req, depot, cipher).await;
+        if !pairs.is_empty() {
+            return pairs;
+        }
+        let pairs = self.fallback.load_all(req, depot, cipher).await;
+        if let Some(pair) = pairs.first().filter(|_| self.migrate_on_read) {
+            depot.insert(self.migrate_key(), pair.clone());
+        }
+        pairs
+    }
+    async fn save(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        token: &str,
+        proof: &str,
+    ) -> Result<(), Self::Error> {
+        if self.save_target != SaveTarget::Fallback {
+            self.primary
+                .save(req, depot, res, token, proof)
+                .await
+                .map_err(CombinedStoreError::Primary)?;
+        }
+        if self.save_target != SaveTarget::Primary {
+            self.fallback
+                .save(req, depot, res, token, proof)
+                .await
+                .map_err(CombinedStoreError::Fallback)?;
+        }
+        Ok(())
+    }
+    async fn persist(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<(), Self::Error> {
+        if let Ok((token, proof)) = depot.remove::<(String, String)>(&self.migrate_key()) {
+            tracing::debug!("migrating csrf proof to the primary store");
+            self.primary
+                .save(req, depot, res, &token, &proof)
+                .await
+                .map_err(CombinedStoreError::Primary)?;
+        }
+        self.primary
+            .persist(req, depot, res)
+            .await
+            .map_err(CombinedStoreError::Primary)?;
+        self.fallback
+            .persist(req, depot, res)
+            .await
+            .map_err(CombinedStoreError::Fallback)
+    }
+    async fn clear(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<(), Self::Error> {
+        self.primary
+            .clear(req, depot, res)
+            .await
+            .map_err(CombinedStoreError::Primary)?;
+        self.fallback
+            .clear(req, depot, res)
+            .await
+            .map_err(CombinedStoreError::Fallback)
+    }
+}
+
+#[cfg(all(test, feature = "cookie-store"))]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+    use crate::{BcryptCipher, CookieStore, Csrf, CsrfDepotExt, HeaderFinder};
+
+    #[handler]
+    async fn get_index(depot: &mut Depot) -> String {
+        depot.csrf_token().unwrap().to_owned()
+    }
+    #[handler]
+    async fn post_index() -> &'static str {
+        "POST"
+    }
+
+    fn combined_csrf() -> Csrf<BcryptCipher, CombinedStore<CookieStore, CookieStore>> {
+        Csrf::new(
+            BcryptCipher::new(),
+            CombinedStore::new(
+                CookieStore::new().name("new.csrf"),
+                CookieStore::new().name("old.csrf"),
+            ),
+            HeaderFinder::new("x-csrf-token"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_combined_store_loads_from_fallback() {
+        let old_csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new().name("old.csrf"),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(Router::new().hoop(old_csrf).get(get_index))
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("old.csrf").unwrap();
+
+        let router = Router::new().hoop(combined_csrf()).post(post_index);
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", csrf_token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(router)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_combined_store_saves_to_primary() {
+        let router = Router::new().hoop(combined_csrf()).get(get_index);
+        let res = TestClient::get("http://127.0.0.1:5801").send(router).await;
+        assert!(res.cookie("new.csrf").is_some());
+        assert!(res.cookie("old.csrf").is_none());
+    }
+
+    #[test]
+    fn test_combined_store_migrate_key() {
+        let store = CombinedStore::new(CookieStore::new(), CookieStore::new());
+        let mut scoped = store.clone();
+        scoped.namespace("form").unwrap();
+        assert_eq!(store.migrate_key(), scoped.migrate_key());
+        assert_ne!(
+            store.migrate_key(),
+            CombinedStore::new(CookieStore::new(), CookieStore::new()).migrate_key()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_combined_store_migrate_on_read() {
+        let old_csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            CookieStore::new().name("old.csrf"),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(Router::new().hoop(old_csrf).get(get_index))
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        let old_cookie = res.cookie("old.csrf").unwrap().clone();
+
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            CombinedStore::new(
+                CookieStore::new().name("new.csrf"),
+                CookieStore::new().name("old.csrf"),
+            )
+            .migrate_on_read(true),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let service = Service::new(Router::new().hoop(csrf).get(get_index).post(post_index));
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", &csrf_token, true)
+            .add_header("cookie", old_cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let new_cookie = res.cookie("new.csrf").unwrap();
+        assert_eq!(new_cookie.value(), old_cookie.value());
+
+        let new_cookie = new_cookie.to_string();
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", &csrf_token, true)
+            .add_header("cookie", &new_cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
+}
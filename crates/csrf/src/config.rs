@@ -0,0 +1,434 @@
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{Csrf, CsrfCipher, CsrfStore, CsrfTokenFinder, MaskedCipher, TokenEncoding};
+
+/// Cipher described by a [`CsrfConfig`].
+///
+/// The keyless ciphers keep their costs, left out costs fall back to the cipher defaults.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum CipherKind {
+    /// [`BcryptCipher`](crate::BcryptCipher), needs the `bcrypt-cipher` feature.
+    Bcrypt {
+        /// The bcrypt cost, between 4 and 31.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cost: Option<u32>,
+    },
+    /// [`Argon2Cipher`](crate::Argon2Cipher), needs the `argon2-cipher` feature.
+    Argon2 {
+        /// The memory cost in KiB.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        memory_cost: Option<u32>,
+        /// The number of iterations.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time_cost: Option<u32>,
+        /// The degree of parallelism.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        parallelism: Option<u32>,
+    },
+    /// [`HmacCipher`](crate::HmacCipher), needs the `hmac-cipher` feature.
+    Hmac,
+    /// [`Blake3Cipher`](crate::Blake3Cipher), needs the `blake3-cipher` feature.
+    Blake3,
+    /// [`AesGcmCipher`](crate::AesGcmCipher), needs the `aes-gcm-cipher` feature.
+    AesGcm,
+    /// [`AesGcmSivCipher`](crate::AesGcmSivCipher), needs the `aes-gcm-siv-cipher` feature.
+    AesGcmSiv,
+    /// [`CcpCipher`](crate::CcpCipher), needs the `ccp-cipher` feature.
+    Ccp,
+    /// [`XCcpCipher`](crate::XCcpCipher), needs the `xccp-cipher` feature.
+    XCcp,
+}
+
+impl Default for CipherKind {
+    /// Bcrypt with its default cost, like the default features of this crate.
+    fn default() -> Self {
+        Self::Bcrypt { cost: None }
+    }
+}
+
+/// Error returned when a [`CsrfConfig`] can not be read or used.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum CsrfConfigError {
+    /// The configuration could not be serialized or deserialized.
+    #[error("invalid csrf config: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The cipher is not enabled in this build.
+    #[error("csrf cipher `{0:?}` is not enabled, turn on its feature")]
+    Unsupported(CipherKind),
+    /// The token size is smaller than 8 bytes, or larger than 72 bytes for bcrypt.
+    #[error("csrf token size {0} is out of range")]
+    InvalidTokenSize(usize),
+    /// The costs of a keyless cipher are out of range.
+    #[error("csrf cipher `{0:?}` has invalid costs")]
+    InvalidCost(CipherKind),
+}
+
+/// Describes how tokens are generated and encoded, without the key, so separate services
+/// can validate the same tokens, e.g. an API gateway and the app behind it:
+///
+/// ```ignore
+/// let config = CsrfConfig::new(CipherKind::Hmac).encoding(TokenEncoding::Hex);
+/// config.to_writer(File::create("csrf.json")?)?;
+///
+/// // In the other service, with the key shared through a secret store.
+/// let config = CsrfConfig::from_reader(File::open("csrf.json")?)?;
+/// let csrf = config.build_csrf(key, CookieStore::new(), HeaderFinder::new("x-csrf-token"))?;
+/// ```
+///
+/// The config is stored as JSON. [`TokenEncoding::Base64`] can not be serialized.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CsrfConfig {
+    /// The cipher tokens are generated with.
+    pub cipher: CipherKind,
+    /// Length of the random tokens in bytes, defaults to 32.
+    #[serde(default = "default_token_size")]
+    pub token_size: usize,
+    /// Whether tokens are masked with [`MaskedCipher`], defaults to `false`.
+    #[serde(default)]
+    pub masked: bool,
+    /// Encoding of the tokens sent to clients, defaults to [`TokenEncoding::UrlSafeNoPad`].
+    #[serde(default, with = "encoding")]
+    pub encoding: TokenEncoding,
+}
+
+fn default_token_size() -> usize {
+    32
+}
+
+impl CsrfConfig {
+    /// Create a new `CsrfConfig` for `cipher` with the default token layout.
+    #[inline]
+    pub fn new(cipher: CipherKind) -> Self {
+        Self {
+            cipher,
+            token_size: default_token_size(),
+            masked: false,
+            encoding: TokenEncoding::UrlSafeNoPad,
+        }
+    }
+
+    /// Sets the length of the tokens in bytes.
+    #[inline]
+    pub fn token_size(mut self, token_size: usize) -> Self {
+        self.token_size = token_size;
+        self
+    }
+
+    /// Sets whether tokens are masked with [`MaskedCipher`].
+    #[inline]
+    pub fn masked(mut self, masked: bool) -> Self {
+        self.masked = masked;
+        self
+    }
+
+    /// Sets the encoding of the tokens sent to clients.
+    #[inline]
+    pub fn encoding(mut self, encoding: TokenEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Read a config from JSON.
+    pub fn from_reader(reader: impl Read) -> Result<Self, CsrfConfigError> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Write the config as JSON.
+    pub fn to_writer(&self, writer: impl Write) -> Result<(), CsrfConfigError> {
+        Ok(serde_json::to_writer(writer, self)?)
+    }
+
+    /// Build the described cipher with `key`, the keyless ciphers ignore it.
+    pub fn build_cipher(&self, key: [u8; 32]) -> Result<Box<dyn CsrfCipher>, CsrfConfigError> {
+        if self.token_size < 8 {
+            return Err(CsrfConfigError::InvalidTokenSize(self.token_size));
+        }
+        let cipher: Result<Box<dyn CsrfCipher>, _> = match self.cipher {
+            #[cfg(feature = "bcrypt-cipher")]
+            CipherKind::Bcrypt { cost } => {
+                if self.token_size > 72 {
+                    return Err(CsrfConfigError::InvalidTokenSize(self.token_size));
+                }
+                let cipher = crate::BcryptCipher::new().token_size(self.token_size);
+                match cost {
+                    Some(cost) if !(4..=31).contains(&cost) => {
+                        Err(CsrfConfigError::InvalidCost(self.cipher))
+                    }
+                    Some(cost) => Ok(Box::new(cipher.cost(cost))),
+                    None => Ok(Box::new(cipher)),
+                }
+            }
+            #[cfg(feature = "argon2-cipher")]
+            CipherKind::Argon2 {
+                memory_cost,
+                time_cost,
+                parallelism,
+            } => {
+                use argon2::Params;
+
+                let m_cost = memory_cost.unwrap_or(Params::DEFAULT_M_COST);
+                let t_cost = time_cost.unwrap_or(Params::DEFAULT_T_COST);
+                let p_cost = parallelism.unwrap_or(Params::DEFAULT_P_COST);
+                if Params::new(m_cost, t_cost, p_cost, None).is_err() {
+                    return Err(CsrfConfigError::InvalidCost(self.cipher));
+                }
+                Ok(Box::new(
+                    crate::Argon2Cipher::new()
+                        .token_size(self.token_size)
+                        .memory_cost(m_cost)
+                        .time_cost(t_cost)
+                        .parallelism(p_cost),
+                ))
+            }
+            #[cfg(feature = "hmac-cipher")]
+            CipherKind::Hmac => Ok(Box::new(
+                crate::HmacCipher::new(key).token_size(self.token_size),
+            )),
+            #[cfg(feature = "blake3-cipher")]
+            CipherKind::Blake3 => Ok(Box::new(
+                crate::Blake3Cipher::new(key).token_size(self.token_size),
+            )),
+            #[cfg(feature = "aes-gcm-cipher")]
+            CipherKind::AesGcm => Ok(Box::new(
+                crate::AesGcmCipher::new(key).token_size(self.token_size),
+            )),
+            #[cfg(feature = "aes-gcm-siv-cipher")]
+            CipherKind::AesGcmSiv => Ok(Box::new(
+                crate::AesGcmSivCipher::new(key).token_size(self.token_size),
+            )),
+            #[cfg(feature = "ccp-cipher")]
+            CipherKind::Ccp => Ok(Box::new(
+                crate::CcpCipher::new(key).token_size(self.token_size),
+            )),
+            #[cfg(feature = "xccp-cipher")]
+            CipherKind::XCcp => Ok(Box::new(
+                crate::XCcpCipher::new(key).token_size(self.token_size),
+            )),
+            #[allow(unreachable_patterns)]
+            kind => {
+                let _ = key;
+                Err(CsrfConfigError::Unsupported(kind))
+            }
+        };
+        let cipher = cipher?;
+        if self.masked {
+            Ok(Box::new(MaskedCipher::new(cipher)))
+        } else {
+            Ok(cipher)
+        }
+    }
+
+    /// Build a [`Csrf`] validating tokens as described, with `key`, `store` and `finder`.
+    pub fn build_csrf<S: CsrfStore>(
+        &self,
+        key: [u8; 32],
+        store: S,
+        finder: impl CsrfTokenFinder,
+    ) -> Result<Csrf<Box<dyn CsrfCipher>, S>, CsrfConfigError> {
+        let cipher = self.build_cipher(key)?;
+        Ok(Csrf::new(cipher, store, finder).with_encoding(self.encoding))
+    }
+}
+
+/// Serializes [`TokenEncoding`] by name, custom base64 engines can not be serialized.
+mod encoding {
+    use super::*;
+
+    pub(super) fn serialize<S: Serializer>(
+        encoding: &TokenEncoding,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match encoding {
+            TokenEncoding::UrlSafeNoPad => serializer.serialize_str("url_safe_no_pad"),
+            TokenEncoding::Standard => serializer.serialize_str("standard"),
+            TokenEncoding::Hex => serializer.serialize_str("hex"),
+            TokenEncoding::Base64(_) => Err(serde::ser::Error::custom(
+                "custom base64 token encodings can not be serialized",
+            )),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<TokenEncoding, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        match &*name {
+            "url_safe_no_pad" => Ok(TokenEncoding::UrlSafeNoPad),
+            "standard" => Ok(TokenEncoding::Standard),
+            "hex" => Ok(TokenEncoding::Hex),
+            _ => Err(serde::de::Error::unknown_variant(
+                &name,
+                &["url_safe_no_pad", "standard", "hex"],
+            )),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "hmac-cipher", feature = "cookie-store"))]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+    use crate::{CookieStore, CsrfDepotExt, HeaderFinder};
+
+    #[handler]
+    async fn get_index(depot: &mut Depot) -> String {
+        depot.csrf_token().unwrap().to_owned()
+    }
+    #[handler]
+    async fn post_index() -> &'static str {
+        "POST"
+    }
+
+    #[cfg(all(feature = "bcrypt-cipher", feature = "argon2-cipher"))]
+    #[test]
+    fn test_config_keyless_ciphers() {
+        let config = CsrfConfig::from_reader(&br#"{"cipher":{"bcrypt":{"cost":4}}}"#[..]).unwrap();
+        assert_eq!(config.cipher, CipherKind::Bcrypt { cost: Some(4) });
+        let argon2 = CipherKind::Argon2 {
+            memory_cost: Some(64),
+            time_cost: Some(1),
+            parallelism: None,
+        };
+        let mut json = vec![];
+        CsrfConfig::new(argon2).to_writer(&mut json).unwrap();
+        assert_eq!(
+            String::from_utf8(json.clone()).unwrap(),
+            r#"{"cipher":{"argon2":{"memory_cost":64,"time_cost":1}},"token_size":32,"masked":false,"encoding":"url_safe_no_pad"}"#
+        );
+        assert_eq!(CsrfConfig::from_reader(&json[..]).unwrap().cipher, argon2);
+        assert_eq!(
+            CsrfConfig::from_reader(&br#"{"cipher":{"bcrypt":{}}}"#[..])
+                .unwrap()
+                .cipher,
+            CipherKind::default()
+        );
+
+        for config in [config, CsrfConfig::new(argon2)] {
+            let cipher = config.build_cipher([0; 32]).unwrap();
+            let (token, proof) = cipher.generate();
+            assert!(cipher.verify(&token, &proof));
+            // The keyless ciphers verify tokens without the key.
+            assert!(config.build_cipher([1; 32]).unwrap().verify(&token, &proof));
+        }
+
+        for kind in [
+            CipherKind::Bcrypt { cost: Some(3) },
+            CipherKind::Argon2 {
+                memory_cost: Some(1),
+                time_cost: None,
+                parallelism: None,
+            },
+        ] {
+            assert!(matches!(
+                CsrfConfig::new(kind).build_cipher([0; 32]),
+                Err(CsrfConfigError::InvalidCost(k)) if k == kind
+            ));
+        }
+        assert!(matches!(
+            CsrfConfig::new(CipherKind::default())
+                .token_size(73)
+                .build_cipher([0; 32]),
+            Err(CsrfConfigError::InvalidTokenSize(73))
+        ));
+    }
+
+    #[test]
+    fn test_config_roundtrip() {
+        let config = CsrfConfig::new(CipherKind::Hmac)
+            .token_size(16)
+            .masked(true)
+            .encoding(TokenEncoding::Hex);
+        let mut json = vec![];
+        config.to_writer(&mut json).unwrap();
+        assert_eq!(
+            String::from_utf8(json.clone()).unwrap(),
+            r#"{"cipher":"hmac","token_size":16,"masked":true,"encoding":"hex"}"#
+        );
+        assert_eq!(CsrfConfig::from_reader(&json[..]).unwrap(), config);
+
+        let config = CsrfConfig::from_reader(&br#"{"cipher":"x_ccp"}"#[..]).unwrap();
+        assert_eq!(config, CsrfConfig::new(CipherKind::XCcp));
+
+        static ENGINE: base64::engine::GeneralPurpose = base64::engine::GeneralPurpose::new(
+            &base64::alphabet::CRYPT,
+            base64::engine::general_purpose::NO_PAD,
+        );
+        let config = CsrfConfig::new(CipherKind::Hmac).encoding(TokenEncoding::Base64(&ENGINE));
+        assert!(matches!(
+            config.to_writer(vec![]),
+            Err(CsrfConfigError::Json(_))
+        ));
+        for json in [
+            r#"{"cipher":"rot13"}"#,
+            r#"{"cipher":"hmac","encoding":"base32"}"#,
+        ] {
+            assert!(CsrfConfig::from_reader(json.as_bytes()).is_err(), "{json}");
+        }
+        assert!(matches!(
+            CsrfConfig::new(CipherKind::Hmac)
+                .token_size(4)
+                .build_cipher([0; 32]),
+            Err(CsrfConfigError::InvalidTokenSize(4))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_config_build_csrf() {
+        let config = CsrfConfig::new(CipherKind::Hmac)
+            .masked(true)
+            .encoding(TokenEncoding::Hex);
+        let issuing = config
+            .build_csrf(
+                [3; 32],
+                CookieStore::new(),
+                HeaderFinder::new("x-csrf-token"),
+            )
+            .unwrap();
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(Router::new().hoop(issuing).get(get_index))
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        assert!(csrf_token.bytes().all(|b| b.is_ascii_hexdigit()));
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
+
+        let mut json = vec![];
+        config.to_writer(&mut json).unwrap();
+        let validating = CsrfConfig::from_reader(&json[..])
+            .unwrap()
+            .build_csrf(
+                [3; 32],
+                CookieStore::new(),
+                HeaderFinder::new("x-csrf-token"),
+            )
+            .unwrap();
+        let service = Service::new(Router::new().hoop(validating).post(post_index));
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", &csrf_token, true)
+            .add_header("cookie", &cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let other_key = config
+            .build_csrf(
+                [4; 32],
+                CookieStore::new(),
+                HeaderFinder::new("x-csrf-token"),
+            )
+            .unwrap();
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", &csrf_token, true)
+            .add_header("cookie", &cookie, true)
+            .send(Router::new().hoop(other_key).post(post_index))
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+}
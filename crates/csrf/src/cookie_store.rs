@@ -1,14 +1,15 @@
 use cookie::time::Duration;
-use cookie::{Cookie, Expiration, SameSite};
+use cookie::{Cookie, CookieBuilder, Expiration, Key, SameSite};
 use salvo_core::http::uri::Scheme;
 use salvo_core::{Depot, Error, Request, Response};
 
-use crate::CsrfCipher;
+use crate::binding::split_proof;
+use crate::{CsrfCipher, NamespaceError};
 
 use super::CsrfStore;
 
 /// A `CsrfStore` implementation that stores the CSRF proof in a cookie.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[non_exhaustive]
 pub struct CookieStore {
     /// CSRF cookie ttl.
@@ -19,6 +20,17 @@ pub struct CookieStore {
     pub path: String,
     /// CSRF cookie domain.
     pub domain: Option<String>,
+    protection: Option<Protection>,
+    host_prefix: bool,
+    http_only: bool,
+    same_site: SameSite,
+    secure: Option<bool>,
+}
+
+#[derive(Clone, Debug)]
+enum Protection {
+    Signed(Key),
+    Private(Key),
 }
 impl Default for CookieStore {
     #[inline]
@@ -35,6 +47,42 @@ impl CookieStore {
             name: "salvo.csrf".into(),
             path: "/".into(),
             domain: None,
+            host_prefix: false,
+            protection: None,
+            http_only: true,
+            same_site: SameSite::Strict,
+            secure: None,
+        }
+    }
+
+    /// Create a new `CookieStore` that signs the cookie with `key`.
+    ///
+    /// A cookie whose signature is invalid is treated as absent.
+    pub fn signed(key: Key) -> Self {
+        Self {
+            protection: Some(Protection::Signed(key)),
+            ..Self::new()
+        }
+    }
+
+    /// Create a new `CookieStore` that signs the cookie with a key derived from `master`,
+    /// see [`signed`](Self::signed).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `master` is shorter than 64 bytes.
+    pub fn from_key(master: &[u8]) -> Self {
+        assert!(master.len() >= 64, "key must be at least 64 bytes");
+        Self::signed(Key::from(master))
+    }
+
+    /// Create a new `CookieStore` that encrypts the cookie with `key`.
+    ///
+    /// A cookie which can not be decrypted is treated as absent.
+    pub fn private(key: Key) -> Self {
+        Self {
+            protection: Some(Protection::Private(key)),
+            ..Self::new()
         }
     }
     /// Sets cookie name.
@@ -50,24 +98,151 @@ impl CookieStore {
     }
 
     /// Sets cookie path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the host prefix is enabled and `path` is not `/`.
     pub fn path(mut self, path: impl Into<String>) -> Self {
         self.path = path.into();
+        assert!(
+            !self.host_prefix || self.path == "/",
+            "`__Host-` prefixed cookies must use path `/`"
+        );
         self
     }
 
     /// Sets cookie domain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the host prefix is enabled.
     pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        assert!(!self.host_prefix, "`__Host-` prefixed cookies must not set a domain");
         self.domain = Some(domain.into());
         self
     }
+
+    /// Sets the `SameSite` attribute of the cookie, defaults to `Strict`.
+    ///
+    /// With `SameSite=None`, the cookie is always marked `Secure`, as browsers reject it
+    /// otherwise. This does not change the cookie set by
+    /// [`Csrf::with_token_cookie`](crate::Csrf::with_token_cookie).
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self.warn_insecure_same_site();
+        self
+    }
+
+    /// Sets whether the cookie is marked `Secure`, by default only on https requests.
+    ///
+    /// `SameSite=None` cookies are always marked `Secure`, as browsers reject them otherwise,
+    /// so `secure(false)` is then ignored and a warning is logged.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = Some(secure);
+        self.warn_insecure_same_site();
+        self
+    }
+
+    fn warn_insecure_same_site(&self) {
+        if self.same_site == SameSite::None && self.secure == Some(false) {
+            tracing::warn!(
+                "csrf cookie `{}` uses `SameSite=None`, which browsers only accept with `Secure`, \
+                 it is marked `Secure` anyway",
+                self.name
+            );
+        }
+    }
+
+    /// Prefixes the cookie name with `__Host-`.
+    ///
+    /// Browsers only accept such cookies when they are `Secure`, use path `/` and have no
+    /// domain, so the cookie is always marked `Secure` and the site must be served over https.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a domain or a path other than `/` is configured.
+    pub fn with_host_prefix(mut self) -> Self {
+        assert!(self.domain.is_none(), "`__Host-` prefixed cookies must not set a domain");
+        assert!(self.path == "/", "`__Host-` prefixed cookies must use path `/`");
+        self.host_prefix = true;
+        self
+    }
+
+    fn with_domain(&self, builder: CookieBuilder<'static>) -> Cookie<'static> {
+        if let Some(domain) = &self.domain {
+            builder.domain(domain.clone()).build()
+        } else {
+            builder.build()
+        }
+    }
+
+    fn cookie_name(&self) -> String {
+        if self.host_prefix {
+            format!("__Host-{}", self.name)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// Makes the cookie readable by scripts, used for the token cookie of `DoubleSubmitStore`.
+    pub(crate) fn readable(mut self) -> Self {
+        assert!(self.protection.is_none(), "readable cookies can not be signed or private");
+        self.http_only = false;
+        self
+    }
+
+    pub(crate) fn get_value(&self, req: &Request) -> Option<String> {
+        let name = self.cookie_name();
+        let cookie = match &self.protection {
+            None => req.cookie(&name).cloned(),
+            Some(Protection::Signed(key)) => req.cookies().signed(key).get(&name),
+            Some(Protection::Private(key)) => req.cookies().private(key).get(&name),
+        };
+        cookie.map(|c| c.value().to_owned())
+    }
+
+    /// Builds the cookie with every attribute but its expiration, shared by the set and removal
+    /// cookies so browsers match them, e.g. `__Host-` cookies are only replaced when `Secure`.
+    fn cookie_builder(&self, req: &Request, value: String) -> CookieBuilder<'static> {
+        let secure = self.host_prefix
+            || self.same_site == SameSite::None
+            || self.secure.unwrap_or_else(|| req.uri().scheme() == Some(&Scheme::HTTPS));
+        Cookie::build((self.cookie_name(), value))
+            .http_only(self.http_only)
+            .same_site(self.same_site)
+            .path(self.path.clone())
+            .secure(secure)
+    }
+
+    pub(crate) fn set_value(&self, req: &Request, res: &mut Response, value: String) {
+        let expires = cookie::time::OffsetDateTime::now_utc() + self.ttl;
+        let cookie_builder = self
+            .cookie_builder(req, value)
+            .expires(Expiration::DateTime(expires));
+        let cookie = self.with_domain(cookie_builder);
+        match &self.protection {
+            None => {
+                res.add_cookie(cookie);
+            }
+            Some(Protection::Signed(key)) => res.cookies_mut().signed_mut(key).add(cookie),
+            Some(Protection::Private(key)) => res.cookies_mut().private_mut(key).add(cookie),
+        }
+    }
+
+    pub(crate) fn remove(&self, req: &Request, res: &mut Response) {
+        let mut cookie = self.with_domain(self.cookie_builder(req, String::new()));
+        cookie.make_removal();
+        res.add_cookie(cookie);
+    }
 }
 impl CsrfStore for CookieStore {
     type Error = Error;
     async fn load<C: CsrfCipher>(&self, req: &mut Request, _depot: &mut Depot, cipher: &C) -> Option<(String, String)> {
-        req.cookie(&self.name)
-            .and_then(|c| c.value().split_once('.'))
+        self.get_value(req)
+            .as_ref()
+            .and_then(|value| value.split_once('.'))
             .and_then(|(token, proof)| {
-                if cipher.verify(token, proof) {
+                if cipher.verify(token, split_proof(proof).0) {
                     Some((token.into(), proof.into()))
                 } else {
                     None
@@ -82,20 +257,15 @@ impl CsrfStore for CookieStore {
         token: &str,
         proof: &str,
     ) -> Result<(), Self::Error> {
-        let secure = req.uri().scheme() == Some(&Scheme::HTTPS);
-        let expires = cookie::time::OffsetDateTime::now_utc() + self.ttl;
-        let cookie_builder = Cookie::build((self.name.clone(), format!("{token}.{proof}")))
-            .http_only(true)
-            .same_site(SameSite::Strict)
-            .path(self.path.clone())
-            .secure(secure)
-            .expires(Expiration::DateTime(expires));
-        let cookie = if let Some(domain) = &self.domain {
-            cookie_builder.domain(domain.clone()).build()
-        } else {
-            cookie_builder.build()
-        };
-        res.add_cookie(cookie);
+        self.set_value(req, res, format!("{token}.{proof}"));
+        Ok(())
+    }
+    async fn clear(&self, req: &mut Request, _depot: &mut Depot, res: &mut Response) -> Result<(), Self::Error> {
+        self.remove(req, res);
+        Ok(())
+    }
+    fn namespace(&mut self, prefix: &str) -> Result<(), NamespaceError> {
+        self.name = format!("{prefix}.{}", self.name);
         Ok(())
     }
 }
@@ -0,0 +1,164 @@
+use std::fmt;
+use std::sync::Arc;
+
+use salvo_core::http::StatusCode;
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler, Request, Response};
+
+use super::{Csrf, CsrfCipher, CsrfStore, CsrfTokenFinder};
+
+/// Protects a subtree with a shared [`Csrf`], overriding some of its settings.
+///
+/// The base `Csrf` is shared through an `Arc`, so its settings are not copied. Hoop the
+/// override instead of the base on the subtree, both would validate the request otherwise:
+///
+/// ```ignore
+/// let csrf = bcrypt_cookie_csrf(FormFinder::new("csrf-token"));
+/// let api = CsrfOverride::new(Arc::new(csrf.clone()))
+///     .rejection_status(StatusCode::BAD_REQUEST)
+///     .add_finder(HeaderFinder::new("x-csrf-token"));
+/// Router::new()
+///     .push(Router::with_path("forms").hoop(csrf).post(submit))
+///     .push(Router::with_path("api").hoop(api).post(call))
+/// ```
+pub struct CsrfOverride<C, S> {
+    base: Arc<Csrf<C, S>>,
+    rejection_status: Option<StatusCode>,
+    extra_finders: Vec<Arc<dyn CsrfTokenFinder>>,
+}
+
+impl<C, S> Clone for CsrfOverride<C, S> {
+    fn clone(&self) -> Self {
+        Self {
+            base: self.base.clone(),
+            rejection_status: self.rejection_status,
+            extra_finders: self.extra_finders.clone(),
+        }
+    }
+}
+
+impl<C, S> fmt::Debug for CsrfOverride<C, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CsrfOverride")
+            .field("base", &self.base)
+            .field("rejection_status", &self.rejection_status)
+            .field(
+                "extra_finders",
+                &self
+                    .extra_finders
+                    .iter()
+                    .map(|finder| finder.type_name())
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl<C: CsrfCipher, S: CsrfStore> CsrfOverride<C, S> {
+    /// Create a new `CsrfOverride` behaving like `base` until settings are overridden.
+    #[inline]
+    pub fn new(base: Arc<Csrf<C, S>>) -> Self {
+        Self {
+            base,
+            rejection_status: None,
+            extra_finders: vec![],
+        }
+    }
+
+    /// Sets the status code of rejected requests, instead of the one of the base.
+    #[inline]
+    pub fn rejection_status(mut self, status: StatusCode) -> Self {
+        self.rejection_status = Some(status);
+        self
+    }
+
+    /// Adds a finder tried after the finders of the base.
+    #[inline]
+    pub fn add_finder(mut self, finder: impl CsrfTokenFinder) -> Self {
+        self.extra_finders.push(Arc::new(finder));
+        self
+    }
+}
+
+#[async_trait]
+impl<C: CsrfCipher, S: CsrfStore> Handler for CsrfOverride<C, S> {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        let rejection_status = self.rejection_status.unwrap_or(self.base.rejection_status);
+        self.base
+            .protect_with(req, depot, res, ctrl, rejection_status, &self.extra_finders)
+            .await;
+    }
+}
+
+#[cfg(all(test, feature = "cookie-store", feature = "bcrypt-cipher"))]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+    use crate::{BcryptCipher, CookieStore, CsrfDepotExt, FormFinder, HeaderFinder};
+
+    #[handler]
+    async fn get_index(depot: &mut Depot) -> String {
+        depot.csrf_token().unwrap().to_owned()
+    }
+    #[handler]
+    async fn post_index() -> &'static str {
+        "POST"
+    }
+
+    #[tokio::test]
+    async fn test_csrf_override() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            FormFinder::new("csrf-token"),
+        );
+        let api = CsrfOverride::new(Arc::new(csrf.clone()))
+            .rejection_status(StatusCode::BAD_REQUEST)
+            .add_finder(HeaderFinder::new("x-csrf-token"));
+        let router = Router::new()
+            .push(
+                Router::with_path("forms")
+                    .hoop(csrf)
+                    .get(get_index)
+                    .post(post_index),
+            )
+            .push(Router::with_path("api").hoop(api).post(post_index));
+        let service = Service::new(router);
+
+        let res = TestClient::post("http://127.0.0.1:5801/forms")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+        let res = TestClient::post("http://127.0.0.1:5801/api")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::BAD_REQUEST);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/forms")
+            .send(&service)
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post("http://127.0.0.1:5801/api")
+            .add_header("x-csrf-token", &csrf_token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let res = TestClient::post("http://127.0.0.1:5801/forms")
+            .add_header("x-csrf-token", &csrf_token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+}
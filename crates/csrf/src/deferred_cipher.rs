@@ -0,0 +1,181 @@
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+use rand::RngCore;
+use salvo_core::Depot;
+
+use super::{CsrfCipher, CsrfVerifyError};
+
+/// Hook reading the key of a [`DeferredCipher`] from the depot.
+type KeyHook = Arc<dyn Fn(&Depot) -> Option<[u8; 32]> + Send + Sync>;
+
+/// A `CsrfCipher` whose key is read from the depot on the first request, e.g. from app state
+/// injected with `affix_state`, when it is not available while building [`Csrf`](crate::Csrf):
+///
+/// ```ignore
+/// let cipher = DeferredCipher::new(
+///     |depot| depot.obtain::<AppConfig>().ok().map(|config| config.csrf_key),
+///     HmacCipher::new,
+/// );
+/// ```
+///
+/// The cipher built from the key is cached and shared by clones of the `Csrf`, later keys are
+/// ignored. `Csrf` responds `500` while the key is not in the depot, see
+/// [`CsrfCipher::prepare`]. Calling [`Csrf::generate_token`](crate::Csrf::generate_token)
+/// before the first request panics, and associated data is not supported.
+pub struct DeferredCipher<C> {
+    key: KeyHook,
+    build: Arc<dyn Fn([u8; 32]) -> C + Send + Sync>,
+    cipher: OnceLock<C>,
+}
+
+impl<C: fmt::Debug> fmt::Debug for DeferredCipher<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeferredCipher")
+            .field("cipher", &self.cipher.get())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C: CsrfCipher> DeferredCipher<C> {
+    /// Create a new `DeferredCipher` building its cipher with `build` from the key `key` reads
+    /// from the depot, e.g. `HmacCipher::new`.
+    #[inline]
+    pub fn new(
+        key: impl Fn(&Depot) -> Option<[u8; 32]> + Send + Sync + 'static,
+        build: impl Fn([u8; 32]) -> C + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            key: Arc::new(key),
+            build: Arc::new(build),
+            cipher: OnceLock::new(),
+        }
+    }
+
+    /// Get the cipher, if its key was read already.
+    #[inline]
+    pub fn get(&self) -> Option<&C> {
+        self.cipher.get()
+    }
+
+    fn cipher(&self) -> &C {
+        self.cipher
+            .get()
+            .expect("`DeferredCipher` used before its key was read from the depot")
+    }
+}
+
+impl<C: CsrfCipher> CsrfCipher for DeferredCipher<C> {
+    fn prepare(&self, depot: &Depot) -> bool {
+        if let Some(cipher) = self.cipher.get() {
+            return cipher.prepare(depot);
+        }
+        match (self.key)(depot) {
+            Some(key) => self.cipher.get_or_init(|| (self.build)(key)).prepare(depot),
+            None => false,
+        }
+    }
+    fn verify(&self, token: &str, proof: &str) -> bool {
+        self.cipher
+            .get()
+            .is_some_and(|cipher| cipher.verify(token, proof))
+    }
+    fn verify_detailed(&self, token: &str, proof: &str) -> Result<(), CsrfVerifyError> {
+        match self.cipher.get() {
+            Some(cipher) => cipher.verify_detailed(token, proof),
+            None => Err(CsrfVerifyError::Invalid),
+        }
+    }
+    fn verify_raw(&self, token: &[u8], proof: &[u8]) -> bool {
+        self.cipher
+            .get()
+            .is_some_and(|cipher| cipher.verify_raw(token, proof))
+    }
+    fn min_token_len(&self) -> usize {
+        self.cipher.get().map_or(0, |cipher| cipher.min_token_len())
+    }
+    fn generate(&self) -> (String, String) {
+        self.cipher().generate()
+    }
+    fn generate_with_rng(&self, rng: &mut dyn RngCore) -> (String, String) {
+        self.cipher().generate_with_rng(rng)
+    }
+    fn random_bytes(&self, len: usize) -> Vec<u8> {
+        self.cipher().random_bytes(len)
+    }
+}
+
+#[cfg(all(test, feature = "hmac-cipher", feature = "cookie-store"))]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+    use crate::{CookieStore, Csrf, CsrfDepotExt, HeaderFinder, HmacCipher};
+
+    struct AppKey([u8; 32]);
+
+    #[handler]
+    async fn inject_key(req: &mut Request, depot: &mut Depot) {
+        if req.header::<String>("x-no-key").is_none() {
+            depot.inject(AppKey([7; 32]));
+        }
+    }
+    #[handler]
+    async fn get_index(depot: &mut Depot) -> String {
+        depot.csrf_token().unwrap().to_owned()
+    }
+    #[handler]
+    async fn post_index() -> &'static str {
+        "POST"
+    }
+
+    #[tokio::test]
+    async fn test_deferred_cipher() {
+        let cipher = DeferredCipher::new(
+            |depot| depot.obtain::<AppKey>().ok().map(|key| key.0),
+            HmacCipher::new,
+        );
+        assert!(cipher.get().is_none());
+        let csrf = Csrf::new(
+            cipher,
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new()
+            .hoop(inject_key)
+            .hoop(csrf)
+            .get(get_index)
+            .post(post_index);
+        let service = Service::new(router);
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("x-no-key", "1", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
+        let (_, proof) = cookie
+            .split_once('=')
+            .and_then(|(_, value)| value.split(';').next())
+            .and_then(|value| value.split_once('.'))
+            .unwrap();
+        assert!(HmacCipher::new([7; 32]).verify(&csrf_token, proof));
+
+        for no_key in [false, true] {
+            let mut req = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", &csrf_token, true)
+                .add_header("cookie", &cookie, true);
+            if no_key {
+                req = req.add_header("x-no-key", "1", true);
+            }
+            let res = req.send(&service).await;
+            assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use salvo_core::{Depot, Error, Request, Response};
+
+use crate::binding::split_proof;
+use crate::{CookieStore, CsrfCipher, CsrfStore, NamespaceError};
+
+/// A `CsrfStore` implementing the double submit cookie pattern with two cookies.
+///
+/// The proof is kept in an `HttpOnly` secret cookie, and the token in a cookie readable by
+/// scripts, which send it back in a header or a form field. A request is valid when the
+/// submitted token matches the secret cookie, so a cross site page, unable to read the
+/// token cookie, can not forge it. Unlike [`Csrf::with_readable_token_cookie`], the token
+/// is not also stored in the secret cookie.
+///
+/// The token cookie holds the token as issued by the cipher, in url-safe base64, so keep
+/// the default [`TokenEncoding`](crate::TokenEncoding) or enable auto detection.
+///
+/// [`Csrf::with_readable_token_cookie`]: crate::Csrf::with_readable_token_cookie
+#[derive(Clone, Debug)]
+pub struct DoubleSubmitStore {
+    secret: CookieStore,
+    token: CookieStore,
+}
+impl Default for DoubleSubmitStore {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DoubleSubmitStore {
+    /// Create a new `DoubleSubmitStore` with a `salvo.csrf` secret cookie and an `XSRF-TOKEN`
+    /// token cookie.
+    pub fn new() -> Self {
+        Self {
+            secret: CookieStore::new(),
+            token: CookieStore::new().name("XSRF-TOKEN").readable(),
+        }
+    }
+
+    /// Sets the store whose name and attributes are used for the secret cookie.
+    #[inline]
+    pub fn with_secret_cookie(mut self, store: CookieStore) -> Self {
+        self.secret = store;
+        self
+    }
+
+    /// Sets the store whose name and attributes are used for the token cookie.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `store` is signed or private, the token cookie must be readable.
+    #[inline]
+    pub fn with_token_cookie(mut self, store: CookieStore) -> Self {
+        self.token = store.readable();
+        self
+    }
+}
+
+impl CsrfStore for DoubleSubmitStore {
+    type Error = Error;
+    fn namespace(&mut self, prefix: &str) -> Result<(), NamespaceError> {
+        self.secret.namespace(prefix)?;
+        self.token.namespace(prefix)
+    }
+    async fn load<C: CsrfCipher>(
+        &self,
+        req: &mut Request,
+        _depot: &mut Depot,
+        cipher: &C,
+    ) -> Option<(String, String)> {
+        let token = self.token.get_value(req)?;
+        let proof = self.secret.get_value(req)?;
+        if cipher.verify(&token, split_proof(&proof).0) {
+            Some((token, proof))
+        } else {
+            None
+        }
+    }
+    async fn save(
+        &self,
+        req: &mut Request,
+        _depot: &mut Depot,
+        res: &mut Response,
+        token: &str,
+        proof: &str,
+    ) -> Result<(), Self::Error> {
+        self.secret.set_value(req, res, proof.to_owned());
+        self.token.set_value(req, res, token.to_owned());
+        Ok(())
+    }
+    async fn clear(
+        &self,
+        req: &mut Request,
+        _depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<(), Self::Error> {
+        self.secret.remove(req, res);
+        self.token.remove(req, res);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "bcrypt-cipher"))]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::TestClient;
+
+    use super::*;
+    use crate::{BcryptCipher, Csrf, HeaderFinder};
+
+    #[handler]
+    async fn post_index() -> &'static str {
+        "POST"
+    }
+
+    #[tokio::test]
+    async fn test_double_submit_store() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            DoubleSubmitStore::new().with_token_cookie(CookieStore::new().name("my-token")),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(post_index).post(post_index);
+        let service = Service::new(router);
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let token = res.cookie("my-token").unwrap().clone();
+        let secret = res.cookie("salvo.csrf").unwrap().clone();
+        assert_ne!(token.http_only(), Some(true));
+        assert_eq!(secret.http_only(), Some(true));
+        assert!(!secret.value().contains(token.value()));
+        let cookies = format!("{}; {}", secret.stripped(), token.stripped());
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", token.value().to_owned(), true)
+            .add_header("cookie", cookies.clone(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("cookie", cookies.clone(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", token.value().to_owned(), true)
+            .add_header("cookie", token.stripped().to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    #[should_panic(expected = "readable cookies can not be signed or private")]
+    fn test_signed_token_cookie() {
+        let key = cookie::Key::generate();
+        DoubleSubmitStore::new().with_token_cookie(CookieStore::signed(key));
+    }
+}
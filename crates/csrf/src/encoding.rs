@@ -0,0 +1,123 @@
+use std::fmt::Write;
+use std::mem;
+use std::ptr;
+
+use base64::engine::general_purpose::{GeneralPurpose, STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+
+/// Encoding of the tokens exposed to and received from clients.
+///
+/// Ciphers always produce url-safe base64 tokens without padding, [`Csrf`](crate::Csrf)
+/// transcodes them to and from the configured encoding.
+#[derive(Copy, Clone, Debug, Default)]
+#[non_exhaustive]
+pub enum TokenEncoding {
+    /// Url-safe base64 without padding.
+    #[default]
+    UrlSafeNoPad,
+    /// Standard base64 with padding.
+    Standard,
+    /// Lowercase hex.
+    Hex,
+    /// Base64 with the given engine, e.g. for a custom alphabet.
+    ///
+    /// Engines are compared by address, so declare them as `static` items:
+    ///
+    /// ```
+    /// use base64::alphabet::CRYPT;
+    /// use base64::engine::general_purpose::{GeneralPurpose, NO_PAD};
+    /// use salvo_csrf::TokenEncoding;
+    ///
+    /// static CRYPT_NO_PAD: GeneralPurpose = GeneralPurpose::new(&CRYPT, NO_PAD);
+    /// let encoding = TokenEncoding::Base64(&CRYPT_NO_PAD);
+    /// ```
+    Base64(&'static GeneralPurpose),
+}
+
+impl PartialEq for TokenEncoding {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Base64(engine), Self::Base64(other)) => ptr::eq(*engine, *other),
+            _ => mem::discriminant(self) == mem::discriminant(other),
+        }
+    }
+}
+impl Eq for TokenEncoding {}
+
+impl TokenEncoding {
+    pub(crate) const ALL: [TokenEncoding; 3] = [Self::UrlSafeNoPad, Self::Standard, Self::Hex];
+
+    /// Encode bytes.
+    pub fn encode(&self, data: &[u8]) -> String {
+        match self {
+            Self::UrlSafeNoPad => URL_SAFE_NO_PAD.encode(data),
+            Self::Standard => STANDARD.encode(data),
+            Self::Hex => data
+                .iter()
+                .fold(String::with_capacity(data.len() * 2), |mut s, b| {
+                    let _ = write!(s, "{b:02x}");
+                    s
+                }),
+            Self::Base64(engine) => engine.encode(data),
+        }
+    }
+
+    /// Decode a string, returns `None` if it is not valid in this encoding.
+    pub fn decode(&self, data: &str) -> Option<Vec<u8>> {
+        match self {
+            Self::UrlSafeNoPad => URL_SAFE_NO_PAD.decode(data).ok(),
+            Self::Standard => STANDARD.decode(data).ok(),
+            Self::Hex => decode_hex(data),
+            Self::Base64(engine) => engine.decode(data).ok(),
+        }
+    }
+}
+
+/// Decodes hex in either case.
+fn decode_hex(data: &str) -> Option<Vec<u8>> {
+    let nibble = |b: u8| char::from(b).to_digit(16).map(|d| d as u8);
+    let pairs = data.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    pairs
+        .map(|pair| Some((nibble(pair[0])? << 4) | nibble(pair[1])?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let data = [0xfb, 0xff, 0x00, 0x10, 0x7f];
+        for encoding in TokenEncoding::ALL {
+            assert_eq!(encoding.decode(&encoding.encode(&data)).unwrap(), data);
+        }
+        assert_eq!(TokenEncoding::Standard.encode(&data), "+/8AEH8=");
+        assert_eq!(TokenEncoding::Hex.encode(&data), "fbff00107f");
+        assert_eq!(TokenEncoding::Hex.decode("+/8AEH8="), None);
+        assert_eq!(TokenEncoding::Hex.decode("FBFF00107F").unwrap(), data);
+        assert_eq!(TokenEncoding::Hex.decode("+f"), None);
+        assert_eq!(TokenEncoding::Hex.decode("fbf"), None);
+    }
+
+    #[test]
+    fn test_base64_engine() {
+        use base64::alphabet::CRYPT;
+        use base64::engine::general_purpose::NO_PAD;
+
+        static CRYPT_NO_PAD: GeneralPurpose = GeneralPurpose::new(&CRYPT, NO_PAD);
+        let encoding = TokenEncoding::Base64(&CRYPT_NO_PAD);
+        let data = [0xfb, 0xff, 0x00, 0x10, 0x7f];
+        assert_eq!(encoding.encode(&data), "yzw.25w");
+        assert_eq!(encoding.decode("yzw.25w").unwrap(), data);
+        assert_eq!(encoding.decode("+/8AEH8="), None);
+
+        assert_eq!(encoding, TokenEncoding::Base64(&CRYPT_NO_PAD));
+        assert_ne!(encoding, TokenEncoding::Base64(&STANDARD));
+        assert_ne!(encoding, TokenEncoding::Standard);
+        assert_eq!(TokenEncoding::Hex, TokenEncoding::Hex);
+    }
+}
@@ -0,0 +1,299 @@
+use std::fmt;
+
+use aead::generic_array::GenericArray;
+use aead::{AeadInPlace, KeyInit};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chacha20poly1305::XChaCha20Poly1305;
+use rand::RngCore;
+use salvo_core::{Depot, Request, Response};
+
+use super::{CsrfCipher, CsrfStore, NamespaceError};
+
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+
+/// Error type for [`EncryptedStore`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum EncryptedStoreError<E>
+where
+    E: std::error::Error + 'static,
+{
+    /// Error from the inner store.
+    #[error("inner csrf store error: {0}")]
+    Store(#[source] E),
+    /// The proof could not be encrypted.
+    #[error("failed to encrypt csrf proof")]
+    Encrypt,
+}
+
+/// A `CsrfStore` decorator encrypting proofs with XChaCha20Poly1305 before the inner store
+/// persists them, so a leaked session store or database alone does not reveal them.
+///
+/// Proofs are bound to their token, and a proof which can not be decrypted, because it was
+/// corrupted or encrypted with another key, is treated as absent. Wrap stores that do not
+/// verify proofs when loading them, like `SessionStore`, `SledStore` or `SqlxStore`; use
+/// `CookieStore::private` to encrypt cookies.
+#[derive(Clone)]
+pub struct EncryptedStore<S> {
+    inner: S,
+    aead: XChaCha20Poly1305,
+}
+
+impl<S: fmt::Debug> fmt::Debug for EncryptedStore<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedStore")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: CsrfStore> EncryptedStore<S> {
+    /// Create a new `EncryptedStore` wrapping `inner`, encrypting proofs with `key`.
+    #[inline]
+    pub fn new(inner: S, key: [u8; 32]) -> Self {
+        Self {
+            inner,
+            aead: XChaCha20Poly1305::new(&GenericArray::from(key)),
+        }
+    }
+
+    /// Get the inner store.
+    #[inline]
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn encrypt(&self, token: &str, proof: &str) -> Option<String> {
+        let mut sealed = vec![0; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut sealed);
+        sealed.extend_from_slice(proof.as_bytes());
+        let (nonce, plain) = sealed.split_at_mut(NONCE_LEN);
+        let tag = self
+            .aead
+            .encrypt_in_place_detached(GenericArray::from_slice(nonce), token.as_bytes(), plain)
+            .ok()?;
+        sealed.extend_from_slice(&tag);
+        Some(URL_SAFE_NO_PAD.encode(sealed))
+    }
+
+    fn decrypt(&self, (token, sealed): (String, String)) -> Option<(String, String)> {
+        let mut sealed = URL_SAFE_NO_PAD.decode(sealed).ok()?;
+        if sealed.len() < NONCE_LEN + TAG_LEN {
+            return None;
+        }
+        let (nonce, sealed) = sealed.split_at_mut(NONCE_LEN);
+        let (plain, tag) = sealed.split_at_mut(sealed.len() - TAG_LEN);
+        if let Err(e) = self.aead.decrypt_in_place_detached(
+            GenericArray::from_slice(nonce),
+            token.as_bytes(),
+            plain,
+            GenericArray::from_slice(tag),
+        ) {
+            tracing::debug!(error = ?e, "failed to decrypt csrf proof");
+            return None;
+        }
+        let proof = String::from_utf8(plain.to_vec()).ok()?;
+        Some((token, proof))
+    }
+}
+
+impl<S: CsrfStore> CsrfStore for EncryptedStore<S> {
+    type Error = EncryptedStoreError<S::Error>;
+    async fn load<C: CsrfCipher>(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        cipher: &C,
+    ) -> Option<(String, String)> {
+        let pair = self.inner.load(req, depot, cipher).await?;
+        self.decrypt(pair)
+    }
+    async fn load_all<C: CsrfCipher>(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        cipher: &C,
+    ) -> Vec<(String, String)> {
+        self.inner
+            .load_all(req, depot, cipher)
+            .await
+            .into_iter()
+            .filter_map(|pair| self.decrypt(pair))
+            .collect()
+    }
+    fn check(&self, depot: &mut Depot) -> Result<(), Self::Error> {
+        self.inner.check(depot).map_err(EncryptedStoreError::Store)
+    }
+    fn namespace(&mut self, prefix: &str) -> Result<(), NamespaceError> {
+        self.inner.namespace(prefix)
+    }
+    fn rotation_due(&self, req: &Request, depot: &Depot) -> bool {
+        self.inner.rotation_due(req, depot)
+    }
+    async fn save(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        token: &str,
+        proof: &str,
+    ) -> Result<(), Self::Error> {
+        let sealed = self
+            .encrypt(token, proof)
+            .ok_or(EncryptedStoreError::Encrypt)?;
+        self.inner
+            .save(req, depot, res, token, &sealed)
+            .await
+            .map_err(EncryptedStoreError::Store)
+    }
+    async fn persist(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<(), Self::Error> {
+        self.inner
+            .persist(req, depot, res)
+            .await
+            .map_err(EncryptedStoreError::Store)
+    }
+    async fn clear(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<(), Self::Error> {
+        self.inner
+            .clear(req, depot, res)
+            .await
+            .map_err(EncryptedStoreError::Store)
+    }
+}
+
+#[cfg(all(test, feature = "bcrypt-cipher"))]
+mod tests {
+    use std::convert::Infallible;
+    use std::sync::{Arc, Mutex};
+
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+    use crate::{BcryptCipher, Csrf, CsrfDepotExt, HeaderFinder};
+
+    #[derive(Clone, Default)]
+    struct MemoryStore(Arc<Mutex<Option<(String, String)>>>);
+    impl MemoryStore {
+        fn get(&self) -> Option<(String, String)> {
+            self.0.lock().unwrap().clone()
+        }
+        fn set(&self, pair: (String, String)) {
+            *self.0.lock().unwrap() = Some(pair);
+        }
+    }
+    impl CsrfStore for MemoryStore {
+        type Error = Infallible;
+        async fn load<C: CsrfCipher>(
+            &self,
+            _req: &mut Request,
+            _depot: &mut Depot,
+            _cipher: &C,
+        ) -> Option<(String, String)> {
+            self.get()
+        }
+        async fn save(
+            &self,
+            _req: &mut Request,
+            _depot: &mut Depot,
+            _res: &mut Response,
+            token: &str,
+            proof: &str,
+        ) -> Result<(), Self::Error> {
+            self.set((token.into(), proof.into()));
+            Ok(())
+        }
+    }
+
+    #[handler]
+    async fn get_index(depot: &mut Depot) -> String {
+        depot.csrf_token().unwrap().to_owned()
+    }
+    #[handler]
+    async fn post_index() -> &'static str {
+        "POST"
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_store() {
+        let inner = MemoryStore::default();
+        let store = EncryptedStore::new(inner.clone(), [1; 32]);
+        let cipher = BcryptCipher::new().cost(4);
+        let (token, proof) = cipher.generate();
+        let mut req = Request::new();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+
+        store
+            .save(&mut req, &mut depot, &mut res, &token, &proof)
+            .await
+            .unwrap();
+        let (stored_token, sealed) = inner.get().unwrap();
+        assert_eq!(stored_token, token);
+        assert_ne!(sealed, proof);
+        assert!(!sealed.contains(&proof));
+        let loaded = store.load(&mut req, &mut depot, &cipher).await;
+        assert_eq!(loaded, Some((token.clone(), proof.clone())));
+
+        let other_key = EncryptedStore::new(inner.clone(), [2; 32]);
+        assert_eq!(other_key.load(&mut req, &mut depot, &cipher).await, None);
+
+        let mut corrupted = URL_SAFE_NO_PAD.decode(&sealed).unwrap();
+        let last = corrupted.len() - 1;
+        corrupted[NONCE_LEN] ^= 1;
+        corrupted[last] ^= 1;
+        for sealed in [
+            URL_SAFE_NO_PAD.encode(&corrupted),
+            sealed[..sealed.len() - 4].to_owned(),
+            "".to_owned(),
+        ] {
+            inner.set((token.clone(), sealed));
+            assert_eq!(store.load(&mut req, &mut depot, &cipher).await, None);
+        }
+
+        let (other_token, _) = cipher.generate();
+        inner.set((other_token, sealed));
+        assert_eq!(store.load(&mut req, &mut depot, &cipher).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_csrf_with_encrypted_store() {
+        let inner = MemoryStore::default();
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            EncryptedStore::new(inner.clone(), [1; 32]),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let token = res.take_string().await.unwrap();
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", &token, true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let (token, _) = inner.get().unwrap();
+        inner.set((token.clone(), "corrupted".into()));
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", &token, true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use salvo_core::extract::{Extractible, Metadata};
+use salvo_core::http::StatusError;
+use salvo_core::Request;
+
+/// The current csrf token, extracted in handlers as an argument instead of calling
+/// [`CsrfDepotExt::csrf_token`](crate::CsrfDepotExt::csrf_token):
+///
+/// ```ignore
+/// #[handler]
+/// async fn form(token: CsrfToken) -> Text<String> {
+///     Text::Html(format!(r#"<input type="hidden" name="csrf-token" value="{token}">"#))
+/// }
+/// ```
+///
+/// Extractors can not read the depot, so [`Csrf`](crate::Csrf) also inserts the token into the
+/// request extensions. The extractor reads the token of the `Csrf` without a namespace, the
+/// innermost one when several are hooped. Instances configured with
+/// [`Csrf::with_namespace`](crate::Csrf::with_namespace) keep their token apart, read it with
+/// [`CsrfToken::with_namespace`]. Extracting fails with `500` if no `Csrf` is hooped.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct CsrfToken(pub String);
+
+impl CsrfToken {
+    /// Get the token as a string slice.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Get the token of the `Csrf` configured with
+    /// [`Csrf::with_namespace`](crate::Csrf::with_namespace), extractors only read the token of
+    /// the `Csrf` without a namespace.
+    #[inline]
+    pub fn with_namespace<'a>(req: &'a Request, namespace: &str) -> Option<&'a CsrfToken> {
+        req.extensions().get::<NamespacedTokens>()?.0.get(namespace)
+    }
+
+    /// Consumes the extractor, returning the token.
+    #[inline]
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+/// Tokens of the [`Csrf`](crate::Csrf) instances configured with a namespace.
+#[derive(Clone, Default)]
+struct NamespacedTokens(HashMap<Arc<str>, CsrfToken>);
+
+/// Inserts the token of a `Csrf` into the request extensions, under its namespace if any.
+pub(crate) fn insert_token(req: &mut Request, namespace: Option<Arc<str>>, token: CsrfToken) {
+    match namespace {
+        Some(namespace) => {
+            let extensions = req.extensions_mut();
+            if extensions.get::<NamespacedTokens>().is_none() {
+                extensions.insert(NamespacedTokens::default());
+            }
+            if let Some(tokens) = extensions.get_mut::<NamespacedTokens>() {
+                tokens.0.insert(namespace, token);
+            }
+        }
+        None => {
+            req.extensions_mut().insert(token);
+        }
+    }
+}
+
+pub(crate) fn remove_token(req: &mut Request, namespace: Option<&str>) {
+    match namespace {
+        Some(namespace) => {
+            if let Some(tokens) = req.extensions_mut().get_mut::<NamespacedTokens>() {
+                tokens.0.remove(namespace);
+            }
+        }
+        None => {
+            req.extensions_mut().remove::<CsrfToken>();
+        }
+    }
+}
+
+impl Deref for CsrfToken {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for CsrfToken {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<'ex> Extractible<'ex> for CsrfToken {
+    fn metadata() -> &'ex Metadata {
+        static METADATA: Metadata = Metadata::new("CsrfToken");
+        &METADATA
+    }
+    #[allow(refining_impl_trait)]
+    async fn extract(req: &'ex mut Request) -> Result<Self, StatusError> {
+        req.extensions().get::<CsrfToken>().cloned().ok_or_else(|| {
+            tracing::error!("csrf token not found in request, is `Csrf` middleware hooped?");
+            StatusError::internal_server_error()
+        })
+    }
+}
+
+#[cfg(feature = "oapi")]
+impl salvo_oapi::EndpointArgRegister for CsrfToken {
+    fn register(
+        _components: &mut salvo_oapi::Components,
+        _operation: &mut salvo_oapi::Operation,
+        _arg: &str,
+    ) {
+    }
+}
+
+#[cfg(all(test, feature = "cookie-store", feature = "bcrypt-cipher"))]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+    use crate::{BcryptCipher, CookieStore, Csrf, CsrfDepotExt, HeaderFinder};
+
+    #[handler]
+    async fn get_token(token: CsrfToken, depot: &mut Depot) -> String {
+        assert_eq!(depot.csrf_token(), Some(token.as_str()));
+        token.into_inner()
+    }
+    #[handler]
+    async fn post_token(token: CsrfToken) -> String {
+        token.to_string()
+    }
+    #[handler]
+    async fn rotate_token(req: &mut Request, depot: &mut Depot, res: &mut Response) -> String {
+        depot.rotate_csrf(req, res).await.unwrap();
+        CsrfToken::extract(req).await.unwrap().into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_csrf_token_extractor() {
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new()
+            .push(
+                Router::new()
+                    .hoop(csrf)
+                    .get(get_token)
+                    .post(post_token)
+                    .push(Router::with_path("rotate").post(rotate_token)),
+            )
+            .push(Router::with_path("unprotected").get(get_token));
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let token = res.take_string().await.unwrap();
+        assert!(!token.is_empty());
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
+
+        let mut res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", &token, true)
+            .add_header("cookie", &cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), token);
+
+        let mut res = TestClient::post("http://127.0.0.1:5801/rotate")
+            .add_header("x-csrf-token", &token, true)
+            .add_header("cookie", &cookie, true)
+            .send(&service)
+            .await;
+        let rotated = res.take_string().await.unwrap();
+        assert!(!rotated.is_empty());
+        assert_ne!(rotated, token);
+
+        let res = TestClient::get("http://127.0.0.1:5801/unprotected")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_csrf_token_with_namespace() {
+        #[handler]
+        async fn get_tokens(req: &mut Request) -> String {
+            let admin = CsrfToken::with_namespace(req, "admin").unwrap().clone();
+            let default = CsrfToken::extract(req).await.unwrap();
+            assert_ne!(admin, default);
+            assert!(CsrfToken::with_namespace(req, "other").is_none());
+            format!("{admin} {default}")
+        }
+
+        let csrf = || {
+            Csrf::new(
+                BcryptCipher::new().cost(4),
+                CookieStore::new(),
+                HeaderFinder::new("x-csrf-token"),
+            )
+        };
+        let router = Router::new()
+            .hoop(csrf())
+            .hoop(csrf().with_namespace("admin"))
+            .get(get_tokens);
+        let mut res = TestClient::get("http://127.0.0.1:5801").send(router).await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let tokens = res.take_string().await.unwrap();
+        let (admin, default) = tokens.split_once(' ').unwrap();
+        assert!(!admin.is_empty() && !default.is_empty());
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt;
+use std::sync::Arc;
+
+use rand::RngCore;
+use salvo_core::Depot;
+
+use super::{CsrfCipher, CsrfVerifyError};
+
+/// A cipher generating tokens with a primary cipher, and also verifying them with fallback
+/// ciphers.
+///
+/// This keeps tokens issued by previous ciphers validating while migrating to a new one, see
+/// [`Csrf::migrate_cipher`](crate::Csrf::migrate_cipher). Stores verifying the proofs they
+/// load, like `CookieStore`, also accept the previous tokens. Remove the fallbacks once the
+/// previous tokens expired.
+#[derive(Clone)]
+pub struct FallbackCipher<C> {
+    primary: C,
+    fallback_ciphers: Vec<Arc<dyn CsrfCipher>>,
+}
+
+impl<C: fmt::Debug> fmt::Debug for FallbackCipher<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FallbackCipher")
+            .field("primary", &self.primary)
+            .field("fallback_ciphers", &self.fallback_ciphers.len())
+            .finish()
+    }
+}
+
+impl<C: CsrfCipher> FallbackCipher<C> {
+    /// Create a new `FallbackCipher` with `primary` and no fallback.
+    #[inline]
+    pub fn new(primary: C) -> Self {
+        Self {
+            primary,
+            fallback_ciphers: vec![],
+        }
+    }
+
+    /// Adds a cipher tokens are also verified with, after the primary and previous fallbacks.
+    #[inline]
+    pub fn add_fallback(mut self, cipher: impl CsrfCipher) -> Self {
+        self.fallback_ciphers.push(Arc::new(cipher));
+        self
+    }
+
+    pub(crate) fn push_fallback(&mut self, cipher: Arc<dyn CsrfCipher>) {
+        self.fallback_ciphers.push(cipher);
+    }
+
+    /// Get the primary cipher.
+    #[inline]
+    pub fn primary(&self) -> &C {
+        &self.primary
+    }
+
+    /// Get the number of fallback ciphers.
+    #[inline]
+    pub fn fallback_count(&self) -> usize {
+        self.fallback_ciphers.len()
+    }
+}
+
+impl<C: CsrfCipher> CsrfCipher for FallbackCipher<C> {
+    fn prepare(&self, depot: &Depot) -> bool {
+        self.primary.prepare(depot)
+            && self
+                .fallback_ciphers
+                .iter()
+                .all(|cipher| cipher.prepare(depot))
+    }
+    fn verify(&self, token: &str, proof: &str) -> bool {
+        self.primary.verify(token, proof)
+            || self
+                .fallback_ciphers
+                .iter()
+                .any(|cipher| cipher.verify(token, proof))
+    }
+    fn verify_detailed(&self, token: &str, proof: &str) -> Result<(), CsrfVerifyError> {
+        // A mismatch is reported over a malformed input, as the token suits that cipher.
+        let mut result = self.primary.verify_detailed(token, proof);
+        for cipher in &self.fallback_ciphers {
+            if result.is_ok() {
+                break;
+            }
+            match cipher.verify_detailed(token, proof) {
+                Ok(()) => result = Ok(()),
+                Err(CsrfVerifyError::Invalid) => result = Err(CsrfVerifyError::Invalid),
+                Err(CsrfVerifyError::Malformed) => {}
+            }
+        }
+        result
+    }
+    fn verify_raw(&self, token: &[u8], proof: &[u8]) -> bool {
+        self.primary.verify_raw(token, proof)
+            || self
+                .fallback_ciphers
+                .iter()
+                .any(|cipher| cipher.verify_raw(token, proof))
+    }
+    fn min_token_len(&self) -> usize {
+        self.fallback_ciphers
+            .iter()
+            .map(|cipher| cipher.min_token_len())
+            .fold(self.primary.min_token_len(), usize::min)
+    }
+    fn generate(&self) -> (String, String) {
+        self.primary.generate()
+    }
+    fn generate_with_rng(&self, rng: &mut dyn RngCore) -> (String, String) {
+        self.primary.generate_with_rng(rng)
+    }
+    fn supports_aad(&self) -> bool {
+        self.primary.supports_aad()
+    }
+    fn verify_with_aad(&self, token: &str, proof: &str, aad: &[u8]) -> bool {
+        self.primary.verify_with_aad(token, proof, aad)
+            || self
+                .fallback_ciphers
+                .iter()
+                .any(|cipher| cipher.verify_with_aad(token, proof, aad))
+    }
+    fn generate_with_aad(&self, aad: &[u8]) -> (String, String) {
+        self.primary.generate_with_aad(aad)
+    }
+}
+
+#[cfg(all(test, feature = "hmac-cipher", feature = "cookie-store"))]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+    use crate::{CookieStore, Csrf, CsrfDepotExt, HeaderFinder, HmacCipher};
+
+    #[handler]
+    async fn get_index(depot: &mut Depot) -> String {
+        depot.csrf_token().unwrap().to_owned()
+    }
+    #[handler]
+    async fn post_index() -> &'static str {
+        "POST"
+    }
+
+    #[test]
+    fn test_fallback_cipher() {
+        let old = HmacCipher::new([1; 32]);
+        let new = HmacCipher::new([2; 32]);
+        let (old_token, old_proof) = old.generate();
+        let cipher = FallbackCipher::new(new.clone()).add_fallback(old.clone());
+        assert_eq!(cipher.fallback_count(), 1);
+        assert!(cipher.verify(&old_token, &old_proof));
+
+        let (token, proof) = cipher.generate();
+        assert!(new.verify(&token, &proof));
+        assert!(!old.verify(&token, &proof));
+        assert!(!FallbackCipher::new(new).verify(&old_token, &old_proof));
+    }
+
+    #[tokio::test]
+    async fn test_migrate_cipher() {
+        let csrf = Csrf::new(
+            HmacCipher::new([1; 32]),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let old_service = Service::new(
+            Router::new()
+                .hoop(csrf.clone())
+                .get(get_index)
+                .post(post_index),
+        );
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&old_service)
+            .await;
+        let old_token = res.take_string().await.unwrap();
+        let old_cookie = res.cookie("salvo.csrf").unwrap().to_string();
+
+        let csrf = csrf.migrate_cipher(HmacCipher::new([2; 32]));
+        let new_service = Service::new(Router::new().hoop(csrf).get(get_index).post(post_index));
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", &old_token, true)
+            .add_header("cookie", &old_cookie, true)
+            .send(&new_service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&new_service)
+            .await;
+        let new_token = res.take_string().await.unwrap();
+        let new_cookie = res.cookie("salvo.csrf").unwrap().to_string();
+        for (service, status) in [
+            (&new_service, StatusCode::OK),
+            (&old_service, StatusCode::FORBIDDEN),
+        ] {
+            let res = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", &new_token, true)
+                .add_header("cookie", &new_cookie, true)
+                .send(service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), status);
+        }
+    }
+}
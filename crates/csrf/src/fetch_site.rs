@@ -0,0 +1,61 @@
+use salvo_core::Request;
+
+use super::CsrfRejectReason;
+
+/// How [`Csrf`](crate::Csrf) uses the `Sec-Fetch-Site` request header on protected requests.
+///
+/// Requests with `same-origin`, `same-site` or `none` (user initiated, e.g. typed url) are
+/// treated as same site, `cross-site` requests are rejected.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+#[non_exhaustive]
+pub enum FetchSiteMode {
+    /// Ignore the header, only tokens are validated.
+    #[default]
+    Disabled,
+    /// Reject cross site requests, then validate the token as usual.
+    WithToken,
+    /// Accept same site requests without validating the token.
+    Standalone,
+}
+
+/// What [`Csrf`](crate::Csrf) does when the `Sec-Fetch-Site` header is absent, which is the
+/// case for older clients.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+#[non_exhaustive]
+pub enum MissingFetchSite {
+    /// Validate the token as usual.
+    #[default]
+    ValidateToken,
+    /// Reject the request.
+    Reject,
+}
+
+pub(crate) enum FetchSiteCheck {
+    Accept,
+    ValidateToken,
+    Reject(CsrfRejectReason),
+}
+
+pub(crate) fn check_fetch_site(
+    req: &Request,
+    mode: FetchSiteMode,
+    missing: MissingFetchSite,
+) -> FetchSiteCheck {
+    if mode == FetchSiteMode::Disabled {
+        return FetchSiteCheck::ValidateToken;
+    }
+    match req.header::<String>("sec-fetch-site").as_deref() {
+        Some("same-origin" | "same-site" | "none") => {
+            if mode == FetchSiteMode::Standalone {
+                FetchSiteCheck::Accept
+            } else {
+                FetchSiteCheck::ValidateToken
+            }
+        }
+        Some(_) => FetchSiteCheck::Reject(CsrfRejectReason::CrossSite),
+        None => match missing {
+            MissingFetchSite::ValidateToken => FetchSiteCheck::ValidateToken,
+            MissingFetchSite::Reject => FetchSiteCheck::Reject(CsrfRejectReason::MissingFetchSite),
+        },
+    }
+}
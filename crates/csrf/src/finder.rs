@@ -1,6 +1,9 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
 
-use salvo_core::{async_trait, Request};
+use salvo_core::http::mime;
+use salvo_core::{async_trait, Depot, Request};
 use serde_json::Value;
 
 /// Used to find csrf token from request.
@@ -8,15 +11,65 @@ use serde_json::Value;
 pub trait CsrfTokenFinder: Send + Sync + 'static {
     /// Find token from request.
     async fn find_token(&self, req: &mut Request) -> Option<String>;
+
+    /// Find all candidate tokens from request, e.g. when a header is sent several times.
+    ///
+    /// [`Csrf`](crate::Csrf) accepts the request if any of them is valid. The default
+    /// implementation returns the result of [`find_token`](Self::find_token).
+    async fn find_tokens(&self, req: &mut Request) -> Vec<String> {
+        self.find_token(req).await.into_iter().collect()
+    }
+
+    /// Find all candidate tokens from the request or the depot, e.g. a token stored by an
+    /// upstream middleware, see [`DepotFinder`].
+    ///
+    /// [`Csrf`](crate::Csrf) calls this method. The default implementation ignores the depot
+    /// and returns the result of [`find_tokens`](Self::find_tokens).
+    async fn find_tokens_with_depot(&self, req: &mut Request, depot: &Depot) -> Vec<String> {
+        let _ = depot;
+        self.find_tokens(req).await
+    }
+
+    /// Name of the header this finder reads the token from, if it reads from a header.
+    fn header_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Type name of the finder, shown in the `Debug` output of [`Csrf`](crate::Csrf).
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// Finders reading the token from the `header_name` header, then the `field_name` form field,
+/// then the `field_name` query parameter.
+///
+/// Pass them to [`Csrf::with_finders`](crate::Csrf::with_finders).
+///
+/// # Panics
+///
+/// Panics if `field_name` is empty.
+pub fn default_finders(header_name: &str, field_name: &str) -> Vec<Box<dyn CsrfTokenFinder>> {
+    vec![
+        Box::new(HeaderFinder::new(header_name)),
+        Box::new(FormFinder::new(field_name)),
+        Box::new(QueryFinder::new().with_query_name(field_name)),
+    ]
 }
 
 /// Find token from http request header.
+///
+/// Values are trimmed of surrounding whitespace. When the header is sent several times, or
+/// its values are joined with commas by a proxy, every non-empty value is a candidate and
+/// [`find_token`](CsrfTokenFinder::find_token) returns the first one.
 #[derive(Clone, Debug)]
 pub struct HeaderFinder {
     header_name: String,
 }
 impl HeaderFinder {
     /// Create new `HeaderFinder`, you can use value like `x-csrf-token`.
+    ///
+    /// Header names are matched ignoring case, so `X-CSRF-Token` finds `x-csrf-token`.
     #[inline]
     pub fn new(header_name: impl Into<String>) -> Self {
         Self {
@@ -28,7 +81,24 @@ impl HeaderFinder {
 impl CsrfTokenFinder for HeaderFinder {
     #[inline]
     async fn find_token(&self, req: &mut Request) -> Option<String> {
-        req.header(&self.header_name)
+        self.find_tokens(req).await.into_iter().next()
+    }
+
+    async fn find_tokens(&self, req: &mut Request) -> Vec<String> {
+        req.headers()
+            .get_all(&self.header_name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+
+    #[inline]
+    fn header_name(&self) -> Option<&str> {
+        Some(&self.header_name)
     }
 }
 
@@ -36,6 +106,8 @@ impl CsrfTokenFinder for HeaderFinder {
 #[derive(Clone, Debug)]
 pub struct FormFinder {
     field_name: String,
+    case_insensitive: bool,
+    query_fallback: Option<QueryFinder>,
 }
 impl FormFinder {
     /// Create new `FormFinder`.
@@ -43,43 +115,384 @@ impl FormFinder {
     pub fn new(field_name: impl Into<String>) -> Self {
         Self {
             field_name: field_name.into(),
+            case_insensitive: false,
+            query_fallback: None,
         }
     }
+
+    /// Also finds the token in the `query_name` query parameter when the body is not a form,
+    /// e.g. a JSON body or no body at all, like a [`QueryFinder`] does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `query_name` is empty.
+    #[inline]
+    pub fn with_query_fallback(mut self, query_name: impl Into<String>) -> Self {
+        self.query_fallback = Some(QueryFinder::new().with_query_name(query_name));
+        self
+    }
+
+    /// Sets whether the field name is matched ignoring ASCII case, e.g. `CSRF-Token` for
+    /// `csrf-token`, defaults to `false`.
+    #[inline]
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
 }
 #[async_trait]
 impl CsrfTokenFinder for FormFinder {
     #[inline]
     async fn find_token(&self, req: &mut Request) -> Option<String> {
-        req.form(&self.field_name).await
+        if let Some(query) = &self.query_fallback {
+            let form = req.content_type().is_some_and(|ctype| {
+                ctype.essence_str() == mime::APPLICATION_WWW_FORM_URLENCODED.essence_str()
+                    || ctype.essence_str() == mime::MULTIPART_FORM_DATA.essence_str()
+            });
+            if !form {
+                return query.find_token(req).await;
+            }
+        }
+        if !self.case_insensitive {
+            return req.form(&self.field_name).await;
+        }
+        req.form_data()
+            .await
+            .ok()?
+            .fields
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&self.field_name))
+            .map(|(_, value)| value.clone())
+    }
+}
+
+/// Error returned when a finder is configured with an empty name.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, thiserror::Error)]
+#[error("csrf token finder name must not be empty")]
+pub struct EmptyNameError;
+
+/// Find token from request query string.
+///
+/// The parameter is named `csrf-token` unless set with
+/// [`with_query_name`](Self::with_query_name). The value is percent-decoded, and a `+` sent
+/// without encoding is kept as `+` instead of being decoded as a space, since tokens never
+/// contain spaces. No token is found when the parameter is absent or empty.
+#[derive(Clone, Debug)]
+pub struct QueryFinder {
+    query_name: String,
+}
+impl Default for QueryFinder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl QueryFinder {
+    /// Create new `QueryFinder` which finds token from the `csrf-token` query parameter.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            query_name: "csrf-token".into(),
+        }
+    }
+
+    /// Sets the query parameter name, defaults to `csrf-token`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `query_name` is empty, use [`try_with_query_name`](Self::try_with_query_name)
+    /// for names read from configuration.
+    #[inline]
+    pub fn with_query_name(self, query_name: impl Into<String>) -> Self {
+        self.try_with_query_name(query_name)
+            .expect("query name must not be empty")
+    }
+
+    /// Sets the query parameter name, failing if it is empty.
+    #[inline]
+    pub fn try_with_query_name(
+        mut self,
+        query_name: impl Into<String>,
+    ) -> Result<Self, EmptyNameError> {
+        let query_name = query_name.into();
+        if query_name.is_empty() {
+            return Err(EmptyNameError);
+        }
+        self.query_name = query_name;
+        Ok(self)
+    }
+}
+#[async_trait]
+impl CsrfTokenFinder for QueryFinder {
+    #[inline]
+    async fn find_token(&self, req: &mut Request) -> Option<String> {
+        self.find_tokens(req).await.into_iter().next()
+    }
+
+    /// Returns the non-empty values of the parameter when it is repeated, e.g. in
+    /// `?csrf-token=&csrf-token=...`, [`Csrf`](crate::Csrf) accepts any valid one.
+    async fn find_tokens(&self, req: &mut Request) -> Vec<String> {
+        let Some(values) = req.queries().get_vec(&self.query_name) else {
+            return vec![];
+        };
+        if let [token] = values.as_slice() {
+            return if token.is_empty() {
+                vec![]
+            } else {
+                vec![token.replace(' ', "+")]
+            };
+        }
+        values
+            .iter()
+            .filter(|token| !token.is_empty())
+            .map(|token| token.replace(' ', "+"))
+            .collect()
+    }
+}
+
+/// Find token from a router path parameter, e.g. `<csrf>` in `/action/<csrf>/submit`.
+#[derive(Clone, Debug)]
+pub struct PathParamFinder {
+    param_name: String,
+}
+impl PathParamFinder {
+    /// Create new `PathParamFinder`.
+    #[inline]
+    pub fn new(param_name: impl Into<String>) -> Self {
+        Self {
+            param_name: param_name.into(),
+        }
+    }
+}
+#[async_trait]
+impl CsrfTokenFinder for PathParamFinder {
+    #[inline]
+    async fn find_token(&self, req: &mut Request) -> Option<String> {
+        req.param::<String>(&self.param_name)
+    }
+}
+
+/// Find token from a `String` stored in the depot under a key by an upstream middleware which
+/// already extracted it, e.g. while parsing a custom body format.
+///
+/// ```ignore
+/// #[handler]
+/// async fn parse_token(req: &mut Request, depot: &mut Depot) {
+///     if let Some(token) = parse_custom_body(req).await {
+///         depot.insert("parsed.csrf.token", token);
+///     }
+/// }
+///
+/// let csrf = Csrf::new(cipher, store, DepotFinder::new("parsed.csrf.token"));
+/// let router = Router::new().hoop(parse_token).hoop(csrf);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DepotFinder {
+    key: String,
+}
+impl DepotFinder {
+    /// Create new `DepotFinder` reading the token stored under `key`.
+    #[inline]
+    pub fn new(key: impl Into<String>) -> Self {
+        Self { key: key.into() }
+    }
+}
+#[async_trait]
+impl CsrfTokenFinder for DepotFinder {
+    /// Always `None`, the token is only read from the depot.
+    #[inline]
+    async fn find_token(&self, _req: &mut Request) -> Option<String> {
+        None
+    }
+    async fn find_tokens_with_depot(&self, _req: &mut Request, depot: &Depot) -> Vec<String> {
+        depot
+            .get::<String>(&self.key)
+            .ok()
+            .map(|token| token.trim())
+            .filter(|token| !token.is_empty())
+            .map(|token| token.to_owned())
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Find token from a request extension of type `T`, inserted by an upstream middleware which
+/// already extracted it, e.g. while parsing a custom body format.
+///
+/// Like [`DepotFinder`], but the upstream middleware inserts the token with
+/// `req.extensions_mut().insert(...)`, wrapped in its own type:
+///
+/// ```ignore
+/// #[derive(Clone)]
+/// struct ParsedToken(String);
+/// impl AsRef<str> for ParsedToken {
+///     fn as_ref(&self) -> &str {
+///         &self.0
+///     }
+/// }
+///
+/// let finder = ExtensionFinder::<ParsedToken>::new();
+/// ```
+pub struct ExtensionFinder<T> {
+    _marker: PhantomData<fn() -> T>,
+}
+impl<T> ExtensionFinder<T> {
+    /// Create new `ExtensionFinder`.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            _marker: PhantomData,
+        }
+    }
+}
+impl<T> Default for ExtensionFinder<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T> Clone for ExtensionFinder<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+impl<T> fmt::Debug for ExtensionFinder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtensionFinder")
+            .field("extension", &std::any::type_name::<T>())
+            .finish()
+    }
+}
+#[async_trait]
+impl<T> CsrfTokenFinder for ExtensionFinder<T>
+where
+    T: AsRef<str> + Send + Sync + 'static,
+{
+    #[inline]
+    async fn find_token(&self, req: &mut Request) -> Option<String> {
+        let token = req.extensions().get::<T>()?.as_ref().trim();
+        (!token.is_empty()).then(|| token.to_owned())
     }
 }
 
 /// Find token from request json body.
+///
+/// The body is read when the media type is `application/json` or has a `+json` suffix, like
+/// `application/vnd.api+json`. Parameters such as `charset=utf-8` are ignored.
+///
+/// Bodies larger than [`max_body_size`](Self::max_body_size) are not read, so the token is
+/// not found and the request is rejected.
 #[derive(Clone, Debug)]
 pub struct JsonFinder {
     field_name: String,
+    max_body_size: usize,
 }
 impl JsonFinder {
-    /// Create new `FormFinder`.
+    /// Create new `JsonFinder`.
     #[inline]
     pub fn new(field_name: impl Into<String>) -> Self {
         Self {
             field_name: field_name.into(),
+            max_body_size: 16 * 1024,
         }
     }
+
+    /// Sets the maximum size in bytes of the body read to find the token, defaults to 16 KiB.
+    #[inline]
+    pub fn max_body_size(mut self, size: usize) -> Self {
+        self.max_body_size = size;
+        self
+    }
 }
 #[async_trait]
 impl CsrfTokenFinder for JsonFinder {
     async fn find_token(&self, req: &mut Request) -> Option<String> {
-        let data = req.parse_json::<HashMap<String, Value>>().await;
-        if let Ok(data) = data {
-            if let Some(value) = data.get(&self.field_name) {
-                if let Some(token) = value.as_str() {
-                    return Some(token.to_owned());
-                }
-            }
+        let ctype = req.content_type()?;
+        if ctype.subtype() != mime::JSON && ctype.suffix() != Some(mime::JSON) {
+            return None;
+        }
+        let payload = read_body(req, self.max_body_size).await?;
+        let data = serde_json::from_slice::<HashMap<String, Value>>(payload).ok()?;
+        data.get(&self.field_name)?.as_str().map(ToOwned::to_owned)
+    }
+}
+
+/// Find token from the JSON body of a GraphQL request, in a variable or an extension.
+///
+/// The body is the usual `{"query": "...", "variables": {...}, "extensions": {...}}`
+/// envelope, with the same content types as [`JsonFinder`]. The token is read from
+/// `variables.<variable_name>`, then from `extensions.<extension_name>` if configured.
+/// The body stays available to the GraphQL handler.
+#[derive(Clone, Debug)]
+pub struct GraphQLFinder {
+    variable_name: Option<String>,
+    extension_name: Option<String>,
+    max_body_size: usize,
+}
+impl GraphQLFinder {
+    /// Create new `GraphQLFinder` reading the token from the `variable_name` variable.
+    #[inline]
+    pub fn new(variable_name: impl Into<String>) -> Self {
+        Self {
+            variable_name: Some(variable_name.into()),
+            extension_name: None,
+            max_body_size: 64 * 1024,
+        }
+    }
+
+    /// Create new `GraphQLFinder` reading the token from the `extension_name` extension only.
+    #[inline]
+    pub fn from_extension(extension_name: impl Into<String>) -> Self {
+        Self {
+            variable_name: None,
+            extension_name: Some(extension_name.into()),
+            max_body_size: 64 * 1024,
+        }
+    }
+
+    /// Also reads the token from the `extension_name` extension when the variable is absent.
+    #[inline]
+    pub fn extension_name(mut self, extension_name: impl Into<String>) -> Self {
+        self.extension_name = Some(extension_name.into());
+        self
+    }
+
+    /// Sets the maximum size in bytes of the body read to find the token, defaults to 64 KiB.
+    #[inline]
+    pub fn max_body_size(mut self, size: usize) -> Self {
+        self.max_body_size = size;
+        self
+    }
+}
+#[async_trait]
+impl CsrfTokenFinder for GraphQLFinder {
+    async fn find_token(&self, req: &mut Request) -> Option<String> {
+        let ctype = req.content_type()?;
+        if ctype.subtype() != mime::JSON && ctype.suffix() != Some(mime::JSON) {
+            return None;
+        }
+        let payload = read_body(req, self.max_body_size).await?;
+        let data = serde_json::from_slice::<HashMap<String, Value>>(payload).ok()?;
+        let field = |object: &str, name: &Option<String>| {
+            data.get(object)?
+                .get(name.as_deref()?)?
+                .as_str()
+                .map(ToOwned::to_owned)
+        };
+        field("variables", &self.variable_name)
+            .or_else(|| field("extensions", &self.extension_name))
+    }
+}
+
+/// Reads at most `max_size` bytes of the body, it stays available to later handlers.
+pub(crate) async fn read_body(req: &mut Request, max_size: usize) -> Option<&[u8]> {
+    match req.payload_with_max_size(max_size).await {
+        Ok(payload) => Some(payload),
+        Err(e) => {
+            tracing::debug!(error = ?e, "failed to read body to find csrf token");
+            None
         }
-        None
     }
 }
 
@@ -98,6 +511,48 @@ mod tests {
         assert_eq!(token, Some("test_token".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_header_finder_case_insensitive() {
+        for (name, sent) in [
+            ("X-CSRF-Token", "x-csrf-token"),
+            ("x-csrf-token", "X-Csrf-Token"),
+            ("X-XSRF-TOKEN", "x-xsrf-token"),
+        ] {
+            let header_finder = HeaderFinder::new(name);
+            let mut req = TestClient::get("http://test.com")
+                .add_header(sent, "test_token", true)
+                .build();
+            let token = header_finder.find_token(&mut req).await;
+            assert_eq!(token, Some("test_token".to_string()), "{name} / {sent}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_header_finder_trims_whitespace() {
+        let header_finder = HeaderFinder::new("x-csrf-token");
+        let mut req = TestClient::get("http://test.com")
+            .add_header("x-csrf-token", "  test_token\t", true)
+            .build();
+        let token = header_finder.find_token(&mut req).await;
+        assert_eq!(token, Some("test_token".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_header_finder_multiple_values() {
+        let header_finder = HeaderFinder::new("x-csrf-token");
+        let mut req = TestClient::get("http://test.com")
+            .add_header("x-csrf-token", " ", false)
+            .add_header("x-csrf-token", "first, second", false)
+            .add_header("x-csrf-token", "third", false)
+            .build();
+        assert_eq!(
+            header_finder.find_tokens(&mut req).await,
+            ["first", "second", "third"]
+        );
+        let token = header_finder.find_token(&mut req).await;
+        assert_eq!(token, Some("first".to_string()));
+    }
+
     #[tokio::test]
     async fn test_form_finder() {
         let form_finder = FormFinder::new("csrf-token");
@@ -108,6 +563,205 @@ mod tests {
         assert_eq!(token, Some("test_token".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_form_finder_query_fallback() {
+        let form_finder = FormFinder::new("csrf-token").with_query_fallback("csrf");
+        let mut req = TestClient::post("http://test.com?csrf=query_token")
+            .json(&serde_json::json!({"csrf-token": "json_token"}))
+            .build();
+        let token = form_finder.find_token(&mut req).await;
+        assert_eq!(token, Some("query_token".to_string()));
+
+        let mut req = TestClient::post("http://test.com?csrf=query_token").build();
+        let token = form_finder.find_token(&mut req).await;
+        assert_eq!(token, Some("query_token".to_string()));
+
+        let mut req = TestClient::post("http://test.com?csrf=query_token")
+            .form(&[("csrf-token", "form_token")])
+            .build();
+        let token = form_finder.find_token(&mut req).await;
+        assert_eq!(token, Some("form_token".to_string()));
+
+        let mut req = TestClient::post("http://test.com?csrf=query_token").build();
+        assert_eq!(
+            FormFinder::new("csrf-token").find_token(&mut req).await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_form_finder_case_insensitive() {
+        let mut req = TestClient::post("http://test.com")
+            .raw_form("a=1&CSRF-Token=test_token")
+            .build();
+        let form_finder = FormFinder::new("csrf-token");
+        assert_eq!(form_finder.find_token(&mut req).await, None);
+
+        let form_finder = form_finder.case_insensitive(true);
+        let token = form_finder.find_token(&mut req).await;
+        assert_eq!(token, Some("test_token".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_query_finder() {
+        let query_finder = QueryFinder::new();
+        let mut req = TestClient::get("http://test.com?csrf-token=a%2Bb%2Fc%3D%3D").build();
+        let token = query_finder.find_token(&mut req).await;
+        assert_eq!(token, Some("a+b/c==".to_string()));
+
+        let mut req = TestClient::get("http://test.com?csrf-token=a+b").build();
+        let token = query_finder.find_token(&mut req).await;
+        assert_eq!(token, Some("a+b".to_string()));
+
+        let query_finder = QueryFinder::new().with_query_name("my-csrf-token");
+        let token = query_finder.find_token(&mut req).await;
+        assert_eq!(token, None);
+    }
+
+    #[tokio::test]
+    async fn test_query_finder_name() {
+        let mut req =
+            TestClient::get("http://test.com?csrf-token=default&my-csrf-token=custom").build();
+        let token = QueryFinder::new().find_token(&mut req).await;
+        assert_eq!(token, Some("default".to_string()));
+        let token = QueryFinder::default()
+            .with_query_name("my-csrf-token")
+            .find_token(&mut req)
+            .await;
+        assert_eq!(token, Some("custom".to_string()));
+        let token = QueryFinder::new()
+            .with_query_name("absent")
+            .find_token(&mut req)
+            .await;
+        assert_eq!(token, None);
+
+        assert_eq!(
+            QueryFinder::new().try_with_query_name("").unwrap_err(),
+            EmptyNameError
+        );
+        let finder = QueryFinder::new()
+            .try_with_query_name("my-csrf-token")
+            .unwrap();
+        assert_eq!(
+            finder.find_token(&mut req).await,
+            Some("custom".to_string())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "query name must not be empty")]
+    fn test_query_finder_empty_name() {
+        QueryFinder::new().with_query_name("");
+    }
+
+    #[tokio::test]
+    async fn test_path_param_finder() {
+        let path_param_finder = PathParamFinder::new("csrf");
+        let mut req = TestClient::get("http://test.com").build();
+        let token = path_param_finder.find_token(&mut req).await;
+        assert_eq!(token, None);
+    }
+
+    #[tokio::test]
+    async fn test_extension_finder() {
+        #[derive(Clone)]
+        struct ParsedToken(&'static str);
+        impl AsRef<str> for ParsedToken {
+            fn as_ref(&self) -> &str {
+                self.0
+            }
+        }
+
+        let finder = ExtensionFinder::<ParsedToken>::new();
+        let mut req = TestClient::post("http://test.com").build();
+        assert_eq!(finder.find_token(&mut req).await, None);
+        req.extensions_mut().insert(ParsedToken(" "));
+        assert_eq!(finder.find_token(&mut req).await, None);
+        req.extensions_mut().insert(ParsedToken("test_token"));
+        let token = finder.find_token(&mut req).await;
+        assert_eq!(token, Some("test_token".to_string()));
+        assert_eq!(
+            ExtensionFinder::<String>::new().find_token(&mut req).await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_depot_finder() {
+        let finder = DepotFinder::new("parsed.csrf.token");
+        let mut req = TestClient::post("http://test.com").build();
+        let mut depot = Depot::new();
+        assert_eq!(finder.find_token(&mut req).await, None);
+        assert!(finder
+            .find_tokens_with_depot(&mut req, &depot)
+            .await
+            .is_empty());
+        depot.insert("parsed.csrf.token", " ".to_owned());
+        assert!(finder
+            .find_tokens_with_depot(&mut req, &depot)
+            .await
+            .is_empty());
+        depot.insert("parsed.csrf.token", "test_token".to_owned());
+        assert_eq!(
+            finder.find_tokens_with_depot(&mut req, &depot).await,
+            ["test_token"]
+        );
+    }
+
+    #[cfg(all(feature = "cookie-store", feature = "bcrypt-cipher"))]
+    #[tokio::test]
+    async fn test_depot_finder_validates() {
+        use salvo_core::prelude::*;
+        use salvo_core::test::ResponseExt;
+
+        use crate::{BcryptCipher, CookieStore, Csrf, CsrfDepotExt};
+
+        #[handler]
+        async fn parse_token(req: &mut Request, depot: &mut Depot) {
+            if let Some(token) = req.header::<String>("x-custom-token") {
+                depot.insert("parsed.csrf.token", token);
+            }
+        }
+        #[handler]
+        async fn get_index(depot: &mut Depot) -> String {
+            depot.csrf_token().unwrap().to_owned()
+        }
+        #[handler]
+        async fn post_index() -> &'static str {
+            "POST"
+        }
+
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            CookieStore::new(),
+            DepotFinder::new("parsed.csrf.token"),
+        );
+        let router = Router::new()
+            .hoop(parse_token)
+            .hoop(csrf)
+            .get(get_index)
+            .post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-custom-token", &token, true)
+            .add_header("cookie", &cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("cookie", &cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
     #[tokio::test]
     async fn test_json_finder() {
         let json_finder = JsonFinder::new("csrf-token");
@@ -117,4 +771,96 @@ mod tests {
         let token = json_finder.find_token(&mut req).await;
         assert_eq!(token, Some("test_token".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_graphql_finder() {
+        let body = r#"{
+            "operationName": "CreatePost",
+            "query": "mutation CreatePost($title: String!, $csrfToken: String!) { createPost(title: $title) { id } }",
+            "variables": {"title": "Hello", "csrfToken": "variable_token"},
+            "extensions": {"csrf": "extension_token"}
+        }"#;
+        let finder = GraphQLFinder::new("csrfToken");
+        let mut req = TestClient::post("http://test.com/graphql")
+            .raw_json(body)
+            .build();
+        let token = finder.find_token(&mut req).await;
+        assert_eq!(token, Some("variable_token".to_string()));
+        let payload = req.payload().await.unwrap();
+        assert_eq!(payload, body.as_bytes());
+
+        let finder = GraphQLFinder::from_extension("csrf");
+        let mut req = TestClient::post("http://test.com/graphql")
+            .raw_json(body)
+            .build();
+        let token = finder.find_token(&mut req).await;
+        assert_eq!(token, Some("extension_token".to_string()));
+
+        let finder = GraphQLFinder::new("missing").extension_name("csrf");
+        let mut req = TestClient::post("http://test.com/graphql")
+            .raw_json(body)
+            .build();
+        let token = finder.find_token(&mut req).await;
+        assert_eq!(token, Some("extension_token".to_string()));
+
+        let finder = GraphQLFinder::new("missing");
+        let mut req = TestClient::post("http://test.com/graphql")
+            .raw_json(body)
+            .build();
+        assert_eq!(finder.find_token(&mut req).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_query_finder_repeated_params() {
+        let finder = QueryFinder::new();
+        let mut req =
+            TestClient::get("http://test.com?csrf-token=&csrf-token=first&csrf-token=second")
+                .build();
+        assert_eq!(finder.find_tokens(&mut req).await, ["first", "second"]);
+        assert_eq!(finder.find_token(&mut req).await, Some("first".to_string()));
+
+        let mut req = TestClient::get("http://test.com?csrf-token=").build();
+        assert!(finder.find_tokens(&mut req).await.is_empty());
+        assert_eq!(finder.find_token(&mut req).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_json_finder_max_body_size() {
+        let json_finder = JsonFinder::new("csrf-token").max_body_size(64);
+        let body = format!(
+            r#"{{"csrf-token":"test_token","padding":"{}"}}"#,
+            "a".repeat(1024)
+        );
+        let mut req = TestClient::post("http://test.com").raw_json(body).build();
+        assert_eq!(json_finder.find_token(&mut req).await, None);
+
+        let mut req = TestClient::post("http://test.com")
+            .raw_json(r#"{"csrf-token":"test_token"}"#)
+            .build();
+        let token = json_finder.find_token(&mut req).await;
+        assert_eq!(token, Some("test_token".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_json_finder_content_type_parameters() {
+        let json_finder = JsonFinder::new("csrf-token");
+        for ctype in [
+            "application/json; charset=utf-8",
+            "Application/JSON;charset=UTF-8",
+            "application/vnd.api+json",
+        ] {
+            let mut req = TestClient::post("http://test.com")
+                .add_header("content-type", ctype, true)
+                .body(r#"{"csrf-token":"test_token"}"#)
+                .build();
+            let token = json_finder.find_token(&mut req).await;
+            assert_eq!(token, Some("test_token".to_string()), "{ctype}");
+        }
+
+        let mut req = TestClient::post("http://test.com")
+            .add_header("content-type", "text/plain", true)
+            .body(r#"{"csrf-token":"test_token"}"#)
+            .build();
+        assert_eq!(json_finder.find_token(&mut req).await, None);
+    }
 }
@@ -0,0 +1,450 @@
+use salvo_core::http::header::CONTENT_LENGTH;
+use salvo_core::http::{mime, ResBody};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler, Request, Response};
+
+use super::origin::request_origin;
+use super::{CsrfDepotExt, CSRF_TOKEN_KEY};
+
+/// Default of [`CsrfFormInjector::max_body_size`], 1 MiB.
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// Injects a hidden input carrying the current token into the forms of HTML responses.
+///
+/// Hoop it with [`Csrf`](crate::Csrf), after the next handlers write the response, a
+/// `<input type="hidden" name="csrf-token" value="...">` is inserted after each `<form>`
+/// opening tag, to be found by a [`FormFinder`](crate::FormFinder) with the same field name.
+/// Forms submitted with `method="get"`, which would leak the token in the URL, and forms whose
+/// absolute `action` points to another origin are skipped. When a placeholder is set with
+/// [`with_placeholder`](Self::with_placeholder), only the placeholder is replaced instead.
+///
+/// Only `text/html` bodies written at once or in chunks and no larger than
+/// [`max_body_size`](Self::max_body_size) are rewritten, streamed bodies are left untouched.
+#[derive(Clone, Debug)]
+pub struct CsrfFormInjector {
+    field_name: String,
+    placeholder: Option<String>,
+    depot_key: &'static str,
+    max_body_size: usize,
+}
+impl Default for CsrfFormInjector {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CsrfFormInjector {
+    /// Create a new `CsrfFormInjector` inserting a `csrf-token` field.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            field_name: "csrf-token".into(),
+            placeholder: None,
+            depot_key: CSRF_TOKEN_KEY,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+
+    /// Sets the name of the hidden field.
+    #[inline]
+    pub fn field_name(mut self, field_name: impl Into<String>) -> Self {
+        self.field_name = field_name.into();
+        self
+    }
+
+    /// Replaces `placeholder`, e.g. `{{csrf}}`, with the hidden input instead of inserting it
+    /// into every form.
+    #[inline]
+    pub fn with_placeholder(mut self, placeholder: impl Into<String>) -> Self {
+        self.placeholder = Some(placeholder.into());
+        self
+    }
+
+    /// Sets the depot key the token is read from, see
+    /// [`Csrf::with_depot_key`](crate::Csrf::with_depot_key).
+    #[inline]
+    pub fn with_depot_key(mut self, key: &'static str) -> Self {
+        self.depot_key = key;
+        self
+    }
+
+    /// Sets the size in bytes above which bodies are left untouched, defaults to 1 MiB.
+    #[inline]
+    pub fn max_body_size(mut self, size: usize) -> Self {
+        self.max_body_size = size;
+        self
+    }
+
+    /// Injects the token, `origin` is the origin of the request, forms with an absolute
+    /// `action` are skipped unless it matches.
+    fn inject(&self, html: &str, token: &str, origin: Option<&str>) -> String {
+        let input = format!(
+            r#"<input type="hidden" name="{}" value="{}">"#,
+            escape(&self.field_name),
+            escape(token)
+        );
+        if let Some(placeholder) = self.placeholder.as_deref().filter(|p| !p.is_empty()) {
+            return html.replace(placeholder, &input);
+        }
+        let mut output = String::with_capacity(html.len() + input.len());
+        let mut rest = html;
+        while let Some((start, end)) = find_form_tag(rest) {
+            output.push_str(&rest[..end]);
+            if accepts_token(&rest[start..end], origin) {
+                output.push_str(&input);
+            }
+            rest = &rest[end..];
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Returns whether the token may be added to the form opened by `tag`: it is not submitted
+/// with `GET` and its `action` is relative or points to `origin`.
+fn accepts_token(tag: &str, origin: Option<&str>) -> bool {
+    if attribute(tag, "method").is_some_and(|method| method.trim().eq_ignore_ascii_case("get")) {
+        return false;
+    }
+    let Some(action) = attribute(tag, "action").map(str::trim) else {
+        return true;
+    };
+    let authority = if let Some(rest) = action.strip_prefix("//") {
+        Some((None, rest))
+    } else {
+        action.split_once("://").and_then(|(scheme, rest)| {
+            (!scheme.is_empty() && !scheme.contains(['/', '?', '#']))
+                .then_some((Some(scheme), rest))
+        })
+    };
+    let Some((scheme, rest)) = authority else {
+        return true;
+    };
+    let Some(origin) = origin else {
+        return false;
+    };
+    let Some((origin_scheme, origin_host)) = origin.split_once("://") else {
+        return false;
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    // `Option::is_none_or` needs Rust 1.82.
+    #[allow(clippy::unnecessary_map_or)]
+    let same_scheme = scheme.map_or(true, |scheme| scheme.eq_ignore_ascii_case(origin_scheme));
+    same_scheme && host.eq_ignore_ascii_case(origin_host)
+}
+
+/// Value of the attribute `name` of an opening tag, unquoted.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let bytes = tag.as_bytes();
+    // Skips the tag name.
+    let mut i = tag.find(|c: char| c.is_ascii_whitespace() || c == '>')?;
+    loop {
+        while i < bytes.len() && (bytes[i].is_ascii_whitespace() || bytes[i] == b'/') {
+            i += 1;
+        }
+        if i >= bytes.len() || bytes[i] == b'>' {
+            return None;
+        }
+        let name_start = i;
+        while i < bytes.len()
+            && !matches!(bytes[i], b'=' | b'>' | b'/')
+            && !bytes[i].is_ascii_whitespace()
+        {
+            i += 1;
+        }
+        let attr_name = &tag[name_start..i];
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let value = if bytes.get(i) == Some(&b'=') {
+            i += 1;
+            while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            match bytes.get(i) {
+                Some(&quote @ (b'"' | b'\'')) => {
+                    let start = i + 1;
+                    let end = tag[start..]
+                        .find(quote as char)
+                        .map_or(tag.len(), |end| start + end);
+                    i = (end + 1).min(tag.len());
+                    &tag[start..end]
+                }
+                _ => {
+                    let start = i;
+                    while i < bytes.len() && bytes[i] != b'>' && !bytes[i].is_ascii_whitespace() {
+                        i += 1;
+                    }
+                    &tag[start..i]
+                }
+            }
+        } else {
+            ""
+        };
+        if attr_name.eq_ignore_ascii_case(name) {
+            return Some(value);
+        }
+    }
+}
+
+/// Positions of the first `<form ...>` opening tag and right after it, skipping `>` in quoted
+/// values.
+fn find_form_tag(html: &str) -> Option<(usize, usize)> {
+    let bytes = html.as_bytes();
+    let mut start = 0;
+    while let Some(offset) = html[start..].find('<') {
+        let tag = start + offset;
+        let name_end = tag + 5;
+        if bytes.len() > name_end
+            && bytes[tag + 1..name_end].eq_ignore_ascii_case(b"form")
+            && (bytes[name_end].is_ascii_whitespace() || bytes[name_end] == b'>')
+        {
+            let mut quote = None;
+            for (i, &byte) in bytes.iter().enumerate().skip(name_end) {
+                match (quote, byte) {
+                    (None, b'"' | b'\'') => quote = Some(byte),
+                    (Some(q), _) if q == byte => quote = None,
+                    (None, b'>') => return Some((tag, i + 1)),
+                    _ => {}
+                }
+            }
+            return None;
+        }
+        start = tag + 1;
+    }
+    None
+}
+
+#[async_trait]
+impl Handler for CsrfFormInjector {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        ctrl.call_next(req, depot, res).await;
+        let Some(token) = depot.csrf_token_with_key(self.depot_key) else {
+            return;
+        };
+        if !res
+            .content_type()
+            .is_some_and(|ctype| ctype.type_() == mime::TEXT && ctype.subtype() == mime::HTML)
+        {
+            return;
+        }
+        let size = match &res.body {
+            ResBody::Once(bytes) => bytes.len(),
+            ResBody::Chunks(chunks) => chunks.iter().map(|chunk| chunk.len()).sum(),
+            _ => return,
+        };
+        if size > self.max_body_size {
+            tracing::debug!(
+                size,
+                "csrf form injector skipped a body over the size limit"
+            );
+            return;
+        }
+        let body = match res.take_body() {
+            ResBody::Once(bytes) => bytes.to_vec(),
+            ResBody::Chunks(chunks) => chunks.iter().flatten().copied().collect(),
+            body => {
+                res.replace_body(body);
+                return;
+            }
+        };
+        match String::from_utf8(body) {
+            Ok(html) => {
+                let html = self.inject(&html, token, request_origin(req).as_deref());
+                res.headers_mut().remove(CONTENT_LENGTH);
+                res.replace_body(ResBody::Once(html.into()));
+            }
+            Err(e) => {
+                tracing::debug!("csrf form injector skipped a non utf-8 html body");
+                res.replace_body(ResBody::Once(e.into_bytes().into()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inject() {
+        let injector = CsrfFormInjector::new();
+        let html = r#"<FORM method="post" action="/a>b"><input name="x"></FORM>
+            <formation></formation><form>{{csrf}}</form>"#;
+        assert_eq!(
+            injector.inject(html, "tok\"en", None),
+            r#"<FORM method="post" action="/a>b"><input type="hidden" name="csrf-token" value="tok&quot;en"><input name="x"></FORM>
+            <formation></formation><form><input type="hidden" name="csrf-token" value="tok&quot;en">{{csrf}}</form>"#
+        );
+        assert_eq!(injector.inject("<form", "token", None), "<form");
+
+        let injector = injector.with_placeholder("{{csrf}}");
+        assert_eq!(
+            injector.inject("<form>{{csrf}}</form>", "token", None),
+            r#"<form><input type="hidden" name="csrf-token" value="token"></form>"#
+        );
+    }
+
+    #[test]
+    fn test_inject_skips_get_forms() {
+        let injector = CsrfFormInjector::new();
+        let input = r#"<input type="hidden" name="csrf-token" value="token">"#;
+        for (form, injected) in [
+            (r#"<form method="get">"#, false),
+            (r#"<form method='GET' action="/search">"#, false),
+            ("<form method=get>", false),
+            (r#"<form data-method="get" method = " get ">"#, false),
+            (r#"<form method="post">"#, true),
+            (r#"<form data-method="get">"#, true),
+            ("<form>", true),
+        ] {
+            let expected = if injected {
+                format!("{form}{input}</form>")
+            } else {
+                format!("{form}</form>")
+            };
+            assert_eq!(
+                injector.inject(&format!("{form}</form>"), "token", None),
+                expected,
+                "{form}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_inject_skips_cross_origin_forms() {
+        let injector = CsrfFormInjector::new();
+        let input = r#"<input type="hidden" name="csrf-token" value="token">"#;
+        let origin = Some("https://example.com");
+        for (form, origin, injected) in [
+            (r#"<form action="https://evil.com/steal">"#, origin, false),
+            (r#"<form action="//evil.com/steal">"#, origin, false),
+            (r#"<form action="http://example.com/a">"#, origin, false),
+            (
+                r#"<form action="https://example.com.evil.com">"#,
+                origin,
+                false,
+            ),
+            (r#"<form action="https://example.com/a">"#, None, false),
+            (r#"<form action="HTTPS://EXAMPLE.COM/a?b">"#, origin, true),
+            (r#"<form action="//example.com">"#, origin, true),
+            (r#"<form action="/a?next=https://evil.com">"#, origin, true),
+            (r#"<form action="a">"#, None, true),
+            (r#"<form action="">"#, None, true),
+        ] {
+            let expected = if injected {
+                format!("{form}{input}</form>")
+            } else {
+                format!("{form}</form>")
+            };
+            assert_eq!(
+                injector.inject(&format!("{form}</form>"), "token", origin),
+                expected,
+                "{form}"
+            );
+        }
+    }
+
+    #[cfg(all(feature = "cookie-store", feature = "bcrypt-cipher"))]
+    #[tokio::test]
+    async fn test_injected_token_validates() {
+        use salvo_core::prelude::*;
+        use salvo_core::test::{ResponseExt, TestClient};
+
+        use crate::{BcryptCipher, CookieStore, Csrf, FormFinder};
+
+        #[handler]
+        async fn get_page(res: &mut Response) {
+            res.render(Text::Html(
+                r#"<html><body><form method="post"><input name="a"></form></body></html>"#,
+            ));
+        }
+        #[handler]
+        async fn get_json(res: &mut Response) {
+            res.render(Text::Json(r#"{"form": "<form>"}"#));
+        }
+        #[handler]
+        async fn get_chunked(res: &mut Response) {
+            res.add_header("content-type", "text/html", true).unwrap();
+            res.write_body("<form method=\"post\">").unwrap();
+            res.write_body("</form>").unwrap();
+        }
+        #[handler]
+        async fn post_page() -> &'static str {
+            "POST"
+        }
+
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            CookieStore::new(),
+            FormFinder::new("csrf-token"),
+        );
+        let limited = Router::new()
+            .hoop(Csrf::new(
+                BcryptCipher::new().cost(4),
+                CookieStore::new(),
+                FormFinder::new("csrf-token"),
+            ))
+            .hoop(CsrfFormInjector::new().max_body_size(16))
+            .get(get_chunked);
+        let router = Router::new()
+            .hoop(csrf)
+            .hoop(CsrfFormInjector::new())
+            .get(get_page)
+            .post(post_page)
+            .push(Router::with_path("json").get(get_json))
+            .push(Router::with_path("chunked").get(get_chunked));
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/chunked")
+            .send(&service)
+            .await;
+        assert!(res
+            .take_string()
+            .await
+            .unwrap()
+            .contains(r#"name="csrf-token""#));
+        let mut res = TestClient::get("http://127.0.0.1:5801").send(limited).await;
+        assert_eq!(
+            res.take_string().await.unwrap(),
+            r#"<form method="post"></form>"#
+        );
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/json")
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), r#"{"form": "<form>"}"#);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let cookie = res.cookie("salvo.csrf").unwrap().clone();
+        let html = res.take_string().await.unwrap();
+        let token = html
+            .split(r#"name="csrf-token" value=""#)
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("cookie", cookie.to_string(), true)
+            .form(&[("a", "1"), ("csrf-token", token)])
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
+}
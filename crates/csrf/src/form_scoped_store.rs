@@ -0,0 +1,259 @@
+use std::borrow::Cow;
+
+use salvo_core::{Depot, Request, Response};
+
+use super::{CsrfCipher, CsrfStore, NamespaceError};
+
+/// Longest accepted form id.
+const MAX_FORM_ID_LEN: usize = 64;
+
+/// A `CsrfStore` decorator keeping a separate proof per form, for pages with many independent
+/// forms.
+///
+/// The form id is read from the `x-csrf-form` header, then from the `csrf-form` query
+/// parameter, and the inner store is namespaced with it like
+/// [`Csrf::with_namespace`](crate::Csrf::with_namespace) does, e.g. the cookie of a
+/// `CookieStore` becomes `form.login.salvo.csrf`. A token issued for one form is therefore
+/// rejected when submitted with another. Ids are up to 64 ASCII letters, digits, `-` and `_`,
+/// requests without a valid id use the inner store as is.
+///
+/// Each form gets its token from a request carrying its id, e.g.
+/// `GET /csrf-token?csrf-form=login` answered by a [`CsrfTokenHandler`](crate::CsrfTokenHandler).
+///
+/// The inner store is cloned for every request with a form id, so it must be namespaceable
+/// once cloned, which a store shared behind an `Arc` is not.
+#[derive(Clone, Debug)]
+pub struct FormScopedStore<S> {
+    inner: S,
+    header_name: String,
+    query_name: String,
+}
+
+impl<S: CsrfStore + Clone> FormScopedStore<S> {
+    /// Create a new `FormScopedStore` wrapping `inner`.
+    ///
+    /// Returns an error if a clone of `inner` can not be namespaced, e.g. `inner` is an
+    /// `Arc<S>`, wrap the `FormScopedStore` in the `Arc` instead.
+    pub fn new(inner: S) -> Result<Self, NamespaceError> {
+        inner.clone().namespace("form")?;
+        Ok(Self {
+            inner,
+            header_name: "x-csrf-form".into(),
+            query_name: "csrf-form".into(),
+        })
+    }
+
+    /// Sets the header the form id is read from, defaults to `x-csrf-form`.
+    #[inline]
+    pub fn header_name(mut self, header_name: impl Into<String>) -> Self {
+        self.header_name = header_name.into();
+        self
+    }
+
+    /// Sets the query parameter the form id is read from, defaults to `csrf-form`.
+    #[inline]
+    pub fn query_name(mut self, query_name: impl Into<String>) -> Self {
+        self.query_name = query_name.into();
+        self
+    }
+
+    /// Get the inner store.
+    #[inline]
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn form_id(&self, req: &Request) -> Option<String> {
+        let id = req
+            .header::<String>(self.header_name.as_str())
+            .or_else(|| req.query::<String>(&self.query_name))?;
+        let valid = !id.is_empty()
+            && id.len() <= MAX_FORM_ID_LEN
+            && id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        if !valid {
+            tracing::debug!("invalid csrf form id, using the unscoped store");
+            return None;
+        }
+        Some(id)
+    }
+
+    /// The inner store namespaced with the form id of the request, if any.
+    fn scoped(&self, req: &Request) -> Cow<'_, S> {
+        match self.form_id(req) {
+            Some(id) => {
+                let mut inner = self.inner.clone();
+                // Checked by `new`, namespacing a clone does not fail afterwards.
+                if let Err(e) = inner.namespace(&format!("form.{id}")) {
+                    tracing::error!(error = %e, "failed to scope csrf store to the form");
+                }
+                Cow::Owned(inner)
+            }
+            None => Cow::Borrowed(&self.inner),
+        }
+    }
+}
+
+impl<S: CsrfStore + Clone> CsrfStore for FormScopedStore<S> {
+    type Error = S::Error;
+    async fn load<C: CsrfCipher>(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        cipher: &C,
+    ) -> Option<(String, String)> {
+        self.scoped(req).load(req, depot, cipher).await
+    }
+    async fn load_all<C: CsrfCipher>(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        cipher: &C,
+    ) -> Vec<(String, String)> {
+        self.scoped(req).load_all(req, depot, cipher).await
+    }
+    fn check(&self, depot: &mut Depot) -> Result<(), Self::Error> {
+        self.inner.check(depot)
+    }
+    fn namespace(&mut self, prefix: &str) -> Result<(), NamespaceError> {
+        self.inner.namespace(prefix)
+    }
+    fn rotation_due(&self, req: &Request, depot: &Depot) -> bool {
+        self.scoped(req).rotation_due(req, depot)
+    }
+    async fn save(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        token: &str,
+        proof: &str,
+    ) -> Result<(), Self::Error> {
+        self.scoped(req).save(req, depot, res, token, proof).await
+    }
+    async fn persist(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<(), Self::Error> {
+        self.scoped(req).persist(req, depot, res).await
+    }
+    async fn clear(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<(), Self::Error> {
+        self.scoped(req).clear(req, depot, res).await
+    }
+}
+
+#[cfg(all(test, feature = "cookie-store", feature = "bcrypt-cipher"))]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+    use crate::{BcryptCipher, CookieStore, Csrf, CsrfDepotExt, HeaderFinder};
+
+    #[handler]
+    async fn get_index(depot: &mut Depot) -> String {
+        depot.csrf_token().unwrap().to_owned()
+    }
+    #[handler]
+    async fn post_index() -> &'static str {
+        "POST"
+    }
+
+    #[tokio::test]
+    async fn test_form_scoped_store() {
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            FormScopedStore::new(CookieStore::new()).unwrap(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut forms = vec![];
+        for id in ["login", "search"] {
+            let mut res = TestClient::get(format!("http://127.0.0.1:5801?csrf-form={id}"))
+                .send(&service)
+                .await;
+            let token = res.take_string().await.unwrap();
+            let cookie = res
+                .cookie(format!("form.{id}.salvo.csrf"))
+                .unwrap()
+                .to_string();
+            forms.push((id, token, cookie));
+        }
+        let cookies = format!("{}; {}", forms[0].2, forms[1].2);
+
+        for (id, token, _) in &forms {
+            let res = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-form", *id, true)
+                .add_header("x-csrf-token", token, true)
+                .add_header("cookie", &cookies, true)
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), StatusCode::OK, "{id}");
+        }
+
+        for (id, token) in [("login", &forms[1].1), ("search", &forms[0].1)] {
+            let res = TestClient::post(format!("http://127.0.0.1:5801?csrf-form={id}"))
+                .add_header("x-csrf-token", token, true)
+                .add_header("cookie", &cookies, true)
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN, "{id}");
+        }
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", &forms[0].1, true)
+            .add_header("cookie", &cookies, true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_form_scoped_store_invalid_id() {
+        let store = FormScopedStore::new(CookieStore::new()).unwrap();
+        for (id, scoped) in [
+            ("login-2_b", true),
+            ("", false),
+            ("../login", false),
+            ("a.b", false),
+            (&*"a".repeat(65), false),
+        ] {
+            let req = TestClient::get("http://127.0.0.1:5801")
+                .add_header("x-csrf-form", id, true)
+                .build();
+            assert_eq!(store.form_id(&req).is_some(), scoped, "{id}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_form_scoped_store_over_arc() {
+        use std::sync::Arc;
+
+        assert!(matches!(
+            FormScopedStore::new(Arc::new(CookieStore::new())),
+            Err(NamespaceError::SharedStore)
+        ));
+
+        let store = Arc::new(FormScopedStore::new(CookieStore::new()).unwrap());
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            store,
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index);
+        let res = TestClient::get("http://127.0.0.1:5801?csrf-form=login")
+            .send(router)
+            .await;
+        assert!(res.cookie("form.login.salvo.csrf").is_some());
+    }
+}
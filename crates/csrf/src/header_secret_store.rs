@@ -0,0 +1,168 @@
+use std::convert::Infallible;
+
+use salvo_core::http::header::AUTHORIZATION;
+use salvo_core::{Depot, Request, Response};
+
+use super::{CsrfCipher, CsrfStore};
+
+/// A `CsrfStore` loading the proof sent by the client itself, e.g. `Authorization: CSRF <proof>`.
+///
+/// This suits machine clients holding a token and proof pair, e.g. from
+/// [`Csrf::generate_token`](crate::Csrf::generate_token), and allows stateless verification
+/// without cookies or sessions. The token is sent in another header, read by a
+/// [`HeaderFinder`](crate::HeaderFinder) with the same name as
+/// [`token_header`](Self::token_header). Issued tokens are never saved.
+///
+/// The proof must not be derivable by attackers, so only use this with ciphers keyed on the
+/// server, like `HmacCipher`.
+#[derive(Clone, Debug)]
+pub struct HeaderSecretStore {
+    header_name: String,
+    scheme: String,
+    token_header: String,
+}
+
+impl Default for HeaderSecretStore {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeaderSecretStore {
+    /// Create a new `HeaderSecretStore` reading `Authorization: CSRF <proof>` and the token
+    /// from the `x-csrf-token` header.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            header_name: AUTHORIZATION.as_str().into(),
+            scheme: "CSRF".into(),
+            token_header: "x-csrf-token".into(),
+        }
+    }
+
+    /// Sets the name of the header carrying the proof.
+    #[inline]
+    pub fn header_name(mut self, header_name: impl Into<String>) -> Self {
+        self.header_name = header_name.into();
+        self
+    }
+
+    /// Sets the scheme preceding the proof, matched ignoring ASCII case.
+    #[inline]
+    pub fn scheme(mut self, scheme: impl Into<String>) -> Self {
+        self.scheme = scheme.into();
+        self
+    }
+
+    /// Sets the name of the header carrying the token.
+    #[inline]
+    pub fn token_header(mut self, token_header: impl Into<String>) -> Self {
+        self.token_header = token_header.into();
+        self
+    }
+
+    /// The proof of a `<scheme> <proof>` header value.
+    fn parse<'a>(&self, value: &'a str) -> Option<&'a str> {
+        let (scheme, proof) = value.trim().split_once(' ')?;
+        let proof = proof.trim_start();
+        if !scheme.eq_ignore_ascii_case(&self.scheme)
+            || proof.is_empty()
+            || proof.contains(char::is_whitespace)
+        {
+            return None;
+        }
+        Some(proof)
+    }
+}
+
+impl CsrfStore for HeaderSecretStore {
+    type Error = Infallible;
+    async fn load<C: CsrfCipher>(
+        &self,
+        req: &mut Request,
+        _depot: &mut Depot,
+        _cipher: &C,
+    ) -> Option<(String, String)> {
+        let header = |name: &str| req.headers().get(name).and_then(|v| v.to_str().ok());
+        let Some(proof) = header(&self.header_name).and_then(|value| self.parse(value)) else {
+            tracing::debug!("csrf proof header missing or malformed");
+            return None;
+        };
+        let token = header(&self.token_header)?.trim();
+        Some((token.to_owned(), proof.to_owned()))
+    }
+    async fn save(
+        &self,
+        _req: &mut Request,
+        _depot: &mut Depot,
+        _res: &mut Response,
+        _token: &str,
+        _proof: &str,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "hmac-cipher"))]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::TestClient;
+
+    use super::*;
+    use crate::{Csrf, HeaderFinder, HmacCipher};
+
+    #[handler]
+    async fn post_index() -> &'static str {
+        "POST"
+    }
+
+    #[test]
+    fn test_parse() {
+        let store = HeaderSecretStore::new();
+        assert_eq!(store.parse("CSRF proof"), Some("proof"));
+        assert_eq!(store.parse(" csrf  proof "), Some("proof"));
+        assert_eq!(store.parse("Bearer proof"), None);
+        assert_eq!(store.parse("CSRF"), None);
+        assert_eq!(store.parse("CSRF "), None);
+        assert_eq!(store.parse("CSRF pro of"), None);
+        assert_eq!(store.scheme("Token").parse("token proof"), Some("proof"));
+    }
+
+    #[tokio::test]
+    async fn test_header_secret_store() {
+        let csrf = Csrf::new(
+            HmacCipher::new(*b"01234567012345670123456701234567"),
+            HeaderSecretStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let (req, depot) = (Request::new(), Depot::new());
+        let (token, proof) = csrf.generate_token(&req, &depot);
+        let (_, other_proof) = csrf.generate_token(&req, &depot);
+        let router = Router::new().hoop(csrf).post(post_index);
+        let service = Service::new(router);
+
+        for (authorization, status) in [
+            (Some(format!("CSRF {proof}")), StatusCode::OK),
+            (Some(format!("CSRF {other_proof}")), StatusCode::FORBIDDEN),
+            (Some(format!("Bearer {proof}")), StatusCode::FORBIDDEN),
+            (Some("CSRF".to_owned()), StatusCode::FORBIDDEN),
+            (None, StatusCode::FORBIDDEN),
+        ] {
+            let mut req =
+                TestClient::post("http://127.0.0.1:5801").add_header("x-csrf-token", &token, true);
+            if let Some(authorization) = &authorization {
+                req = req.add_header("authorization", authorization, true);
+            }
+            let res = req.send(&service).await;
+            assert_eq!(res.status_code.unwrap(), status, "{authorization:?}");
+            assert!(res.cookies().iter().next().is_none());
+        }
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("authorization", format!("CSRF {proof}"), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+}
@@ -1,26 +1,50 @@
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
 use hmac::{Hmac, Mac};
+use rand::RngCore;
 use sha2::Sha256;
+use zeroize::{ZeroizeOnDrop, Zeroizing};
 
-use super::CsrfCipher;
+use super::{CsrfCipher, CsrfVerifyError};
+
+const MAC_LEN: usize = 32;
 
 /// A CSRF protection implementation that uses HMAC.
+///
+/// The key is wiped from memory when the cipher is dropped.
+#[derive(Clone)]
 pub struct HmacCipher {
-    hmac_key: [u8; 32],
+    hmac_key: Zeroizing<[u8; 32]>,
     token_size: usize,
+    legacy_format: bool,
 }
 
+// `hmac_key` is wiped on drop by `Zeroizing`.
+impl ZeroizeOnDrop for HmacCipher {}
+
 impl HmacCipher {
     /// Given an HMAC key, return an `HmacCipher` instance.
     #[inline]
     pub fn new(hmac_key: [u8; 32]) -> Self {
         Self {
-            hmac_key,
+            hmac_key: Zeroizing::new(hmac_key),
             token_size: 32,
+            legacy_format: false,
         }
     }
 
+    /// Create an `HmacCipher` with a key derived from `passphrase` and `salt` with HKDF-SHA256.
+    ///
+    /// The passphrase must be a long random secret, e.g. read from an environment variable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `passphrase` is shorter than 32 bytes.
+    #[inline]
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Self {
+        Self::new(crate::kdf::derive_key(passphrase, salt, b"salvo-csrf hmac-cipher"))
+    }
+
     /// Sets the length of the token.
     #[inline]
     pub fn token_size(mut self, token_size: usize) -> Self {
@@ -29,31 +53,76 @@ impl HmacCipher {
         self
     }
 
+    /// Also accepts tokens in the legacy `hex(hmac) ':' secret` layout, where `hmac` is the
+    /// HMAC-SHA256 of `secret` with the same key and the proof is `secret` itself.
+    ///
+    /// This keeps tokens issued by a previous implementation validating while migrating,
+    /// new tokens are always generated in the current format. Disabled by default.
+    #[inline]
+    pub fn legacy_format(mut self, legacy_format: bool) -> Self {
+        self.legacy_format = legacy_format;
+        self
+    }
+
+    fn verify_legacy(&self, token: &str, proof: &str) -> bool {
+        let Some((mac, secret)) = token.split_once(':') else {
+            return false;
+        };
+        let Ok(mac) = hex::decode(mac) else {
+            return false;
+        };
+        // Verifying the mac against both the secret and the proof compares them in constant time.
+        [secret, proof].into_iter().all(|value| {
+            let mut hmac = self.hmac();
+            hmac.update(value.as_bytes());
+            hmac.verify_slice(&mac).is_ok()
+        })
+    }
+
     #[inline]
     fn hmac(&self) -> Hmac<Sha256> {
-        Hmac::<Sha256>::new_from_slice(&self.hmac_key).expect("HMAC can take key of any size")
+        Hmac::<Sha256>::new_from_slice(&*self.hmac_key).expect("HMAC can take key of any size")
     }
 }
 
 impl CsrfCipher for HmacCipher {
     fn verify(&self, token: &str, proof: &str) -> bool {
+        self.verify_detailed(token, proof).is_ok()
+    }
+    fn verify_detailed(&self, token: &str, proof: &str) -> Result<(), CsrfVerifyError> {
         if let (Ok(token), Ok(proof)) = (
             URL_SAFE_NO_PAD.decode(token.as_bytes()),
             URL_SAFE_NO_PAD.decode(proof.as_bytes()),
         ) {
-            if proof.len() != self.token_size {
-                false
+            if token.len() != self.token_size || proof.len() != MAC_LEN {
+                Err(CsrfVerifyError::Malformed)
+            } else if self.verify_raw(&token, &proof) {
+                Ok(())
             } else {
-                let mut hmac = self.hmac();
-                hmac.update(&token);
-                hmac.verify((&*proof).into()).is_ok()
+                Err(CsrfVerifyError::Invalid)
             }
+        } else if !self.legacy_format {
+            Err(CsrfVerifyError::Malformed)
+        } else if self.verify_legacy(token, proof) {
+            Ok(())
         } else {
+            Err(CsrfVerifyError::Invalid)
+        }
+    }
+    fn verify_raw(&self, token: &[u8], proof: &[u8]) -> bool {
+        if token.len() != self.token_size || proof.len() != MAC_LEN {
             false
+        } else {
+            let mut hmac = self.hmac();
+            hmac.update(token);
+            hmac.verify(proof.into()).is_ok()
         }
     }
     fn generate(&self) -> (String, String) {
-        let token = self.random_bytes(self.token_size);
+        self.generate_with_rng(&mut rand::thread_rng())
+    }
+    fn generate_with_rng(&self, rng: &mut dyn RngCore) -> (String, String) {
+        let token = self.random_bytes_with_rng(rng, self.token_size);
         let mut hmac = self.hmac();
         hmac.update(&token);
         let mac = hmac.finalize();
@@ -73,7 +142,7 @@ mod tests {
     fn test_new() {
         let hmac_key = [0u8; 32];
         let hmac_cipher = HmacCipher::new(hmac_key);
-        assert_eq!(hmac_cipher.hmac_key, hmac_key);
+        assert_eq!(*hmac_cipher.hmac_key, hmac_key);
         assert_eq!(hmac_cipher.token_size, 32);
     }
 
@@ -84,6 +153,37 @@ mod tests {
         assert_eq!(hmac_cipher.token_size, 16);
     }
 
+    #[test]
+    fn test_custom_token_size() {
+        for token_size in [8, 16, 64] {
+            let hmac_cipher = HmacCipher::new([0u8; 32]).token_size(token_size);
+            let (token, proof) = hmac_cipher.generate();
+            assert_eq!(URL_SAFE_NO_PAD.decode(&token).unwrap().len(), token_size);
+            assert!(hmac_cipher.verify(&token, &proof));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "length must be larger than 8")]
+    fn test_too_short_token_size() {
+        HmacCipher::new([0u8; 32]).token_size(7);
+    }
+
+    #[test]
+    fn test_generate_with_seeded_rng() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let hmac_cipher = HmacCipher::new([0u8; 32]).token_size(8);
+        let (token, proof) = hmac_cipher.generate_with_rng(&mut StdRng::seed_from_u64(42));
+        assert_eq!(token, "oiQnImN3zIY");
+        assert_eq!(
+            (token.clone(), proof.clone()),
+            hmac_cipher.generate_with_rng(&mut StdRng::seed_from_u64(42))
+        );
+        assert!(hmac_cipher.verify(&token, &proof));
+    }
+
     #[test]
     fn test_verify() {
         let hmac_key = [0u8; 32];
@@ -97,10 +197,60 @@ mod tests {
         let hmac_key = [0u8; 32];
         let hmac_cipher = HmacCipher::new(hmac_key);
         let (token, _) = hmac_cipher.generate();
-        let invalid_proof = URL_SAFE_NO_PAD.encode(vec![0u8; hmac_cipher.token_size]);
+        let invalid_proof = URL_SAFE_NO_PAD.encode(vec![0u8; MAC_LEN]);
         assert!(!hmac_cipher.verify(&token, &invalid_proof));
     }
 
+    #[test]
+    fn test_verify_detailed() {
+        let hmac_cipher = HmacCipher::new([0u8; 32]);
+        let (token, proof) = hmac_cipher.generate();
+        assert_eq!(hmac_cipher.verify_detailed(&token, &proof), Ok(()));
+
+        let (other_token, _) = hmac_cipher.generate();
+        assert_eq!(
+            hmac_cipher.verify_detailed(&other_token, &proof),
+            Err(CsrfVerifyError::Invalid)
+        );
+        for (token, proof) in [
+            (&token[..], "not base64!"),
+            ("not base64!", &proof[..]),
+            (&token[..], &proof[..proof.len() - 4]),
+            (&token[..token.len() - 4], &proof[..]),
+        ] {
+            assert_eq!(
+                hmac_cipher.verify_detailed(token, proof),
+                Err(CsrfVerifyError::Malformed)
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_raw() {
+        let hmac_cipher = HmacCipher::new([0u8; 32]);
+        let (token, proof) = hmac_cipher.generate();
+        let raw_token = URL_SAFE_NO_PAD.decode(&token).unwrap();
+        let raw_proof = URL_SAFE_NO_PAD.decode(&proof).unwrap();
+        assert!(hmac_cipher.verify_raw(&raw_token, &raw_proof));
+
+        let mut tampered = raw_token.clone();
+        tampered[0] ^= 1;
+        for (token, proof) in [
+            (&tampered[..], &raw_proof[..]),
+            (&raw_token[1..], &raw_proof[..]),
+            (&raw_token[..], &raw_proof[1..]),
+        ] {
+            assert!(!hmac_cipher.verify_raw(token, proof));
+            assert_eq!(
+                hmac_cipher.verify_raw(token, proof),
+                hmac_cipher.verify(
+                    &URL_SAFE_NO_PAD.encode(token),
+                    &URL_SAFE_NO_PAD.encode(proof)
+                )
+            );
+        }
+    }
+
     #[test]
     fn test_generate() {
         let hmac_key = [0u8; 32];
@@ -108,4 +258,37 @@ mod tests {
         let (token, proof) = hmac_cipher.generate();
         assert!(hmac_cipher.verify(&token, &proof));
     }
+
+    #[test]
+    fn test_from_passphrase() {
+        let passphrase = "0123456789abcdef0123456789abcdef";
+        let cipher = HmacCipher::from_passphrase(passphrase, b"salt");
+        let (token, proof) = cipher.generate();
+        assert!(HmacCipher::from_passphrase(passphrase, b"salt").verify(&token, &proof));
+        assert!(!HmacCipher::from_passphrase(passphrase, b"pepper").verify(&token, &proof));
+    }
+
+    #[test]
+    fn test_legacy_format() {
+        let hmac_cipher = HmacCipher::new([0u8; 32]);
+        let secret = "legacy-secret";
+        let mut hmac = hmac_cipher.hmac();
+        hmac.update(secret.as_bytes());
+        let legacy_token = format!("{}:{secret}", hex::encode(hmac.finalize().into_bytes()));
+        assert!(!hmac_cipher.verify(&legacy_token, secret));
+
+        let hmac_cipher = hmac_cipher.legacy_format(true);
+        assert!(hmac_cipher.verify(&legacy_token, secret));
+        let (token, proof) = hmac_cipher.generate();
+        assert!(!token.contains(':'));
+        assert!(hmac_cipher.verify(&token, &proof));
+
+        assert!(!hmac_cipher.verify(&legacy_token, "other-secret"));
+        assert!(!hmac_cipher.verify(&legacy_token.replace("legacy", "forged"), secret));
+        assert!(!hmac_cipher.verify(&format!("zz{legacy_token}"), secret));
+        assert!(!hmac_cipher.verify(secret, secret));
+        assert!(!HmacCipher::new([1u8; 32])
+            .legacy_format(true)
+            .verify(&legacy_token, secret));
+    }
 }
@@ -0,0 +1,151 @@
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::Engine;
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+/// Error returned by the `*_csrf_from_env` helpers when the key can not be read.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum EnvKeyError {
+    /// The environment variable is not set or is not valid unicode.
+    #[error("environment variable `{0}` is not set or not valid unicode")]
+    Missing(String),
+    /// The value is neither hex nor base64.
+    #[error("environment variable `{0}` is neither hex nor base64")]
+    Invalid(String),
+    /// The decoded key is not 32 bytes long.
+    #[error("environment variable `{var}` holds a {len} bytes key, expected 32")]
+    WrongLength {
+        /// Name of the environment variable.
+        var: String,
+        /// Length of the decoded key.
+        len: usize,
+    },
+}
+
+/// Minimum length in bytes of passphrases keys are derived from.
+pub(crate) const MIN_PASSPHRASE_LEN: usize = 32;
+
+/// Derives a 32 bytes key from a passphrase with HKDF-SHA256.
+///
+/// HKDF does not stretch its input, so the passphrase must be a long random secret rather
+/// than a human chosen password. `info` separates keys derived for different ciphers.
+pub(crate) fn derive_key(passphrase: &str, salt: &[u8], info: &[u8]) -> [u8; 32] {
+    assert!(
+        passphrase.len() >= MIN_PASSPHRASE_LEN,
+        "passphrase must be at least {MIN_PASSPHRASE_LEN} bytes"
+    );
+    let mut key = [0; 32];
+    Hkdf::<Sha256>::new(Some(salt), passphrase.as_bytes())
+        .expand(info, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+/// Reads a 32 bytes key, encoded as hex or base64, from the `var` environment variable.
+///
+/// Some values are valid in several encodings, the first one decoding to 32 bytes is used.
+pub(crate) fn key_from_env(var: &str) -> Result<[u8; 32], EnvKeyError> {
+    let value = std::env::var(var).map_err(|_| EnvKeyError::Missing(var.into()))?;
+    let value = value.trim();
+    let decoded: Vec<Vec<u8>> = std::iter::once(hex::decode(value).ok())
+        .chain(
+            [STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD]
+                .iter()
+                .map(|engine| engine.decode(value).ok()),
+        )
+        .flatten()
+        .collect();
+    if let Some(key) = decoded
+        .iter()
+        .find_map(|key| <[u8; 32]>::try_from(&key[..]).ok())
+    {
+        return Ok(key);
+    }
+    match decoded.first() {
+        Some(key) => Err(EnvKeyError::WrongLength {
+            var: var.into(),
+            len: key.len(),
+        }),
+        None => Err(EnvKeyError::Invalid(var.into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSPHRASE: &str = "correct horse battery staple and more words";
+
+    #[test]
+    fn test_derive_key() {
+        let key = derive_key(PASSPHRASE, b"salt", b"info");
+        assert_eq!(key, derive_key(PASSPHRASE, b"salt", b"info"));
+        assert_ne!(key, derive_key(PASSPHRASE, b"pepper", b"info"));
+        assert_ne!(key, derive_key(PASSPHRASE, b"salt", b"other"));
+    }
+
+    #[test]
+    #[should_panic(expected = "passphrase must be at least 32 bytes")]
+    fn test_derive_key_short_passphrase() {
+        derive_key("short", b"salt", b"info");
+    }
+
+    #[test]
+    fn test_key_from_env() {
+        let key = [7u8; 32];
+        std::env::set_var("SALVO_CSRF_TEST_HEX_KEY", hex::encode(key));
+        std::env::set_var("SALVO_CSRF_TEST_BASE64_KEY", STANDARD.encode(key));
+        std::env::set_var("SALVO_CSRF_TEST_URL_SAFE_KEY", URL_SAFE_NO_PAD.encode(key));
+        assert_eq!(key_from_env("SALVO_CSRF_TEST_HEX_KEY").unwrap(), key);
+        assert_eq!(key_from_env("SALVO_CSRF_TEST_BASE64_KEY").unwrap(), key);
+        assert_eq!(key_from_env("SALVO_CSRF_TEST_URL_SAFE_KEY").unwrap(), key);
+    }
+
+    #[test]
+    fn test_key_from_env_errors() {
+        assert!(matches!(
+            key_from_env("SALVO_CSRF_TEST_MISSING_KEY"),
+            Err(EnvKeyError::Missing(_))
+        ));
+        std::env::set_var("SALVO_CSRF_TEST_INVALID_KEY", "not a key!");
+        assert!(matches!(
+            key_from_env("SALVO_CSRF_TEST_INVALID_KEY"),
+            Err(EnvKeyError::Invalid(_))
+        ));
+        std::env::set_var("SALVO_CSRF_TEST_SHORT_KEY", hex::encode([7u8; 16]));
+        assert!(matches!(
+            key_from_env("SALVO_CSRF_TEST_SHORT_KEY"),
+            Err(EnvKeyError::WrongLength { len: 16, .. })
+        ));
+    }
+
+    #[cfg(feature = "hmac-cipher")]
+    #[test]
+    fn test_csrf_from_env() {
+        use crate::{hmac_csrf_from_env, HeaderFinder, NoopStore};
+
+        std::env::set_var("SALVO_CSRF_TEST_HMAC_KEY", hex::encode([7u8; 32]));
+        let csrf = hmac_csrf_from_env(
+            "SALVO_CSRF_TEST_HMAC_KEY",
+            NoopStore::new("", ""),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .unwrap();
+        let (req, depot) = (salvo_core::Request::new(), salvo_core::Depot::new());
+        let (token, proof) = csrf.generate_token(&req, &depot);
+        assert!(csrf.verify_token(&req, &depot, &token, &proof));
+
+        let error = hmac_csrf_from_env(
+            "SALVO_CSRF_TEST_ABSENT_HMAC_KEY",
+            NoopStore::new("", ""),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "environment variable `SALVO_CSRF_TEST_ABSENT_HMAC_KEY` is not set or not valid unicode"
+        );
+    }
+}
@@ -10,18 +10,95 @@
 #![doc(html_logo_url = "https://salvo.rs/images/logo.svg")]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+use std::any::type_name;
+use std::borrow::Cow;
 use std::error::Error as StdError;
+use std::fmt;
 use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
+use base64::engine::general_purpose::{GeneralPurpose, URL_SAFE_NO_PAD};
+use base64::Engine;
+
+#[cfg(any(
+    feature = "aes-gcm-cipher",
+    feature = "aes-gcm-siv-cipher",
+    feature = "ccp-cipher",
+    feature = "xccp-cipher"
+))]
+mod aead_proof;
+mod binding;
+mod combined_store;
+mod csrf_override;
+mod deferred_cipher;
+mod encoding;
+mod extract;
+mod fallback_cipher;
+mod fetch_site;
 mod finder;
+mod form_injector;
+mod form_scoped_store;
+mod header_secret_store;
+#[cfg(any(
+    feature = "blake3-cipher",
+    feature = "hmac-cipher",
+    feature = "aes-gcm-cipher",
+    feature = "aes-gcm-siv-cipher",
+    feature = "ccp-cipher",
+    feature = "xccp-cipher"
+))]
+mod kdf;
+#[cfg(any(
+    feature = "blake3-cipher",
+    feature = "hmac-cipher",
+    feature = "aes-gcm-cipher",
+    feature = "aes-gcm-siv-cipher",
+    feature = "ccp-cipher",
+    feature = "xccp-cipher"
+))]
+pub use kdf::EnvKeyError;
+mod masked_cipher;
+mod noop_store;
+mod origin;
+mod session_adapter;
+mod skipper;
+#[cfg(any(feature = "sled-store", feature = "sqlx-store"))]
+mod sweep;
+
+use binding::Binding;
 
-pub use finder::{CsrfTokenFinder, FormFinder, HeaderFinder, JsonFinder};
+pub use combined_store::{CombinedStore, CombinedStoreError, SaveTarget};
+pub use csrf_override::CsrfOverride;
+pub use deferred_cipher::DeferredCipher;
+pub use encoding::TokenEncoding;
+pub use extract::CsrfToken;
+pub use fallback_cipher::FallbackCipher;
+pub use fetch_site::{FetchSiteMode, MissingFetchSite};
+pub use finder::{
+    default_finders, CsrfTokenFinder, DepotFinder, EmptyNameError, ExtensionFinder, FormFinder,
+    GraphQLFinder, HeaderFinder, JsonFinder, PathParamFinder, QueryFinder,
+};
+pub use form_injector::CsrfFormInjector;
+pub use form_scoped_store::FormScopedStore;
+pub use header_secret_store::HeaderSecretStore;
+pub use masked_cipher::{remask_token, MaskedCipher};
+pub use noop_store::NoopStore;
+pub use origin::{MissingOrigin, OriginMode};
+pub use session_adapter::{CsrfSession, SessionAdapter, SessionAdapterError};
+pub use skipper::BearerAuthSkipper;
+#[cfg(feature = "regex-skipper")]
+pub use skipper::RegexSkipper;
 
-use rand::distributions::Standard;
-use rand::Rng;
+use fetch_site::{check_fetch_site, FetchSiteCheck};
+use origin::{check_origin, OriginPolicy};
+use rand::RngCore;
 use salvo_core::handler::Skipper;
-use salvo_core::http::{Method, StatusCode};
-use salvo_core::{async_trait, Depot, FlowCtrl, Handler, Request, Response};
+use salvo_core::http::header::{HeaderName, HeaderValue, ACCEPT, UPGRADE, VARY};
+use salvo_core::http::{mime, Method, StatusCode, StatusError};
+use salvo_core::writing::{Json, Text};
+use salvo_core::{async_trait, Depot, FlowCtrl, Handler, Request, Response, Router};
+use tracing::{Instrument, Level};
 
 #[macro_use]
 mod cfg;
@@ -30,7 +107,11 @@ cfg_feature! {
     #![feature = "cookie-store"]
 
     mod cookie_store;
+    mod double_submit_store;
+    mod token_cookie;
     pub use cookie_store::CookieStore;
+    pub use double_submit_store::DoubleSubmitStore;
+    pub use token_cookie::TokenCookie;
 
     /// Helper function to create a `CookieStore`.
     pub fn cookie_store<>() -> CookieStore {
@@ -41,13 +122,49 @@ cfg_feature! {
     #![feature = "session-store"]
 
     mod session_store;
-    pub use session_store::SessionStore;
+    pub use session_store::{SessionStore, SessionStoreError};
 
     /// Helper function to create a `SessionStore`.
     pub fn session_store() -> SessionStore {
         SessionStore::new()
     }
 }
+cfg_feature! {
+    #![feature = "sled-store"]
+
+    mod sled_store;
+    pub use sled_store::{SledStore, SledStoreError};
+}
+cfg_feature! {
+    #![feature = "sqlx-store"]
+
+    mod sqlx_store;
+    pub use sqlx_store::{SqlxStore, SqlxStoreError};
+}
+cfg_feature! {
+    #![feature = "encrypted-store"]
+
+    mod encrypted_store;
+    pub use encrypted_store::{EncryptedStore, EncryptedStoreError};
+}
+cfg_feature! {
+    #![feature = "config"]
+
+    mod config;
+    pub use config::{CipherKind, CsrfConfig, CsrfConfigError};
+}
+cfg_feature! {
+    #![feature = "cbor-finder"]
+
+    mod cbor_finder;
+    pub use cbor_finder::CborFinder;
+}
+cfg_feature! {
+    #![feature = "msgpack-finder"]
+
+    mod msgpack_finder;
+    pub use msgpack_finder::MsgpackFinder;
+}
 cfg_feature! {
     #![feature = "bcrypt-cipher"]
 
@@ -74,6 +191,64 @@ cfg_feature! {
     }
 }
 
+cfg_feature! {
+    #![feature = "argon2-cipher"]
+
+    mod argon2_cipher;
+    pub use argon2_cipher::Argon2Cipher;
+
+    /// Helper function to create a `Csrf` use `Argon2Cipher`.
+    pub fn argon2_csrf<S>(store: S, finder: impl CsrfTokenFinder ) -> Csrf<Argon2Cipher, S> where S: CsrfStore {
+        Csrf::new(Argon2Cipher::new(), store, finder)
+    }
+}
+cfg_feature! {
+    #![all(feature = "argon2-cipher", feature = "cookie-store")]
+    /// Helper function to create a `Csrf` use `Argon2Cipher` and `CookieStore`.
+    pub fn argon2_cookie_csrf(finder: impl CsrfTokenFinder ) -> Csrf<Argon2Cipher, CookieStore> {
+        Csrf::new(Argon2Cipher::new(), CookieStore::new(), finder)
+    }
+}
+cfg_feature! {
+    #![all(feature = "argon2-cipher", feature = "session-store")]
+    /// Helper function to create a `Csrf` use `Argon2Cipher` and `SessionStore`.
+    pub fn argon2_session_csrf(finder: impl CsrfTokenFinder ) -> Csrf<Argon2Cipher, SessionStore> {
+        Csrf::new(Argon2Cipher::new(), SessionStore::new(), finder)
+    }
+}
+
+cfg_feature! {
+    #![feature = "blake3-cipher"]
+
+    mod blake3_cipher;
+    pub use blake3_cipher::Blake3Cipher;
+
+    /// Helper function to create a `Csrf` use `Blake3Cipher`.
+    pub fn blake3_csrf<S>(key: [u8; 32], store: S, finder: impl CsrfTokenFinder ) -> Csrf<Blake3Cipher, S> where S: CsrfStore {
+        Csrf::new(Blake3Cipher::new(key), store, finder)
+    }
+
+    /// Helper function to create a `Csrf` use `Blake3Cipher` with a hex or base64 key read from the
+    /// `var` environment variable.
+    pub fn blake3_csrf_from_env<S>(var: &str, store: S, finder: impl CsrfTokenFinder ) -> Result<Csrf<Blake3Cipher, S>, EnvKeyError> where S: CsrfStore {
+        Ok(blake3_csrf(kdf::key_from_env(var)?, store, finder))
+    }
+}
+cfg_feature! {
+    #![all(feature = "blake3-cipher", feature = "cookie-store")]
+    /// Helper function to create a `Csrf` use `Blake3Cipher` and `CookieStore`.
+    pub fn blake3_cookie_csrf(key: [u8; 32], finder: impl CsrfTokenFinder ) -> Csrf<Blake3Cipher, CookieStore> {
+        Csrf::new(Blake3Cipher::new(key), CookieStore::new(), finder)
+    }
+}
+cfg_feature! {
+    #![all(feature = "blake3-cipher", feature = "session-store")]
+    /// Helper function to create a `Csrf` use `Blake3Cipher` and `SessionStore`.
+    pub fn blake3_session_csrf(key: [u8; 32], finder: impl CsrfTokenFinder ) -> Csrf<Blake3Cipher, SessionStore> {
+        Csrf::new(Blake3Cipher::new(key), SessionStore::new(), finder)
+    }
+}
+
 cfg_feature! {
     #![feature = "hmac-cipher"]
 
@@ -84,6 +259,12 @@ cfg_feature! {
     pub fn hmac_csrf<S>(hmac_key: [u8; 32], store: S, finder: impl CsrfTokenFinder ) -> Csrf<HmacCipher, S> where S: CsrfStore {
         Csrf::new(HmacCipher::new(hmac_key), store, finder)
     }
+
+    /// Helper function to create a `Csrf` use `HmacCipher` with a hex or base64 key read from the
+    /// `var` environment variable.
+    pub fn hmac_csrf_from_env<S>(var: &str, store: S, finder: impl CsrfTokenFinder ) -> Result<Csrf<HmacCipher, S>, EnvKeyError> where S: CsrfStore {
+        Ok(hmac_csrf(kdf::key_from_env(var)?, store, finder))
+    }
 }
 cfg_feature! {
     #![all(feature = "hmac-cipher", feature = "cookie-store")]
@@ -110,6 +291,12 @@ cfg_feature! {
     pub fn aes_gcm_csrf<S>(aead_key: [u8; 32], store: S, finder: impl CsrfTokenFinder ) -> Csrf<AesGcmCipher, S> where S: CsrfStore {
         Csrf::new(AesGcmCipher::new(aead_key), store, finder)
     }
+
+    /// Helper function to create a `Csrf` use `AesGcmCipher` with a hex or base64 key read from the
+    /// `var` environment variable.
+    pub fn aes_gcm_csrf_from_env<S>(var: &str, store: S, finder: impl CsrfTokenFinder ) -> Result<Csrf<AesGcmCipher, S>, EnvKeyError> where S: CsrfStore {
+        Ok(aes_gcm_csrf(kdf::key_from_env(var)?, store, finder))
+    }
 }
 cfg_feature! {
     #![all(feature = "aes-gcm-cipher", feature = "cookie-store")]
@@ -125,6 +312,37 @@ cfg_feature! {
         Csrf::new(AesGcmCipher::new(aead_key), SessionStore::new(), finder)
     }
 }
+cfg_feature! {
+    #![feature = "aes-gcm-siv-cipher"]
+
+    mod aes_gcm_siv_cipher;
+    pub use aes_gcm_siv_cipher::AesGcmSivCipher;
+
+    /// Helper function to create a `Csrf` use `AesGcmSivCipher`.
+    pub fn aes_gcm_siv_csrf<S>(aead_key: [u8; 32], store: S, finder: impl CsrfTokenFinder ) -> Csrf<AesGcmSivCipher, S> where S: CsrfStore {
+        Csrf::new(AesGcmSivCipher::new(aead_key), store, finder)
+    }
+
+    /// Helper function to create a `Csrf` use `AesGcmSivCipher` with a hex or base64 key read from the
+    /// `var` environment variable.
+    pub fn aes_gcm_siv_csrf_from_env<S>(var: &str, store: S, finder: impl CsrfTokenFinder ) -> Result<Csrf<AesGcmSivCipher, S>, EnvKeyError> where S: CsrfStore {
+        Ok(aes_gcm_siv_csrf(kdf::key_from_env(var)?, store, finder))
+    }
+}
+cfg_feature! {
+    #![all(feature = "aes-gcm-siv-cipher", feature = "cookie-store")]
+    /// Helper function to create a `Csrf` use `AesGcmSivCipher` and `CookieStore`.
+    pub fn aes_gcm_siv_cookie_csrf(aead_key: [u8; 32], finder: impl CsrfTokenFinder ) -> Csrf<AesGcmSivCipher, CookieStore> {
+        Csrf::new(AesGcmSivCipher::new(aead_key), CookieStore::new(), finder)
+    }
+}
+cfg_feature! {
+    #![all(feature = "aes-gcm-siv-cipher", feature = "session-store")]
+    /// Helper function to create a `Csrf` use `AesGcmSivCipher` and `SessionStore`.
+    pub fn aes_gcm_siv_session_csrf(aead_key: [u8; 32], finder: impl CsrfTokenFinder ) -> Csrf<AesGcmSivCipher, SessionStore> {
+        Csrf::new(AesGcmSivCipher::new(aead_key), SessionStore::new(), finder)
+    }
+}
 
 cfg_feature! {
     #![feature = "ccp-cipher"]
@@ -136,6 +354,12 @@ cfg_feature! {
     pub fn ccp_csrf<S>(aead_key: [u8; 32], store: S, finder: impl CsrfTokenFinder ) -> Csrf<CcpCipher, S> where S: CsrfStore {
         Csrf::new(CcpCipher::new(aead_key), store, finder)
     }
+
+    /// Helper function to create a `Csrf` use `CcpCipher` with a hex or base64 key read from the
+    /// `var` environment variable.
+    pub fn ccp_csrf_from_env<S>(var: &str, store: S, finder: impl CsrfTokenFinder ) -> Result<Csrf<CcpCipher, S>, EnvKeyError> where S: CsrfStore {
+        Ok(ccp_csrf(kdf::key_from_env(var)?, store, finder))
+    }
 }
 cfg_feature! {
     #![all(feature = "ccp-cipher", feature = "cookie-store")]
@@ -152,11 +376,271 @@ cfg_feature! {
     }
 }
 
+cfg_feature! {
+    #![feature = "xccp-cipher"]
+
+    mod xccp_cipher;
+    pub use xccp_cipher::XCcpCipher;
+
+    /// Helper function to create a `Csrf` use `XCcpCipher`.
+    pub fn xccp_csrf<S>(aead_key: [u8; 32], store: S, finder: impl CsrfTokenFinder ) -> Csrf<XCcpCipher, S> where S: CsrfStore {
+        Csrf::new(XCcpCipher::new(aead_key), store, finder)
+    }
+
+    /// Helper function to create a `Csrf` use `XCcpCipher` with a hex or base64 key read from the
+    /// `var` environment variable.
+    pub fn xccp_csrf_from_env<S>(var: &str, store: S, finder: impl CsrfTokenFinder ) -> Result<Csrf<XCcpCipher, S>, EnvKeyError> where S: CsrfStore {
+        Ok(xccp_csrf(kdf::key_from_env(var)?, store, finder))
+    }
+}
+cfg_feature! {
+    #![all(feature = "xccp-cipher", feature = "cookie-store")]
+    /// Helper function to create a `Csrf` use `XCcpCipher` and `CookieStore`.
+    pub fn xccp_cookie_csrf(aead_key: [u8; 32], finder: impl CsrfTokenFinder ) -> Csrf<XCcpCipher, CookieStore> {
+        Csrf::new(XCcpCipher::new(aead_key), CookieStore::new(), finder)
+    }
+}
+cfg_feature! {
+    #![all(feature = "xccp-cipher", feature = "session-store")]
+    /// Helper function to create a `Csrf` use `XCcpCipher` and `SessionStore`.
+    pub fn xccp_session_csrf(aead_key: [u8; 32], finder: impl CsrfTokenFinder ) -> Csrf<XCcpCipher, SessionStore> {
+        Csrf::new(XCcpCipher::new(aead_key), SessionStore::new(), finder)
+    }
+}
+
+cfg_feature! {
+    #![feature = "test-util"]
+
+    mod test_util;
+    pub use test_util::{NoopCipher, StaticSecretStore};
+}
+
 /// key used to insert auth decoded data to depot.
 pub const CSRF_TOKEN_KEY: &str = "salvo.csrf.token";
+/// key used to insert the header name clients should send the token in to depot.
+pub const CSRF_HEADER_NAME_KEY: &str = "salvo.csrf.header_name";
+/// key of a `bool` upstream middleware can insert to depot to bypass csrf validation.
+///
+/// When the value is `true`, [`Csrf`] does not validate the request but still issues a token.
+/// A request is validated only if neither the skipper nor this flag skips it.
+pub const CSRF_BYPASS_KEY: &str = "salvo.csrf.bypass";
+/// key used to insert the capability used by [`CsrfDepotExt::rotate_csrf`] and
+/// [`CsrfDepotExt::clear_csrf`] to depot.
+const CSRF_CONTROL_KEY: &str = "salvo.csrf.control";
+
+/// `key` prefixed with the namespace of a [`Csrf`], see [`Csrf::with_namespace`].
+fn namespaced_key(namespace: Option<&str>, key: &'static str) -> Cow<'static, str> {
+    match namespace {
+        Some(namespace) => Cow::Owned(format!("{namespace}.{key}")),
+        None => Cow::Borrowed(key),
+    }
+}
+
+/// Reads the token under the depot key the `Csrf` is configured with.
+fn header_pair<'a>(depot: &'a Depot, namespace: Option<&str>) -> Option<(&'a str, &'a String)> {
+    let header_name = depot
+        .get::<String>(&namespaced_key(namespace, CSRF_HEADER_NAME_KEY))
+        .ok()?;
+    let control = depot
+        .get::<Arc<dyn TokenControl>>(&namespaced_key(namespace, CSRF_CONTROL_KEY))
+        .ok()?;
+    let token = depot.get::<String>(control.depot_key()).ok()?;
+    Some((header_name, token))
+}
+
+fn token_control(
+    depot: &Depot,
+    namespace: Option<&str>,
+) -> Result<Arc<dyn TokenControl>, CsrfError> {
+    depot
+        .get::<Arc<dyn TokenControl>>(&namespaced_key(namespace, CSRF_CONTROL_KEY))
+        .cloned()
+        .map_err(|_| CsrfError::NotHooped)
+}
+
+fn is_websocket_upgrade(req: &Request) -> bool {
+    req.method() == Method::GET
+        && req
+            .headers()
+            .get(UPGRADE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.eq_ignore_ascii_case("websocket"))
+}
+
+/// Whether the request is a browser navigation expecting an HTML page, from
+/// `Sec-Fetch-Mode`, or from `Accept` for browsers not sending it.
+fn is_html_navigation(req: &Request) -> bool {
+    match req.headers().get("sec-fetch-mode") {
+        Some(mode) => mode.as_bytes().eq_ignore_ascii_case(b"navigate"),
+        None => req
+            .headers()
+            .get(ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains("text/html")),
+    }
+}
+
+/// Whether the request was made over HTTPS, from the `X-Forwarded-Proto` header set by the
+/// proxy in front of the app when `trust_forwarded_proto` is set, else from its scheme.
+fn is_secure_request(req: &Request, trust_forwarded_proto: bool) -> bool {
+    let forwarded = trust_forwarded_proto
+        .then(|| req.headers().get("x-forwarded-proto"))
+        .flatten()
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next());
+    match forwarded {
+        Some(proto) => proto.trim().eq_ignore_ascii_case("https"),
+        None => req
+            .uri()
+            .scheme_str()
+            .unwrap_or(req.scheme().as_str())
+            .eq_ignore_ascii_case("https"),
+    }
+}
 
+/// Skips the safe methods of RFC 9110, which must not change state. Every other method,
+/// including `CONNECT` and extension methods, is validated.
 fn default_skipper(req: &mut Request, _depot: &Depot) -> bool {
-    ![Method::POST, Method::PATCH, Method::DELETE, Method::PUT].contains(req.method())
+    matches!(
+        *req.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// Reason why a request is rejected by [`Csrf`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum CsrfRejectReason {
+    /// No proof was found in the store.
+    MissingProof,
+    /// No token was found in the request.
+    MissingToken,
+    /// The token could not be decoded with the configured [`TokenEncoding`].
+    DecodeFailed,
+    /// The token does not match the proof.
+    InvalidToken,
+    /// The token or the proof is malformed for the cipher, e.g. it has the wrong length, see
+    /// [`CsrfCipher::verify_detailed`].
+    MalformedToken,
+    /// The `Sec-Fetch-Site` header shows a cross site request, see [`FetchSiteMode`].
+    CrossSite,
+    /// The `Sec-Fetch-Site` header is absent, see [`MissingFetchSite`].
+    MissingFetchSite,
+    /// The `Origin` header is neither the request origin nor trusted, see [`OriginMode`].
+    UntrustedOrigin,
+    /// The `Origin` header is absent, see [`MissingOrigin`].
+    MissingOrigin,
+    /// The hook set with [`Csrf::with_pre_check`] rejected the request.
+    PreCheck,
+    /// The request was not made over HTTPS, see [`Csrf::require_secure_request`].
+    InsecureRequest,
+}
+
+/// Outcome of [`Csrf::check_request`] and [`protect`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CsrfOutcome {
+    /// The request is valid or not validated, e.g. a `GET` request, and may proceed. Holds
+    /// the current token, as sent to clients, also inserted into the depot.
+    Allowed(String),
+    /// The request must be rejected, `Csrf` responds `403` by default.
+    Rejected(CsrfRejectReason),
+    /// The store failed, `Csrf` responds `500`.
+    Failed(CsrfError),
+}
+
+/// Validates the request and issues a token like `csrf` does as a middleware, leaving the
+/// response and calling the next handlers to the caller.
+///
+/// This suits composite middlewares not hooping a separate handler, the [`Handler`] impl of
+/// [`Csrf`] runs this too. Build the `Csrf` once, e.g. when building the composite
+/// middleware, and pass it for every request. See [`Csrf::check_request`] for what is
+/// written to `res`.
+pub async fn protect<C: CsrfCipher, S: CsrfStore>(
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+    csrf: &Csrf<C, S>,
+) -> CsrfOutcome {
+    csrf.check_with(req, depot, res, &[]).await
+}
+
+/// Outcome of the hook set with [`Csrf::with_pre_check`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum PreCheck {
+    /// Do not validate the request, a token is still issued.
+    Skip,
+    /// Validate the request as usual, according to its method and the skipper.
+    Protect,
+    /// Reject the request without validating its token.
+    Reject,
+}
+
+/// Events fired by [`Csrf`], see [`Csrf::on_event`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum CsrfEvent {
+    /// A new token was issued.
+    TokenIssued,
+    /// A protected request carried a valid token.
+    Validated,
+    /// A protected request was rejected.
+    Rejected(CsrfRejectReason),
+}
+
+/// Hook called with [`CsrfEvent`]s.
+pub type EventHook = Arc<dyn Fn(CsrfEvent) + Send + Sync>;
+
+/// Hook deciding how a request is handled before its token is checked, see
+/// [`Csrf::with_pre_check`].
+pub type PreCheckHook =
+    Arc<dyn Fn(&mut Request, &mut Depot, &mut Response) -> PreCheck + Send + Sync>;
+
+/// Hook returning the value tokens are bound to, see [`Csrf::with_binding`].
+#[cfg(feature = "binding")]
+pub type BindingHook = Arc<dyn Fn(&Depot) -> Option<Vec<u8>> + Send + Sync>;
+
+/// Hook returning the associated data proofs are authenticated with, see [`Csrf::with_aad`].
+pub type AadHook = Arc<dyn Fn(&Request, &Depot) -> Option<Vec<u8>> + Send + Sync>;
+
+/// Errors that can happen while issuing a csrf token.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum CsrfError {
+    /// The store failed to save the proof.
+    #[error("csrf store error: {0}")]
+    Store(#[source] Box<dyn StdError + Send + Sync>),
+    /// No [`Csrf`] middleware handled the request before.
+    #[error("csrf middleware is not hooped")]
+    NotHooped,
+    /// The cipher can not be used with the request, see [`CsrfCipher::prepare`].
+    #[error("csrf cipher is not ready")]
+    CipherNotReady,
+}
+
+/// Error returned when a store can not be namespaced, see [`CsrfStore::namespace`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum NamespaceError {
+    /// The store is shared behind an `Arc`, namespace it before wrapping it instead.
+    #[error(
+        "csrf store shared behind `Arc` can not be namespaced, namespace it before wrapping it"
+    )]
+    SharedStore,
+}
+
+/// Reason why a token does not verify, see [`CsrfCipher::verify_detailed`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum CsrfVerifyError {
+    /// The token or the proof can not have been generated by the cipher, e.g. it is not
+    /// valid base64 or has the wrong length.
+    #[error("malformed csrf token or proof")]
+    Malformed,
+    /// The token and the proof are well formed, but the token does not match the proof.
+    #[error("csrf token does not match its proof")]
+    Invalid,
 }
 
 /// Store proof.
@@ -170,6 +654,18 @@ pub trait CsrfStore: Send + Sync + 'static {
         depot: &mut Depot,
         cipher: &C,
     ) -> impl Future<Output = Option<(String, String)>> + Send;
+    /// Get the current token and proof followed by previously issued ones still accepted.
+    ///
+    /// The default implementation only returns the result of [`load`](Self::load), stores
+    /// keeping a history, like `SessionStore::with_history`, override it.
+    fn load_all<C: CsrfCipher>(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        cipher: &C,
+    ) -> impl Future<Output = Vec<(String, String)>> + Send {
+        async move { self.load(req, depot, cipher).await.into_iter().collect() }
+    }
     /// Save the proof from the store.
     fn save(
         &self,
@@ -179,18 +675,263 @@ pub trait CsrfStore: Send + Sync + 'static {
         token: &str,
         proof: &str,
     ) -> impl Future<Output = Result<(), Self::Error>> + Send;
+    /// Check the store can be used with the request, e.g. that the session it needs is in the
+    /// depot.
+    ///
+    /// [`Csrf`] responds `500` without validating the request when this fails, and logs the
+    /// error the first time. The default implementation always succeeds.
+    fn check(&self, depot: &mut Depot) -> Result<(), Self::Error> {
+        let _ = depot;
+        Ok(())
+    }
+    /// Prefixes the names proofs are kept under, like cookie names and session keys, with
+    /// `prefix`, see [`Csrf::with_namespace`].
+    ///
+    /// The default implementation does nothing, stores keeping proofs under a name override it.
+    fn namespace(&mut self, prefix: &str) -> Result<(), NamespaceError> {
+        let _ = prefix;
+        Ok(())
+    }
+    /// Returns whether the stored proof is due to be replaced, e.g. by
+    /// `SessionStore::with_rotation`.
+    ///
+    /// [`Csrf`] then issues a new token once the request is allowed, bound and saved through
+    /// [`save`](Self::save) like any other. The default implementation returns `false`.
+    fn rotation_due(&self, req: &Request, depot: &Depot) -> bool {
+        let _ = (req, depot);
+        false
+    }
+    /// Write back changes made while loading, e.g. proofs migrated by
+    /// `CombinedStore::migrate_on_read`.
+    ///
+    /// [`Csrf`] calls it once the request is allowed, as loading has no access to the
+    /// response. The default implementation does nothing.
+    fn persist(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let _ = (req, depot, res);
+        async { Ok(()) }
+    }
+    /// Remove the stored proofs, so the current token stops validating.
+    ///
+    /// The default implementation does nothing, stores able to forget proofs override it.
+    fn clear(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let _ = (req, depot, res);
+        async { Ok(()) }
+    }
+}
+
+/// Shares one store between several [`Csrf`] instances, e.g. a stateful store keeping its
+/// proofs in memory.
+///
+/// [`namespace`](CsrfStore::namespace) fails with [`NamespaceError::SharedStore`] if the `Arc`
+/// is shared already, namespace the store before wrapping it instead.
+impl<S: CsrfStore> CsrfStore for Arc<S> {
+    type Error = S::Error;
+    fn load<C: CsrfCipher>(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        cipher: &C,
+    ) -> impl Future<Output = Option<(String, String)>> + Send {
+        (**self).load(req, depot, cipher)
+    }
+    fn load_all<C: CsrfCipher>(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        cipher: &C,
+    ) -> impl Future<Output = Vec<(String, String)>> + Send {
+        (**self).load_all(req, depot, cipher)
+    }
+    fn save(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        token: &str,
+        proof: &str,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        (**self).save(req, depot, res, token, proof)
+    }
+    fn check(&self, depot: &mut Depot) -> Result<(), Self::Error> {
+        (**self).check(depot)
+    }
+    fn namespace(&mut self, prefix: &str) -> Result<(), NamespaceError> {
+        Arc::get_mut(self)
+            .ok_or(NamespaceError::SharedStore)?
+            .namespace(prefix)
+    }
+    fn rotation_due(&self, req: &Request, depot: &Depot) -> bool {
+        (**self).rotation_due(req, depot)
+    }
+    fn persist(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        (**self).persist(req, depot, res)
+    }
+    fn clear(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        (**self).clear(req, depot, res)
+    }
 }
 
 /// Generate token and proof and valid token.
 pub trait CsrfCipher: Send + Sync + 'static {
+    /// Prepare the cipher for a request, e.g. read its key from the depot, see
+    /// [`DeferredCipher`].
+    ///
+    /// [`Csrf`] calls it before using the cipher and responds `500` when it returns `false`.
+    /// The default implementation returns `true`.
+    fn prepare(&self, depot: &Depot) -> bool {
+        let _ = depot;
+        true
+    }
+
     /// Verify token is valid.
     fn verify(&self, token: &str, proof: &str) -> bool;
+
+    /// Verify token is valid, telling a malformed token or proof from a mismatch.
+    ///
+    /// [`Csrf`] rejects requests with [`CsrfRejectReason::MalformedToken`] when every
+    /// verification failed with [`CsrfVerifyError::Malformed`]. The default implementation
+    /// calls [`verify`](Self::verify) and reports every failure as
+    /// [`CsrfVerifyError::Invalid`].
+    fn verify_detailed(&self, token: &str, proof: &str) -> Result<(), CsrfVerifyError> {
+        if self.verify(token, proof) {
+            Ok(())
+        } else {
+            Err(CsrfVerifyError::Invalid)
+        }
+    }
+
+    /// Verify the raw bytes of a token against the bytes of its proof, decoded from url-safe
+    /// base64 when proofs are encoded.
+    ///
+    /// The default implementation encodes both and calls [`verify`](Self::verify), ciphers
+    /// override it to skip the round-trip.
+    fn verify_raw(&self, token: &[u8], proof: &[u8]) -> bool {
+        self.verify(
+            &URL_SAFE_NO_PAD.encode(token),
+            &URL_SAFE_NO_PAD.encode(proof),
+        )
+    }
+
     /// Generate new token and proof.
     fn generate(&self) -> (String, String);
 
+    /// The length of the shortest valid token, in url-safe base64.
+    ///
+    /// [`Csrf`] rejects shorter tokens without calling [`verify`](Self::verify), sparing
+    /// expensive verifications like bcrypt's. The default implementation returns 0, so every
+    /// token is verified.
+    fn min_token_len(&self) -> usize {
+        0
+    }
+
+    /// Generate new token and proof using the given random number generator.
+    ///
+    /// Seeding the generator makes the output reproducible, which is useful in tests. The
+    /// default implementation ignores `rng` and calls [`generate`](Self::generate), all the
+    /// ciphers in this crate override it.
+    fn generate_with_rng(&self, rng: &mut dyn RngCore) -> (String, String) {
+        let _ = rng;
+        self.generate()
+    }
+
+    /// Whether the cipher authenticates associated data with its proofs, see
+    /// [`Csrf::with_aad`]. The default implementation returns `false`.
+    fn supports_aad(&self) -> bool {
+        false
+    }
+
+    /// Verify token is valid and its proof was generated with the associated data `aad`.
+    ///
+    /// The default implementation ignores `aad` and calls [`verify`](Self::verify), ciphers
+    /// returning `true` from [`supports_aad`](Self::supports_aad) override it.
+    fn verify_with_aad(&self, token: &str, proof: &str, aad: &[u8]) -> bool {
+        let _ = aad;
+        self.verify(token, proof)
+    }
+
+    /// Generate new token and proof, authenticating the associated data `aad` with the proof.
+    ///
+    /// The default implementation ignores `aad` and calls [`generate`](Self::generate).
+    fn generate_with_aad(&self, aad: &[u8]) -> (String, String) {
+        let _ = aad;
+        self.generate()
+    }
+
     /// Generate a random bytes.
+    ///
+    /// The default implementation uses `rand::thread_rng`, a fast generator seeded from the
+    /// operating system and reseeded periodically, which suits tokens. The AEAD ciphers
+    /// override it with `OsRng`, reading every byte from the operating system, for their
+    /// nonces.
+    fn random_bytes(&self, len: usize) -> Vec<u8> {
+        self.random_bytes_with_rng(&mut rand::thread_rng(), len)
+    }
+
+    /// Generate a random bytes using the given random number generator.
+    fn random_bytes_with_rng(&self, rng: &mut dyn RngCore, len: usize) -> Vec<u8> {
+        let mut bytes = vec![0; len];
+        rng.fill_bytes(&mut bytes);
+        bytes
+    }
+}
+
+/// A cipher chosen at runtime, e.g. built from a `CsrfConfig`.
+impl CsrfCipher for Box<dyn CsrfCipher> {
+    fn prepare(&self, depot: &Depot) -> bool {
+        (**self).prepare(depot)
+    }
+    fn verify(&self, token: &str, proof: &str) -> bool {
+        (**self).verify(token, proof)
+    }
+    fn verify_detailed(&self, token: &str, proof: &str) -> Result<(), CsrfVerifyError> {
+        (**self).verify_detailed(token, proof)
+    }
+    fn verify_raw(&self, token: &[u8], proof: &[u8]) -> bool {
+        (**self).verify_raw(token, proof)
+    }
+    fn generate(&self) -> (String, String) {
+        (**self).generate()
+    }
+    fn min_token_len(&self) -> usize {
+        (**self).min_token_len()
+    }
+    fn generate_with_rng(&self, rng: &mut dyn RngCore) -> (String, String) {
+        (**self).generate_with_rng(rng)
+    }
+    fn supports_aad(&self) -> bool {
+        (**self).supports_aad()
+    }
+    fn verify_with_aad(&self, token: &str, proof: &str, aad: &[u8]) -> bool {
+        (**self).verify_with_aad(token, proof, aad)
+    }
+    fn generate_with_aad(&self, aad: &[u8]) -> (String, String) {
+        (**self).generate_with_aad(aad)
+    }
     fn random_bytes(&self, len: usize) -> Vec<u8> {
-        rand::thread_rng().sample_iter(Standard).take(len).collect()
+        (**self).random_bytes(len)
+    }
+    fn random_bytes_with_rng(&self, rng: &mut dyn RngCore, len: usize) -> Vec<u8> {
+        (**self).random_bytes_with_rng(rng, len)
     }
 }
 
@@ -198,298 +939,4485 @@ pub trait CsrfCipher: Send + Sync + 'static {
 pub trait CsrfDepotExt {
     /// Get csrf token reference from depot.
     fn csrf_token(&self) -> Option<&str>;
+    /// Get csrf token reference from depot, inserted by a [`Csrf`] configured with
+    /// [`Csrf::with_depot_key`].
+    fn csrf_token_with_key(&self, key: &str) -> Option<&str>;
+    /// Get the header name and csrf token pair from depot.
+    ///
+    /// The header name comes from the first [`HeaderFinder`] configured on the middleware, so
+    /// this returns `None` when no `HeaderFinder` is used. The token is read under the key set
+    /// with [`Csrf::with_depot_key`], if any.
+    fn csrf_header_pair(&self) -> Option<(&str, &String)>;
+    /// Get the header name and csrf token pair of the [`Csrf`] configured with
+    /// [`Csrf::with_namespace`], see [`csrf_header_pair`](Self::csrf_header_pair).
+    fn csrf_header_pair_with_namespace(&self, namespace: &str) -> Option<(&str, &String)>;
+    /// Issue and save a new token, replacing the current one, and return it.
+    ///
+    /// Call this after a privilege change like a login. The token in depot, the response
+    /// header and the readable token cookie are updated, and the previous token stops
+    /// validating unless the store keeps a history. It fails with [`CsrfError::NotHooped`]
+    /// when no [`Csrf`] middleware handled the request.
+    fn rotate_csrf(
+        &mut self,
+        req: &mut Request,
+        res: &mut Response,
+    ) -> impl Future<Output = Result<String, CsrfError>> + Send;
+    /// Remove the stored proof and the token in depot, e.g. on logout.
+    ///
+    /// The next request gets a new token, and protected requests are rejected until then. It
+    /// fails with [`CsrfError::NotHooped`] when no [`Csrf`] middleware handled the request.
+    fn clear_csrf(
+        &mut self,
+        req: &mut Request,
+        res: &mut Response,
+    ) -> impl Future<Output = Result<(), CsrfError>> + Send;
+    /// Rotate the token of the [`Csrf`] configured with [`Csrf::with_namespace`], see
+    /// [`rotate_csrf`](Self::rotate_csrf).
+    fn rotate_csrf_with_namespace(
+        &mut self,
+        namespace: &str,
+        req: &mut Request,
+        res: &mut Response,
+    ) -> impl Future<Output = Result<String, CsrfError>> + Send;
+    /// Clear the token of the [`Csrf`] configured with [`Csrf::with_namespace`], see
+    /// [`clear_csrf`](Self::clear_csrf).
+    fn clear_csrf_with_namespace(
+        &mut self,
+        namespace: &str,
+        req: &mut Request,
+        res: &mut Response,
+    ) -> impl Future<Output = Result<(), CsrfError>> + Send;
 }
 
 impl CsrfDepotExt for Depot {
     #[inline]
     fn csrf_token(&self) -> Option<&str> {
-        self.get::<String>(CSRF_TOKEN_KEY).map(|v| &**v).ok()
+        self.csrf_token_with_key(CSRF_TOKEN_KEY)
     }
-}
 
-/// Cross-Site Request Forgery (CSRF) protection middleware.
-pub struct Csrf<C, S> {
-    cipher: C,
-    store: S,
-    skipper: Box<dyn Skipper>,
-    finders: Vec<Box<dyn CsrfTokenFinder>>,
-}
+    #[inline]
+    fn csrf_token_with_key(&self, key: &str) -> Option<&str> {
+        self.get::<String>(key).map(|v| &**v).ok()
+    }
 
-impl<C: CsrfCipher, S: CsrfStore> Csrf<C, S> {
-    /// Create a new instance.
     #[inline]
-    pub fn new(cipher: C, store: S, finder: impl CsrfTokenFinder) -> Self {
-        Self {
-            cipher,
-            store,
-            skipper: Box::new(default_skipper),
-            finders: vec![Box::new(finder)],
-        }
+    fn csrf_header_pair(&self) -> Option<(&str, &String)> {
+        header_pair(self, None)
     }
 
-    /// Add finder to find csrf token.
     #[inline]
-    pub fn add_finder(mut self, finder: impl CsrfTokenFinder) -> Self {
-        self.finders.push(Box::new(finder));
-        self
+    fn csrf_header_pair_with_namespace(&self, namespace: &str) -> Option<(&str, &String)> {
+        header_pair(self, Some(namespace))
+    }
+
+    async fn rotate_csrf(
+        &mut self,
+        req: &mut Request,
+        res: &mut Response,
+    ) -> Result<String, CsrfError> {
+        token_control(self, None)?.rotate(req, self, res).await
     }
 
-    // /// Clear all finders.
-    // #[inline]
-    // pub fn clear_finders(mut self) -> Self {
-    //     self.finders = vec![];
-    //     self
-    // }
+    async fn clear_csrf(&mut self, req: &mut Request, res: &mut Response) -> Result<(), CsrfError> {
+        token_control(self, None)?.clear(req, self, res).await
+    }
 
-    // /// Sets all finders.
-    // #[inline]
-    // pub fn with_finders(mut self, finders: Vec<Box<dyn CsrfTokenFinder>>) -> Self {
-    //     self.finders = finders;
-    //     self
-    // }
+    async fn rotate_csrf_with_namespace(
+        &mut self,
+        namespace: &str,
+        req: &mut Request,
+        res: &mut Response,
+    ) -> Result<String, CsrfError> {
+        token_control(self, Some(namespace))?
+            .rotate(req, self, res)
+            .await
+    }
 
-    async fn find_token(&self, req: &mut Request) -> Option<String> {
-        for finder in self.finders.iter() {
-            if let Some(token) = finder.find_token(req).await {
-                return Some(token);
-            }
-        }
-        None
+    async fn clear_csrf_with_namespace(
+        &mut self,
+        namespace: &str,
+        req: &mut Request,
+        res: &mut Response,
+    ) -> Result<(), CsrfError> {
+        token_control(self, Some(namespace))?
+            .clear(req, self, res)
+            .await
     }
 }
 
+/// Handler that writes the current csrf token to the response.
+///
+/// It responds `{"token": "..."}` when the client prefers `application/json` and plain text
+/// otherwise. It must be placed under a [`Csrf`] hoop, or it responds `500`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CsrfTokenHandler;
+
+/// Helper function to create a [`CsrfTokenHandler`].
+#[inline]
+pub fn csrf_token_handler() -> CsrfTokenHandler {
+    CsrfTokenHandler
+}
+
 #[async_trait]
-impl<C: CsrfCipher, S: CsrfStore> Handler for Csrf<C, S> {
+impl Handler for CsrfTokenHandler {
     async fn handle(
         &self,
         req: &mut Request,
         depot: &mut Depot,
         res: &mut Response,
-        ctrl: &mut FlowCtrl,
+        _ctrl: &mut FlowCtrl,
     ) {
-        match self.store.load(req, depot, &self.cipher).await {
-            Some((token, proof)) => {
-                depot.insert(CSRF_TOKEN_KEY, token);
-
-                if !self.skipper.skipped(req, depot) {
-                    if let Some(token) = &self.find_token(req).await {
-                        tracing::debug!("csrf token: {token}");
-                        if !self.cipher.verify(token, &proof) {
-                            tracing::debug!(
-                                "rejecting request due to invalid or expired CSRF token"
-                            );
-                            res.status_code(StatusCode::FORBIDDEN);
-                            ctrl.skip_rest();
-                            return;
-                        } else {
-                            tracing::debug!("cipher verify CSRF token success");
-                        }
-                    } else {
-                        tracing::debug!("rejecting request due to missing CSRF token",);
-                        res.status_code(StatusCode::FORBIDDEN);
-                        ctrl.skip_rest();
-                        return;
-                    }
-                }
-                ctrl.call_next(req, depot, res).await;
-            }
-            None => {
-                if !self.skipper.skipped(req, depot) {
-                    tracing::debug!("rejecting request due to missing CSRF token",);
-                    res.status_code(StatusCode::FORBIDDEN);
-                    ctrl.skip_rest();
-                } else {
-                    let (token, proof) = self.cipher.generate();
-                    if let Err(e) = self.store.save(req, depot, res, &token, &proof).await {
-                        tracing::error!(error = ?e, "salvo csrf token failed");
-                    }
-                    tracing::debug!("new token: {:?}", token);
-                    depot.insert(CSRF_TOKEN_KEY, token);
-                    ctrl.call_next(req, depot, res).await;
-                }
-            }
-        }
+        render_token(req, depot, res, CSRF_TOKEN_KEY);
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use salvo_core::prelude::*;
-    use salvo_core::test::{ResponseExt, TestClient};
+/// The [`CsrfTokenHandler`] pushed by [`Csrf::protect`], reading the depot key of its `Csrf`.
+struct ProtectTokenHandler {
+    depot_key: Cow<'static, str>,
+}
+
+#[async_trait]
+impl Handler for ProtectTokenHandler {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        render_token(req, depot, res, &self.depot_key);
+    }
+}
+
+fn render_token(req: &Request, depot: &Depot, res: &mut Response, depot_key: &str) {
+    let Some(token) = depot.csrf_token_with_key(depot_key) else {
+        tracing::error!("csrf token not found in depot, is `Csrf` middleware hooped?");
+        res.render(StatusError::internal_server_error());
+        return;
+    };
+    let prefers_json = req
+        .accept()
+        .iter()
+        .find(|m| m.subtype() == mime::JSON || m.subtype() == mime::PLAIN)
+        .map(|m| m.subtype() == mime::JSON)
+        .unwrap_or(false);
+    if prefers_json {
+        res.render(Json(serde_json::json!({ "token": token })));
+    } else {
+        res.render(Text::Plain(token.to_owned()));
+    }
+}
+
+/// Cross-Site Request Forgery (CSRF) protection middleware.
+///
+/// `Csrf` is cheap to clone, so one configured instance can be hooped on several routers.
+///
+/// Requests using the safe methods `GET`, `HEAD`, `OPTIONS` and `TRACE` are not validated, so
+/// CORS preflights and navigations always pass. Every other method is validated, including
+/// `CONNECT` and extension methods like WebDAV's `PROPFIND`.
+pub struct Csrf<C, S> {
+    issuer: Issuer<C, S>,
+    skipper: Option<Arc<dyn Skipper>>,
+    pre_check: Option<PreCheckHook>,
+    finders: Vec<Arc<dyn CsrfTokenFinder>>,
+    auto_detect_encoding: bool,
+    websocket_finder: Option<QueryFinder>,
+    required_methods: Vec<Method>,
+    bootstrap_grace: bool,
+    monitor_mode: bool,
+    rejection_status: StatusCode,
+    reject_log_level: Level,
+    min_token_len: Option<usize>,
+    fetch_site_mode: FetchSiteMode,
+    missing_fetch_site: MissingFetchSite,
+    origin: OriginPolicy,
+    require_secure_request: bool,
+    trust_forwarded_proto: bool,
+}
+
+impl<C, S> Clone for Csrf<C, S> {
+    fn clone(&self) -> Self {
+        Self {
+            issuer: self.issuer.clone(),
+            skipper: self.skipper.clone(),
+            pre_check: self.pre_check.clone(),
+            finders: self.finders.clone(),
+            auto_detect_encoding: self.auto_detect_encoding,
+            websocket_finder: self.websocket_finder.clone(),
+            required_methods: self.required_methods.clone(),
+            bootstrap_grace: self.bootstrap_grace,
+            monitor_mode: self.monitor_mode,
+            rejection_status: self.rejection_status,
+            reject_log_level: self.reject_log_level,
+            min_token_len: self.min_token_len,
+            fetch_site_mode: self.fetch_site_mode,
+            missing_fetch_site: self.missing_fetch_site,
+            origin: self.origin.clone(),
+            require_secure_request: self.require_secure_request,
+            trust_forwarded_proto: self.trust_forwarded_proto,
+        }
+    }
+}
+
+impl<C, S> fmt::Debug for Csrf<C, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let exposure = &self.issuer.exposure;
+        let mut s = f.debug_struct("Csrf");
+        s.field("cipher", &format_args!("{}", type_name::<C>()))
+            .field("store", &format_args!("{}", type_name::<S>()))
+            .field(
+                "finders",
+                &self
+                    .finders
+                    .iter()
+                    .map(|finder| finder.type_name())
+                    .collect::<Vec<_>>(),
+            )
+            .field("skipper", &self.skipper.is_some())
+            .field("pre_check", &self.pre_check.is_some())
+            .field("encoding", &self.issuer.encoding)
+            .field("token_transform", &self.issuer.transform.is_some())
+            .field("auto_detect_encoding", &self.auto_detect_encoding)
+            .field("websocket_upgrades", &self.websocket_finder.is_some())
+            .field("required_methods", &self.required_methods)
+            .field("bootstrap_grace", &self.bootstrap_grace)
+            .field("monitor_mode", &self.monitor_mode)
+            .field("rejection_status", &self.rejection_status)
+            .field("reject_log_level", &self.reject_log_level)
+            .field("min_token_len", &self.min_token_len)
+            .field("fetch_site_mode", &self.fetch_site_mode)
+            .field("missing_fetch_site", &self.missing_fetch_site)
+            .field("origin_mode", &self.origin.mode)
+            .field("require_secure_request", &self.require_secure_request)
+            .field("trust_forwarded_proto", &self.trust_forwarded_proto)
+            .field("binding", &self.issuer.binding.is_some())
+            .field("aad", &self.issuer.aad.is_some())
+            .field("event_hook", &self.issuer.event_hook.is_some())
+            .field("header_name", &exposure.header_name)
+            .field("response_header", &exposure.response_header)
+            .field("vary", &exposure.vary);
+        #[cfg(feature = "cookie-store")]
+        s.field("token_cookie", &exposure.token_cookie);
+        s.finish()
+    }
+}
+
+/// Issues and exposes tokens, shared with the rotation capability inserted into the depot.
+struct Issuer<C, S> {
+    cipher: Arc<C>,
+    store: Arc<S>,
+    event_hook: Option<EventHook>,
+    binding: Option<Binding>,
+    aad: Option<AadHook>,
+    transform: Option<TokenTransform>,
+    encoding: TokenEncoding,
+    exposure: Arc<Exposure>,
+    skip_unchanged_save: bool,
+    check_failed: Arc<AtomicBool>,
+    depot_key: Cow<'static, str>,
+    namespace: Option<Arc<str>>,
+}
+
+impl<C, S> Clone for Issuer<C, S> {
+    fn clone(&self) -> Self {
+        Self {
+            cipher: self.cipher.clone(),
+            store: self.store.clone(),
+            event_hook: self.event_hook.clone(),
+            binding: self.binding.clone(),
+            aad: self.aad.clone(),
+            transform: self.transform.clone(),
+            encoding: self.encoding,
+            exposure: self.exposure.clone(),
+            skip_unchanged_save: self.skip_unchanged_save,
+            check_failed: self.check_failed.clone(),
+            depot_key: self.depot_key.clone(),
+            namespace: self.namespace.clone(),
+        }
+    }
+}
+
+/// Reverses a token transform, `None` rejects the token.
+type UntransformHook = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// Transforms applied to tokens after encoding them, and reversed before decoding them.
+#[derive(Clone)]
+struct TokenTransform {
+    encode: Arc<dyn Fn(&str) -> String + Send + Sync>,
+    decode: UntransformHook,
+}
+
+/// Cipher of a request, generating and verifying proofs with its associated data.
+struct AadCipher<C> {
+    inner: Arc<C>,
+    aad: Option<Vec<u8>>,
+}
+
+impl<C: CsrfCipher> CsrfCipher for AadCipher<C> {
+    fn prepare(&self, depot: &Depot) -> bool {
+        self.inner.prepare(depot)
+    }
+    fn verify(&self, token: &str, proof: &str) -> bool {
+        match &self.aad {
+            Some(aad) => self.inner.verify_with_aad(token, proof, aad),
+            None => self.inner.verify(token, proof),
+        }
+    }
+    fn verify_detailed(&self, token: &str, proof: &str) -> Result<(), CsrfVerifyError> {
+        match &self.aad {
+            Some(aad) if self.inner.verify_with_aad(token, proof, aad) => Ok(()),
+            Some(_) => Err(CsrfVerifyError::Invalid),
+            None => self.inner.verify_detailed(token, proof),
+        }
+    }
+    fn verify_raw(&self, token: &[u8], proof: &[u8]) -> bool {
+        match &self.aad {
+            Some(_) => self.verify(
+                &URL_SAFE_NO_PAD.encode(token),
+                &URL_SAFE_NO_PAD.encode(proof),
+            ),
+            None => self.inner.verify_raw(token, proof),
+        }
+    }
+    fn generate(&self) -> (String, String) {
+        match &self.aad {
+            Some(aad) => self.inner.generate_with_aad(aad),
+            None => self.inner.generate(),
+        }
+    }
+    fn min_token_len(&self) -> usize {
+        self.inner.min_token_len()
+    }
+}
+
+/// Where tokens are exposed besides the depot.
+#[derive(Clone, Default)]
+struct Exposure {
+    header_name: Option<String>,
+    response_header: Option<HeaderName>,
+    rotated_header: Option<HeaderName>,
+    #[cfg(feature = "cookie-store")]
+    token_cookie: Option<TokenCookie>,
+    vary: Option<bool>,
+    security_headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl Exposure {
+    /// Whether `Vary` is added, by default when the token is written to the response.
+    fn vary(&self) -> bool {
+        #[cfg(feature = "cookie-store")]
+        let token_cookie = self.token_cookie.is_some();
+        #[cfg(not(feature = "cookie-store"))]
+        let token_cookie = false;
+        self.vary
+            .unwrap_or(token_cookie || self.response_header.is_some())
+    }
+}
+
+/// Adds `name` to the `Vary` header of `res` unless it is already listed.
+fn add_vary(res: &mut Response, name: &str) {
+    let listed = res
+        .headers()
+        .get_all(VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| {
+            let value = value.trim();
+            value == "*" || value.eq_ignore_ascii_case(name)
+        });
+    if !listed {
+        if let Ok(value) = HeaderValue::from_str(name) {
+            res.headers_mut().append(VARY, value);
+        }
+    }
+}
+
+impl<C: CsrfCipher, S: CsrfStore> Csrf<C, S> {
+    /// Create a new instance.
+    #[inline]
+    pub fn new(cipher: C, store: S, finder: impl CsrfTokenFinder) -> Self {
+        Self {
+            issuer: Issuer {
+                cipher: Arc::new(cipher),
+                store: Arc::new(store),
+                event_hook: None,
+                binding: None,
+                aad: None,
+                transform: None,
+                encoding: TokenEncoding::UrlSafeNoPad,
+                exposure: Arc::new(Exposure {
+                    header_name: finder.header_name().map(ToOwned::to_owned),
+                    ..Default::default()
+                }),
+                skip_unchanged_save: false,
+                check_failed: Arc::new(AtomicBool::new(false)),
+                depot_key: Cow::Borrowed(CSRF_TOKEN_KEY),
+                namespace: None,
+            },
+            skipper: None,
+            pre_check: None,
+            finders: vec![Arc::new(finder)],
+            auto_detect_encoding: false,
+            websocket_finder: None,
+            required_methods: vec![],
+            bootstrap_grace: false,
+            monitor_mode: false,
+            rejection_status: StatusCode::FORBIDDEN,
+            reject_log_level: Level::WARN,
+            min_token_len: None,
+            fetch_site_mode: FetchSiteMode::Disabled,
+            missing_fetch_site: MissingFetchSite::ValidateToken,
+            origin: OriginPolicy::default(),
+            require_secure_request: false,
+            trust_forwarded_proto: false,
+        }
+    }
+
+    /// Create a new instance finding the token in the `x-csrf-token` header.
+    ///
+    /// Like every instance, it only validates requests using unsafe methods, like `POST`.
+    #[inline]
+    pub fn with_defaults(cipher: C, store: S) -> Self {
+        Self::new(cipher, store, HeaderFinder::new("x-csrf-token"))
+    }
+
+    /// Sets a skipper, requests it skips are not validated, e.g. a [`RegexSkipper`].
+    ///
+    /// Requests using safe methods like `GET` are always skipped, unless passed to
+    /// [`require_on`](Self::require_on), the skipper can only exempt more requests.
+    #[inline]
+    pub fn with_skipper(mut self, skipper: impl Skipper) -> Self {
+        self.skipper = Some(Arc::new(skipper));
+        self
+    }
+
+    /// Sets a hook called first on every request, which can skip validation, keep the usual
+    /// validation or reject the request, e.g. a detected bot.
+    ///
+    /// On [`PreCheck::Reject`], the rest of the handlers are skipped, and the rejection status
+    /// is set unless the hook set one, so the hook can also write a custom response.
+    #[inline]
+    pub fn with_pre_check(
+        mut self,
+        hook: impl Fn(&mut Request, &mut Depot, &mut Response) -> PreCheck + Send + Sync + 'static,
+    ) -> Self {
+        self.pre_check = Some(Arc::new(hook));
+        self
+    }
+
+    /// Skips validation for requests authenticated with a bearer token, see
+    /// [`BearerAuthSkipper`]. This replaces the skipper set by [`with_skipper`](Self::with_skipper).
+    #[inline]
+    pub fn skip_bearer_auth(self) -> Self {
+        self.with_skipper(BearerAuthSkipper::new())
+    }
+
+    /// Also validates websocket upgrade requests, finding their token with `finder`.
+    ///
+    /// Browsers can not set custom headers on websocket handshakes, so the token is passed in
+    /// the query string, e.g. `new WebSocket("wss://example.com/ws?csrf-token=" + token)`.
+    /// `finder` is only used for upgrade requests, and the store is read from the cookies the
+    /// browser sends with the handshake. Hoop `Csrf` on the router of the websocket handler so
+    /// the upgrade is rejected with `403` before it happens:
+    ///
+    /// ```ignore
+    /// Router::with_path("ws")
+    ///     .hoop(csrf.protect_websocket_upgrades(QueryFinder::new()))
+    ///     .goal(connect)
+    /// ```
+    #[inline]
+    pub fn protect_websocket_upgrades(mut self, finder: QueryFinder) -> Self {
+        self.websocket_finder = Some(finder);
+        self
+    }
+
+    /// Also validates requests using `methods`, e.g. `GET` endpoints with side effects.
+    ///
+    /// Requests using safe methods are otherwise never validated. A client first visiting
+    /// such an endpoint has no token yet and is rejected, unless
+    /// [`with_bootstrap_grace`](Self::with_bootstrap_grace) is enabled.
+    #[inline]
+    pub fn require_on(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.required_methods = methods.into_iter().collect();
+        self
+    }
+
+    /// Lets requests using a method passed to [`require_on`](Self::require_on) through
+    /// without a token when no token was issued to the client yet, defaults to `false`.
+    ///
+    /// The request is not validated and a new token is issued, so the first visit works.
+    /// This also lets a cross site page trigger the endpoint for clients without a token.
+    #[inline]
+    pub fn with_bootstrap_grace(mut self, grace: bool) -> Self {
+        self.bootstrap_grace = grace;
+        self
+    }
+
+    /// Lets requests failing validation through instead of rejecting them, defaults to
+    /// `false`.
+    ///
+    /// They are still logged and fire [`CsrfEvent::Rejected`], so the protection can be
+    /// rolled out gradually by watching what would have been rejected.
+    #[inline]
+    pub fn monitor_mode(mut self, monitor: bool) -> Self {
+        self.monitor_mode = monitor;
+        self
+    }
+
+    /// Sets the status code of rejected requests, defaults to `403 Forbidden`.
+    #[inline]
+    pub fn with_rejection_status(mut self, status: StatusCode) -> Self {
+        self.rejection_status = status;
+        self
+    }
+
+    /// Sets the level rejections are logged at with their [`CsrfRejectReason`], defaults to
+    /// `WARN`.
+    #[inline]
+    pub fn with_reject_log_level(mut self, level: Level) -> Self {
+        self.reject_log_level = level;
+        self
+    }
+
+    /// Sets the length of the shortest token verified, in url-safe base64, defaults to
+    /// [`CsrfCipher::min_token_len`].
+    ///
+    /// Shorter tokens are rejected with [`CsrfRejectReason::DecodeFailed`] before the cipher
+    /// runs.
+    #[inline]
+    pub fn with_min_token_len(mut self, len: usize) -> Self {
+        self.min_token_len = Some(len);
+        self
+    }
+
+    /// Add finder to find csrf token.
+    #[inline]
+    pub fn add_finder(mut self, finder: impl CsrfTokenFinder) -> Self {
+        if self.issuer.exposure.header_name.is_none() {
+            Arc::make_mut(&mut self.issuer.exposure).header_name =
+                finder.header_name().map(ToOwned::to_owned);
+        }
+        self.finders.push(Arc::new(finder));
+        self
+    }
+
+    /// Replaces all finders, they are tried in order.
+    ///
+    /// The header name exposed in depot comes from the first [`HeaderFinder`] among them, use
+    /// [`default_finders`] for the common header, form and query combination.
+    #[inline]
+    pub fn with_finders(mut self, finders: Vec<Box<dyn CsrfTokenFinder>>) -> Self {
+        Arc::make_mut(&mut self.issuer.exposure).header_name = finders
+            .iter()
+            .find_map(|finder| finder.header_name())
+            .map(ToOwned::to_owned);
+        self.finders = finders.into_iter().map(Arc::from).collect();
+        self
+    }
+
+    /// Returns the number of configured finders.
+    #[inline]
+    pub fn finder_count(&self) -> usize {
+        self.finders.len()
+    }
+
+    /// Returns the type names of the configured finders, in the order they are tried.
+    #[inline]
+    pub fn finder_names(&self) -> Vec<&'static str> {
+        self.finders
+            .iter()
+            .map(|finder| finder.type_name())
+            .collect()
+    }
+
+    /// Sets a response header that the current token is written to.
+    ///
+    /// The header is set before the next handler is called, so handlers can still override
+    /// or remove it.
+    #[inline]
+    pub fn with_response_header(mut self, name: HeaderName) -> Self {
+        Arc::make_mut(&mut self.issuer.exposure).response_header = Some(name);
+        self
+    }
+
+    /// Sets a response header set to `true` whenever a new token is issued, e.g.
+    /// `X-CSRF-Rotated`.
+    ///
+    /// Clients caching the token, like SPAs, can refresh it when the header is present. It is
+    /// set for the first token, after [`CsrfDepotExt::rotate_csrf`] and when an expired proof
+    /// is replaced, and absent while the current token is reused.
+    #[inline]
+    pub fn with_rotated_header(mut self, name: HeaderName) -> Self {
+        Arc::make_mut(&mut self.issuer.exposure).rotated_header = Some(name);
+        self
+    }
+
+    /// Sets the name of a cookie that the current token is written to, readable from JavaScript.
+    ///
+    /// This follows the convention used by Angular and axios, which read an `XSRF-TOKEN`
+    /// cookie and echo it in a request header. The cookie is not `HttpOnly`, the proof stored
+    /// by [`CookieStore`] stays `HttpOnly`.
+    #[cfg(feature = "cookie-store")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cookie-store")))]
+    #[inline]
+    pub fn with_readable_token_cookie(self, name: impl Into<String>) -> Self {
+        self.with_token_cookie(TokenCookie::new(name))
+    }
+
+    /// Like [`with_readable_token_cookie`](Self::with_readable_token_cookie), with the
+    /// attributes of `cookie`, independent from the ones of the [`CookieStore`] cookie.
+    #[cfg(feature = "cookie-store")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "cookie-store")))]
+    #[inline]
+    pub fn with_token_cookie(mut self, cookie: TokenCookie) -> Self {
+        Arc::make_mut(&mut self.issuer.exposure).token_cookie = Some(cookie);
+        self
+    }
+
+    /// Adds `Cookie` and the header of the first [`HeaderFinder`] to the `Vary` response
+    /// header, so shared caches do not serve a response carrying the token of one client to
+    /// another.
+    ///
+    /// Defaults to `true` when the token is written to a response header or a readable
+    /// cookie, `false` otherwise.
+    #[inline]
+    pub fn with_vary(mut self, vary: bool) -> Self {
+        Arc::make_mut(&mut self.issuer.exposure).vary = Some(vary);
+        self
+    }
+
+    /// Sets headers added to responses of HTML navigations, e.g. `Cross-Origin-Opener-Policy`
+    /// or `X-Frame-Options`, complementing the csrf protection of pages rendering forms.
+    ///
+    /// Navigations are detected from `Sec-Fetch-Mode: navigate`, or from an `Accept` header
+    /// listing `text/html` when `Sec-Fetch-Mode` is absent. The headers are set before the
+    /// next handler is called, so handlers can still override them. None are set by default.
+    #[inline]
+    pub fn with_security_headers(
+        mut self,
+        headers: impl IntoIterator<Item = (HeaderName, HeaderValue)>,
+    ) -> Self {
+        Arc::make_mut(&mut self.issuer.exposure).security_headers = headers.into_iter().collect();
+        self
+    }
+
+    /// Sets a hook that is called with a [`CsrfEvent`] when tokens are issued, validated or
+    /// rejected, e.g. to increment metrics counters.
+    #[inline]
+    pub fn on_event(mut self, hook: impl Fn(CsrfEvent) + Send + Sync + 'static) -> Self {
+        self.issuer.event_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Sets the depot key the token is inserted under, defaults to [`CSRF_TOKEN_KEY`].
+    ///
+    /// Use distinct keys when several `Csrf` instances handle the same request, and read the
+    /// tokens with [`CsrfDepotExt::csrf_token_with_key`]. [`CsrfTokenHandler`] and
+    /// [`CsrfDepotExt::csrf_token`] only read the default key.
+    #[inline]
+    pub fn with_depot_key(mut self, key: &'static str) -> Self {
+        self.issuer.depot_key = Cow::Borrowed(key);
+        self
+    }
+
+    /// Prefixes every name this instance keeps state under with `prefix`, so several `Csrf`
+    /// instances with different policies can protect overlapping routes.
+    ///
+    /// The depot key becomes `<prefix>.salvo.csrf.token`, read it with
+    /// [`CsrfDepotExt::csrf_token_with_key`], and the token is rotated, cleared and read with
+    /// the `*_with_namespace` methods of [`CsrfDepotExt`] and [`CsrfToken::with_namespace`].
+    /// The names of the store, like the `CookieStore`
+    /// cookie or the `SessionStore` key, and the cookie set by
+    /// [`with_token_cookie`](Self::with_token_cookie) become `<prefix>.<name>`. Call it after
+    /// [`with_depot_key`](Self::with_depot_key) and `with_token_cookie`, whose names are
+    /// prefixed when this is called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the store can not be namespaced, see
+    /// [`try_with_namespace`](Self::try_with_namespace).
+    pub fn with_namespace(self, prefix: &str) -> Self
+    where
+        S: Clone,
+    {
+        match self.try_with_namespace(prefix) {
+            Ok(csrf) => csrf,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// Prefixes every name this instance keeps state under with `prefix`, see
+    /// [`with_namespace`](Self::with_namespace), failing if the store can not be namespaced.
+    pub fn try_with_namespace(mut self, prefix: &str) -> Result<Self, NamespaceError>
+    where
+        S: Clone,
+    {
+        Arc::make_mut(&mut self.issuer.store).namespace(prefix)?;
+        self.issuer.depot_key = Cow::Owned(format!("{prefix}.{}", self.issuer.depot_key));
+        self.issuer.namespace = Some(match &self.issuer.namespace {
+            Some(namespace) => format!("{prefix}.{namespace}").into(),
+            None => prefix.into(),
+        });
+        #[cfg(feature = "cookie-store")]
+        {
+            let exposure = Arc::make_mut(&mut self.issuer.exposure);
+            exposure.token_cookie = exposure
+                .token_cookie
+                .take()
+                .map(|cookie| cookie.prefixed(prefix));
+        }
+        Ok(self)
+    }
+
+    /// Skips saving a newly issued token when it equals the current one.
+    ///
+    /// The proofs loaded from the store are cached in the depot for the rest of the request,
+    /// so `Csrf` clones hooped on nested routers read the store once. With this enabled, a
+    /// token equal to the cached one, e.g. from a deterministic cipher, is not written back,
+    /// which saves a round trip for stores with I/O cost. Defaults to `false`.
+    #[inline]
+    pub fn with_skip_unchanged_save(mut self, skip: bool) -> Self {
+        self.issuer.skip_unchanged_save = skip;
+        self
+    }
+
+    /// Binds tokens to a value read from the depot, e.g. the session id or the user id.
+    ///
+    /// A tag derived from the token and the value is saved with the proof, and a stored proof
+    /// whose tag does not match the current value is treated as absent. A token issued before
+    /// login is therefore rejected after login, and a new one is issued.
+    ///
+    /// The tag is an HMAC keyed with `key`, which must be kept secret, so a client can not
+    /// compute the tag for another user's value even when it can write the store.
+    #[cfg(feature = "binding")]
+    #[inline]
+    pub fn with_binding(
+        mut self,
+        key: [u8; 32],
+        binding: impl Fn(&Depot) -> Option<Vec<u8>> + Send + Sync + 'static,
+    ) -> Self {
+        self.issuer.binding = Some(Binding::new(key, Arc::new(binding)));
+        self
+    }
+
+    /// Authenticates proofs with associated data read from the request and the depot, e.g. the
+    /// request path or the user id.
+    ///
+    /// Unlike [`with_binding`](Self::with_binding), the data is authenticated by the cipher
+    /// itself, so a token generated with one value fails verification under another, whatever
+    /// the store. Returning `None` uses no associated data.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cipher does not support associated data, see
+    /// [`CsrfCipher::supports_aad`].
+    #[inline]
+    pub fn with_aad(
+        mut self,
+        aad: impl Fn(&Request, &Depot) -> Option<Vec<u8>> + Send + Sync + 'static,
+    ) -> Self {
+        assert!(
+            self.issuer.cipher.supports_aad(),
+            "csrf cipher does not support associated data"
+        );
+        self.issuer.aad = Some(Arc::new(aad));
+        self
+    }
+
+    /// Sets the encoding of tokens exposed to and received from clients, defaults to
+    /// [`TokenEncoding::UrlSafeNoPad`].
+    #[inline]
+    pub fn with_encoding(mut self, encoding: TokenEncoding) -> Self {
+        self.issuer.encoding = encoding;
+        self
+    }
+
+    /// Sets the base64 engine tokens exposed to and received from clients are encoded with,
+    /// e.g. for a custom alphabet, see [`TokenEncoding::Base64`].
+    #[inline]
+    pub fn with_base64_engine(self, engine: &'static GeneralPurpose) -> Self {
+        self.with_encoding(TokenEncoding::Base64(engine))
+    }
+
+    /// Transforms tokens sent to clients after encoding them, e.g. to add a prefix or embed
+    /// them in a signed envelope with metadata, while `decode` reverses it for received tokens.
+    ///
+    /// Tokens for which `decode` returns `None` are rejected. Ciphers and stores only see
+    /// untransformed tokens, the transform defaults to identity.
+    #[inline]
+    pub fn with_token_transform(
+        mut self,
+        encode: impl Fn(&str) -> String + Send + Sync + 'static,
+        decode: impl Fn(&str) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.issuer.transform = Some(TokenTransform {
+            encode: Arc::new(encode),
+            decode: Arc::new(decode),
+        });
+        self
+    }
+
+    /// Accept tokens in any [`TokenEncoding`], not only the configured one.
+    #[inline]
+    pub fn with_encoding_auto_detect(mut self, auto_detect: bool) -> Self {
+        self.auto_detect_encoding = auto_detect;
+        self
+    }
+
+    /// Sets how the `Sec-Fetch-Site` header is used on protected requests, defaults to
+    /// [`FetchSiteMode::Disabled`].
+    #[inline]
+    pub fn with_fetch_site_mode(mut self, mode: FetchSiteMode) -> Self {
+        self.fetch_site_mode = mode;
+        self
+    }
+
+    /// Sets what to do when a protected request has no `Sec-Fetch-Site` header, defaults to
+    /// [`MissingFetchSite::ValidateToken`]. Only used when the fetch site mode is enabled.
+    #[inline]
+    pub fn with_missing_fetch_site(mut self, missing: MissingFetchSite) -> Self {
+        self.missing_fetch_site = missing;
+        self
+    }
+
+    /// Trusts `origins`, like `https://app.example.com`, besides the request origin.
+    ///
+    /// This enables the `Origin` gate with [`OriginMode::WithToken`] unless another mode was
+    /// set. Protected requests pass the gates in order: `Sec-Fetch-Site`, `Origin`, then the
+    /// token, and the first gate rejecting or accepting the request decides. List `null` to
+    /// trust requests from opaque origins, like sandboxed iframes.
+    #[inline]
+    pub fn with_trusted_origins(mut self, origins: Vec<String>) -> Self {
+        self.origin.trusted = origins;
+        if self.origin.mode == OriginMode::Disabled {
+            self.origin.mode = OriginMode::WithToken;
+        }
+        self
+    }
+
+    /// Sets how the `Origin` header is used on protected requests, defaults to
+    /// [`OriginMode::Disabled`].
+    #[inline]
+    pub fn with_origin_mode(mut self, mode: OriginMode) -> Self {
+        self.origin.mode = mode;
+        self
+    }
+
+    /// Sets what to do when a protected request has no `Origin` header, defaults to
+    /// [`MissingOrigin::ValidateToken`]. Only used when the origin mode is enabled.
+    #[inline]
+    pub fn with_missing_origin(mut self, missing: MissingOrigin) -> Self {
+        self.origin.missing = missing;
+        self
+    }
+
+    /// Sets whether the `null` origin sent by native apps and some webviews passes the `Origin`
+    /// gate, defaults to `false`. Only used when the origin mode is enabled.
+    ///
+    /// Attackers can send `null` too, from sandboxed iframes or `data:` URLs, so such requests
+    /// always have their token validated, even with [`OriginMode::Standalone`]. Mismatched
+    /// origins are still rejected.
+    #[inline]
+    pub fn allow_null_origin(mut self, allow: bool) -> Self {
+        self.origin.allow_null = allow;
+        self
+    }
+
+    /// Sets whether protected requests not made over HTTPS are rejected with
+    /// [`CsrfRejectReason::InsecureRequest`], defaults to `false`.
+    ///
+    /// Tokens and cookies sent over plain HTTP can be read and replayed by anyone on the
+    /// network. Behind a TLS terminating proxy, also set
+    /// [`trust_forwarded_proto`](Self::trust_forwarded_proto).
+    #[inline]
+    pub fn require_secure_request(mut self, require: bool) -> Self {
+        self.require_secure_request = require;
+        self
+    }
+
+    /// Sets whether the `X-Forwarded-Proto` header decides if a request was made over HTTPS,
+    /// defaults to `false`. Only used when [`require_secure_request`](Self::require_secure_request)
+    /// is set.
+    ///
+    /// Only enable it behind a proxy overwriting the header, clients can set it to anything.
+    #[inline]
+    pub fn trust_forwarded_proto(mut self, trust: bool) -> Self {
+        self.trust_forwarded_proto = trust;
+        self
+    }
+
+    /// Replaces the cipher with `cipher`, keeping the previous one as a fallback, so tokens
+    /// issued before the migration keep validating while new tokens use `cipher`.
+    ///
+    /// The other settings are kept. Migrating again keeps every previous cipher, see
+    /// [`FallbackCipher`].
+    pub fn migrate_cipher<N: CsrfCipher>(self, cipher: N) -> Csrf<FallbackCipher<N>, S> {
+        let issuer = self.issuer;
+        let mut cipher = FallbackCipher::new(cipher);
+        cipher.push_fallback(issuer.cipher);
+        Csrf {
+            issuer: Issuer {
+                cipher: Arc::new(cipher),
+                store: issuer.store,
+                event_hook: issuer.event_hook,
+                binding: issuer.binding,
+                aad: issuer.aad,
+                transform: issuer.transform,
+                encoding: issuer.encoding,
+                exposure: issuer.exposure,
+                skip_unchanged_save: issuer.skip_unchanged_save,
+                check_failed: issuer.check_failed,
+                depot_key: issuer.depot_key,
+                namespace: issuer.namespace,
+            },
+            skipper: self.skipper,
+            pre_check: self.pre_check,
+            finders: self.finders,
+            auto_detect_encoding: self.auto_detect_encoding,
+            websocket_finder: self.websocket_finder,
+            required_methods: self.required_methods,
+            bootstrap_grace: self.bootstrap_grace,
+            monitor_mode: self.monitor_mode,
+            rejection_status: self.rejection_status,
+            reject_log_level: self.reject_log_level,
+            min_token_len: self.min_token_len,
+            fetch_site_mode: self.fetch_site_mode,
+            missing_fetch_site: self.missing_fetch_site,
+            origin: self.origin,
+            require_secure_request: self.require_secure_request,
+            trust_forwarded_proto: self.trust_forwarded_proto,
+        }
+    }
+
+    /// Hoops this `Csrf` on `router` and pushes a [`CsrfTokenHandler`] leaf at `token_path`,
+    /// for the common "GET the token, send it with everything else" setup:
+    ///
+    /// ```ignore
+    /// let router = Csrf::new(cipher, store, finder).protect(
+    ///     Router::new().push(Router::with_path("items").post(create_item)),
+    ///     "csrf-token",
+    /// );
+    /// ```
+    ///
+    /// The token route is itself protected, so only its safe methods are answered without
+    /// a token. It reads the token under the depot key of this `Csrf`, see
+    /// [`with_depot_key`](Self::with_depot_key) and [`with_namespace`](Self::with_namespace).
+    ///
+    /// With the `oapi` feature, the endpoints of `router` also document the csrf header, see
+    /// [`oapi_security_scheme`](Self::oapi_security_scheme).
+    pub fn protect(self, router: Router, token_path: impl Into<String>) -> Router {
+        let handler = ProtectTokenHandler {
+            depot_key: self.issuer.depot_key.clone(),
+        };
+        #[cfg(feature = "oapi")]
+        let router = self.oapi_document(router);
+        router
+            .hoop(self)
+            .push(Router::with_path(token_path).get(handler))
+    }
+
+    /// Issues a token without validating the request, e.g. for a page only rendering a form
+    /// and served by a method the middleware would otherwise protect.
+    ///
+    /// The stored token is reused if there is one, otherwise a new one is saved. It is exposed
+    /// like the middleware does, so `depot.csrf_token()` returns it afterwards. Returns the
+    /// token encoded as sent to clients.
+    pub async fn issue_token(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<String, CsrfError> {
+        self.issuer
+            .store
+            .check(depot)
+            .map_err(|e| CsrfError::Store(Box::new(e)))?;
+        if !self.issuer.cipher.prepare(depot) {
+            return Err(CsrfError::CipherNotReady);
+        }
+        let loaded = self.issuer.load_valid(req, depot).await;
+        self.issuer.reuse_or_issue(req, depot, res, loaded).await
+    }
+
+    /// Validates the request and issues a token like the middleware, but leaves the response
+    /// and calling the next handlers to the caller, e.g. in a composite middleware.
+    ///
+    /// The status and body of `res` are left untouched, but its headers and cookies are
+    /// written as by the middleware:
+    ///
+    /// - the store saves an issued token, e.g. in a cookie,
+    /// - the token is exposed with `with_token_cookie`,
+    ///   [`with_response_header`](Self::with_response_header) and
+    ///   [`with_rotated_header`](Self::with_rotated_header), and `Vary` is extended unless
+    ///   disabled with [`with_vary`](Self::with_vary),
+    /// - [`with_security_headers`](Self::with_security_headers) are added to HTML navigations,
+    /// - the hook of [`with_pre_check`](Self::with_pre_check) gets `res` and may write to it.
+    pub async fn check_request(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> CsrfOutcome {
+        protect(req, depot, res, self).await
+    }
+
+    /// Verify a token as sent by the client against a proof as stored, as the middleware does
+    /// for protected requests: the token is decoded and checked against
+    /// [`with_min_token_len`](Self::with_min_token_len), and the
+    /// [binding](Self::with_binding) and [associated data](Self::with_aad) of `req` and `depot`
+    /// must match those the proof was generated with.
+    ///
+    /// This can be used to validate tokens manually, e.g. for a websocket upgrade, without
+    /// going through the middleware. The proof is the string kept by the store rather than
+    /// raw secret bytes, as a bound proof carries its binding tag.
+    pub fn verify_token(&self, req: &Request, depot: &Depot, token: &str, proof: &str) -> bool {
+        let candidates = self.candidates(&[token]);
+        // The binding tag covers the token, so it is checked for each candidate.
+        let proofs: Vec<_> = candidates
+            .iter()
+            .filter_map(|token| {
+                self.issuer
+                    .check_binding(depot, token.clone(), proof.to_owned())
+            })
+            .collect();
+        let cipher = self.issuer.request_cipher(req, depot);
+        verify_candidates(&cipher, &candidates, &proofs).is_ok()
+    }
+
+    /// Verify the raw bytes of a token against a proof as stored, with the same checks as
+    /// [`verify_token`](Self::verify_token), see [`CsrfCipher::verify_raw`].
+    ///
+    /// Unlike `verify_token`, the token is not decoded, which suits server-to-server flows
+    /// already holding the bytes.
+    pub fn verify_raw(&self, req: &Request, depot: &Depot, token: &[u8], proof: &str) -> bool {
+        let encoded = URL_SAFE_NO_PAD.encode(token);
+        if encoded.len() < self.min_token_len() {
+            return false;
+        }
+        let Some((_, proof)) = self.issuer.check_binding(depot, encoded, proof.to_owned()) else {
+            return false;
+        };
+        match URL_SAFE_NO_PAD.decode(proof) {
+            Ok(proof) => self
+                .issuer
+                .request_cipher(req, depot)
+                .verify_raw(token, &proof),
+            Err(_) => false,
+        }
+    }
+
+    /// Generate a new token, encoded as sent to clients, and its proof as stored, bound and
+    /// authenticated with the associated data of `req` and `depot` like the middleware does.
+    ///
+    /// The proof is returned as the string to store, see [`verify_token`](Self::verify_token).
+    #[inline]
+    pub fn generate_token(&self, req: &Request, depot: &Depot) -> (String, String) {
+        let (token, proof) = self.issuer.generate(req, depot);
+        (self.issuer.encode_token(&token), proof)
+    }
+
+    fn min_token_len(&self) -> usize {
+        self.min_token_len
+            .unwrap_or_else(|| self.issuer.cipher.min_token_len())
+    }
+
+    /// Decode a client token to the cipher's url-safe base64 format. With auto detection,
+    /// every encoding the token is valid in is returned.
+    fn decode_token(&self, token: &str) -> Vec<String> {
+        let token = match &self.issuer.transform {
+            Some(transform) => match (transform.decode)(token) {
+                Some(token) => Cow::Owned(token),
+                None => return vec![],
+            },
+            None => Cow::Borrowed(token),
+        };
+        let token = &*token;
+        let mut encodings = vec![self.issuer.encoding];
+        if self.auto_detect_encoding {
+            encodings.extend(
+                TokenEncoding::ALL
+                    .into_iter()
+                    .filter(|encoding| *encoding != self.issuer.encoding),
+            );
+        }
+        let mut decoded = Vec::new();
+        for encoding in &encodings {
+            if *encoding == TokenEncoding::UrlSafeNoPad {
+                decoded.push(token.to_owned());
+            } else if let Some(data) = encoding.decode(token) {
+                decoded.push(TokenEncoding::UrlSafeNoPad.encode(&data));
+            }
+        }
+        decoded
+    }
+
+    /// Decoded client tokens long enough to be checked.
+    fn candidates<T: AsRef<str>>(&self, tokens: &[T]) -> Vec<String> {
+        let min_token_len = self.min_token_len();
+        tokens
+            .iter()
+            .flat_map(|token| self.decode_token(token.as_ref()))
+            .filter(|token| token.len() >= min_token_len)
+            .collect()
+    }
+
+    /// Candidate tokens from the first finder finding any, `extra_finders` are tried last.
+    async fn find_tokens(
+        &self,
+        req: &mut Request,
+        depot: &Depot,
+        extra_finders: &[Arc<dyn CsrfTokenFinder>],
+    ) -> Vec<String> {
+        for finder in self.finders.iter().chain(extra_finders) {
+            let tokens = finder.find_tokens_with_depot(req, depot).await;
+            if !tokens.is_empty() {
+                return tokens;
+            }
+        }
+        match &self.websocket_finder {
+            Some(finder) if is_websocket_upgrade(req) => {
+                finder.find_tokens_with_depot(req, depot).await
+            }
+            _ => vec![],
+        }
+    }
+
+    async fn validate(
+        &self,
+        req: &mut Request,
+        depot: &Depot,
+        proofs: &[(String, String)],
+        extra_finders: &[Arc<dyn CsrfTokenFinder>],
+    ) -> Result<(), CsrfRejectReason> {
+        if self.require_secure_request && !is_secure_request(req, self.trust_forwarded_proto) {
+            return Err(CsrfRejectReason::InsecureRequest);
+        }
+        for check in [
+            check_fetch_site(req, self.fetch_site_mode, self.missing_fetch_site),
+            check_origin(req, &self.origin),
+        ] {
+            match check {
+                FetchSiteCheck::Accept => return Ok(()),
+                FetchSiteCheck::Reject(reason) => return Err(reason),
+                FetchSiteCheck::ValidateToken => {}
+            }
+        }
+        if proofs.is_empty() {
+            return Err(CsrfRejectReason::MissingProof);
+        }
+        let tokens = self.find_tokens(req, depot, extra_finders).await;
+        if tokens.is_empty() {
+            return Err(CsrfRejectReason::MissingToken);
+        }
+        tracing::debug!(
+            tokens = ?tokens.iter().map(|token| truncate_token(token)).collect::<Vec<_>>(),
+            "csrf tokens found"
+        );
+        let candidates = self.candidates(&tokens);
+        let cipher = self.issuer.request_cipher(req, depot);
+        verify_candidates(&cipher, &candidates, proofs)
+    }
+}
+
+/// Checks every candidate token against every proof, as [`Csrf::validate`] and
+/// [`Csrf::verify_token`] do.
+fn verify_candidates(
+    cipher: &impl CsrfCipher,
+    candidates: &[String],
+    proofs: &[(String, String)],
+) -> Result<(), CsrfRejectReason> {
+    if candidates.is_empty() {
+        return Err(CsrfRejectReason::DecodeFailed);
+    }
+    let mut malformed = true;
+    for token in candidates {
+        for (_, proof) in proofs {
+            match cipher.verify_detailed(token, proof) {
+                Ok(()) => return Ok(()),
+                Err(CsrfVerifyError::Invalid) => malformed = false,
+                Err(CsrfVerifyError::Malformed) => {}
+            }
+        }
+    }
+    if malformed {
+        Err(CsrfRejectReason::MalformedToken)
+    } else {
+        Err(CsrfRejectReason::InvalidToken)
+    }
+}
+
+impl<C: CsrfCipher, S: CsrfStore> Issuer<C, S> {
+    fn encode_token(&self, token: &str) -> String {
+        let encoded = if self.encoding == TokenEncoding::UrlSafeNoPad {
+            token.to_owned()
+        } else {
+            match TokenEncoding::UrlSafeNoPad.decode(token) {
+                Some(data) => self.encoding.encode(&data),
+                None => token.to_owned(),
+            }
+        };
+        match &self.transform {
+            Some(transform) => (transform.encode)(&encoded),
+            None => encoded,
+        }
+    }
+
+    fn emit(&self, event: CsrfEvent) {
+        if let Some(hook) = &self.event_hook {
+            hook(event);
+        }
+    }
+
+    /// Depot key the loaded proofs are cached under, shared by clones of the same `Csrf`.
+    fn namespaced_key(&self, key: &'static str) -> Cow<'static, str> {
+        namespaced_key(self.namespace.as_deref(), key)
+    }
+
+    fn cache_key(&self) -> String {
+        format!("salvo.csrf.loaded.{:p}", Arc::as_ptr(&self.store))
+    }
+
+    /// The cipher used for the current request, authenticating the associated data if any.
+    fn request_cipher(&self, req: &Request, depot: &Depot) -> AadCipher<C> {
+        AadCipher {
+            inner: self.cipher.clone(),
+            aad: self.aad.as_ref().and_then(|aad| aad(req, depot)),
+        }
+    }
+
+    /// Loads the proofs from the store, or from the depot if they were loaded before.
+    async fn load_cached(&self, req: &mut Request, depot: &mut Depot) -> Vec<(String, String)> {
+        let cache_key = self.cache_key();
+        if let Ok(pairs) = depot.get::<Vec<(String, String)>>(&cache_key) {
+            return pairs.clone();
+        }
+        let cipher = self.request_cipher(req, depot);
+        let pairs = self.store.load_all(req, depot, &cipher).await;
+        depot.insert(cache_key, pairs.clone());
+        pairs
+    }
+
+    /// Generates a token and its proof, bound to the current binding value.
+    fn generate(&self, req: &Request, depot: &Depot) -> (String, String) {
+        let (token, mut proof) = self.request_cipher(req, depot).generate();
+        if let Some(binding) = &self.binding {
+            if let Some(value) = binding.value(depot) {
+                proof = binding.bind_proof(&proof, &token, &value);
+            }
+        }
+        (token, proof)
+    }
+
+    async fn issue_token(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<String, CsrfError> {
+        let (token, proof) = self.generate(req, depot);
+        let cache_key = self.cache_key();
+        let unchanged = self.skip_unchanged_save
+            && depot
+                .get::<Vec<(String, String)>>(&cache_key)
+                .ok()
+                .and_then(|pairs| pairs.first())
+                .is_some_and(|(t, p)| *t == token && *p == proof);
+        if unchanged {
+            tracing::debug!("csrf token unchanged, skipping save");
+        } else {
+            self.store
+                .save(req, depot, res, &token, &proof)
+                .await
+                .map_err(|e| CsrfError::Store(Box::new(e)))?;
+            let mut pairs = depot
+                .remove::<Vec<(String, String)>>(&cache_key)
+                .unwrap_or_default();
+            pairs.insert(0, (token.clone(), proof));
+            depot.insert(cache_key, pairs);
+            if let Some(name) = &self.exposure.rotated_header {
+                res.headers_mut()
+                    .insert(name.clone(), HeaderValue::from_static("true"));
+            }
+        }
+        tracing::debug!(token = truncate_token(&token), "new csrf token issued");
+        self.emit(CsrfEvent::TokenIssued);
+        Ok(token)
+    }
+
+    /// Strips the binding tag from a loaded proof, drops the pair if the binding changed.
+    fn check_binding(
+        &self,
+        depot: &Depot,
+        token: String,
+        proof: String,
+    ) -> Option<(String, String)> {
+        let (cipher_proof, tag) = binding::split_proof(&proof);
+        let matches = match &self.binding {
+            Some(binding) => binding.matches(tag, &token, binding.value(depot).as_deref()),
+            None => tag.is_none(),
+        };
+        if matches {
+            let cipher_proof = cipher_proof.to_owned();
+            Some((token, cipher_proof))
+        } else {
+            tracing::debug!("csrf binding changed, discarding stored proof");
+            None
+        }
+    }
+
+    /// Loads the proofs of the request, dropping those whose binding changed.
+    async fn load_valid(&self, req: &mut Request, depot: &mut Depot) -> Vec<(String, String)> {
+        self.load_cached(req, depot)
+            .await
+            .into_iter()
+            .filter_map(|(token, proof)| self.check_binding(depot, token, proof))
+            .collect()
+    }
+
+    /// Exposes the current token, issuing one if none was loaded, and returns it encoded.
+    async fn reuse_or_issue(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        loaded: Vec<(String, String)>,
+    ) -> Result<String, CsrfError> {
+        self.store
+            .persist(req, depot, res)
+            .await
+            .map_err(|e| CsrfError::Store(Box::new(e)))?;
+        let token = match loaded.into_iter().next() {
+            Some((token, _)) if !self.store.rotation_due(req, depot) => token,
+            Some(_) => {
+                tracing::debug!("csrf token due for rotation");
+                self.issue_token(req, depot, res).await?
+            }
+            None => self.issue_token(req, depot, res).await?,
+        };
+        let exposed = self.expose_token(req, depot, res, token);
+        depot.insert(
+            self.namespaced_key(CSRF_CONTROL_KEY),
+            Arc::new(self.clone()) as Arc<dyn TokenControl>,
+        );
+        Ok(exposed)
+    }
+
+    /// Exposes the token to the client and handlers, returns it encoded.
+    fn expose_token(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        token: String,
+    ) -> String {
+        let token = self.encode_token(&token);
+        #[cfg(feature = "cookie-store")]
+        if let Some(token_cookie) = &self.exposure.token_cookie {
+            let https = req.uri().scheme() == Some(&salvo_core::http::uri::Scheme::HTTPS);
+            res.add_cookie(token_cookie.build(token.clone(), https));
+        }
+        if let Some(name) = &self.exposure.response_header {
+            match HeaderValue::from_str(&token) {
+                Ok(value) => {
+                    res.headers_mut().insert(name.clone(), value);
+                }
+                Err(e) => tracing::error!(error = ?e, "invalid csrf token header value"),
+            }
+        }
+        if !self.exposure.security_headers.is_empty() && is_html_navigation(req) {
+            for (name, value) in &self.exposure.security_headers {
+                res.headers_mut().insert(name.clone(), value.clone());
+            }
+        }
+        if self.exposure.vary() {
+            add_vary(res, "cookie");
+            if let Some(header_name) = &self.exposure.header_name {
+                add_vary(res, header_name);
+            }
+        }
+        if let Some(header_name) = &self.exposure.header_name {
+            depot.insert(
+                self.namespaced_key(CSRF_HEADER_NAME_KEY),
+                header_name.clone(),
+            );
+        }
+        extract::insert_token(req, self.namespace.clone(), CsrfToken(token.clone()));
+        depot.insert(&*self.depot_key, token.clone());
+        token
+    }
+}
+
+/// Capability the middleware inserts to depot, closing over its cipher and store.
+#[async_trait]
+trait TokenControl: Send + Sync {
+    fn depot_key(&self) -> &str;
+
+    async fn rotate(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<String, CsrfError>;
+
+    async fn clear(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<(), CsrfError>;
+}
+
+#[async_trait]
+impl<C: CsrfCipher, S: CsrfStore> TokenControl for Issuer<C, S> {
+    fn depot_key(&self) -> &str {
+        &self.depot_key
+    }
+
+    async fn rotate(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<String, CsrfError> {
+        let token = self.issue_token(req, depot, res).await?;
+        Ok(self.expose_token(req, depot, res, token))
+    }
+
+    async fn clear(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<(), CsrfError> {
+        self.store
+            .clear(req, depot, res)
+            .await
+            .map_err(|e| CsrfError::Store(Box::new(e)))?;
+        #[cfg(feature = "cookie-store")]
+        if let Some(token_cookie) = &self.exposure.token_cookie {
+            res.add_cookie(token_cookie.removal());
+        }
+        depot.delete(&self.cache_key());
+        depot.delete(&self.depot_key);
+        extract::remove_token(req, self.namespace.as_deref());
+        Ok(())
+    }
+}
+
+cfg_feature! {
+    #![feature = "oapi"]
+
+    impl<C: CsrfCipher, S: CsrfStore> Csrf<C, S> {
+        /// Returns an OpenAPI security scheme describing the header the csrf token is sent in.
+        ///
+        /// Middlewares are not visible to `salvo-oapi`, [`protect`](Self::protect) adds the
+        /// scheme to the router it protects, named `csrf` or `csrf-{namespace}` with
+        /// [`with_namespace`](Self::with_namespace). When hooping the `Csrf` yourself, register
+        /// it with `OpenApi::add_security_scheme` and reference it from the protected router
+        /// with `RouterExt::oapi_security`. Returns `None` when no [`HeaderFinder`] is
+        /// configured.
+        pub fn oapi_security_scheme(&self) -> Option<salvo_oapi::security::SecurityScheme> {
+            use salvo_oapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+
+            self.issuer.exposure.header_name.as_ref().map(|name| {
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                    name.clone(),
+                    "CSRF token".into(),
+                )))
+            })
+        }
+
+        /// Adds the security scheme and a requirement for it to `router`.
+        fn oapi_document(&self, router: Router) -> Router {
+            use salvo_oapi::{RouterExt, SecurityRequirement};
+
+            let Some(scheme) = self.oapi_security_scheme() else {
+                return router;
+            };
+            let name = match &self.issuer.namespace {
+                Some(namespace) => format!("csrf-{namespace}"),
+                None => "csrf".to_owned(),
+            };
+            router
+                .oapi_security_scheme(name.clone(), scheme)
+                .oapi_security(SecurityRequirement::new(name, Vec::<String>::new()))
+        }
+    }
+}
+
+#[async_trait]
+impl<C: CsrfCipher, S: CsrfStore> Handler for Csrf<C, S> {
+    async fn handle(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+    ) {
+        let outcome = protect(req, depot, res, self).await;
+        self.respond(req, depot, res, ctrl, self.rejection_status, outcome)
+            .await;
+    }
+}
+
+/// The start of a token, enough to correlate logs without leaking it.
+fn truncate_token(token: &str) -> String {
+    let mut truncated: String = token.chars().take(6).collect();
+    if truncated.len() < token.len() {
+        truncated.push_str("...");
+    }
+    truncated
+}
+
+fn log_rejection(level: Level, reason: CsrfRejectReason, monitor: bool) {
+    macro_rules! log {
+        ($level:expr) => {
+            tracing::event!($level, ?reason, monitor, "csrf protected request rejected")
+        };
+    }
+    match level {
+        Level::ERROR => log!(Level::ERROR),
+        Level::WARN => log!(Level::WARN),
+        Level::INFO => log!(Level::INFO),
+        Level::DEBUG => log!(Level::DEBUG),
+        _ => log!(Level::TRACE),
+    }
+}
+
+impl<C: CsrfCipher, S: CsrfStore> Csrf<C, S> {
+    /// Handles the request, rejecting with `rejection_status` and also trying
+    /// `extra_finders`, as overridden by [`CsrfOverride`].
+    async fn protect_with(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+        rejection_status: StatusCode,
+        extra_finders: &[Arc<dyn CsrfTokenFinder>],
+    ) {
+        let outcome = self.check_with(req, depot, res, extra_finders).await;
+        self.respond(req, depot, res, ctrl, rejection_status, outcome)
+            .await;
+    }
+
+    /// Validates the request and issues a token, also trying `extra_finders`. Every entry
+    /// point, the handler, [`protect`] and [`check_request`](Self::check_request), runs this.
+    async fn check_with(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        extra_finders: &[Arc<dyn CsrfTokenFinder>],
+    ) -> CsrfOutcome {
+        let span = tracing::info_span!("csrf", method = %req.method(), path = %req.uri().path());
+        self.evaluate(req, depot, res, extra_finders)
+            .instrument(span)
+            .await
+    }
+
+    /// Responds to `outcome` as the middleware does.
+    async fn respond(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        ctrl: &mut FlowCtrl,
+        rejection_status: StatusCode,
+        outcome: CsrfOutcome,
+    ) {
+        match outcome {
+            CsrfOutcome::Allowed(_) => {
+                ctrl.call_next(req, depot, res).await;
+            }
+            CsrfOutcome::Rejected(reason) => {
+                if reason != CsrfRejectReason::PreCheck || res.status_code.is_none() {
+                    res.status_code(rejection_status);
+                }
+                ctrl.skip_rest();
+            }
+            CsrfOutcome::Failed(_) => {
+                res.render(StatusError::internal_server_error());
+                ctrl.skip_rest();
+            }
+        }
+    }
+
+    /// Validates the request and issues a token, without responding.
+    async fn evaluate(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        res: &mut Response,
+        extra_finders: &[Arc<dyn CsrfTokenFinder>],
+    ) -> CsrfOutcome {
+        let issuer = &self.issuer;
+        let pre_check = match &self.pre_check {
+            Some(hook) => hook(req, depot, res),
+            None => PreCheck::Protect,
+        };
+        if pre_check == PreCheck::Reject {
+            log_rejection(self.reject_log_level, CsrfRejectReason::PreCheck, false);
+            issuer.emit(CsrfEvent::Rejected(CsrfRejectReason::PreCheck));
+            return CsrfOutcome::Rejected(CsrfRejectReason::PreCheck);
+        }
+        if let Err(e) = issuer.store.check(depot) {
+            if !issuer.check_failed.swap(true, Ordering::Relaxed) {
+                tracing::error!(
+                    error = %e,
+                    "csrf store can not be used, every request is answered with 500 until the \
+                     configuration is fixed"
+                );
+            } else {
+                tracing::debug!(error = %e, "csrf store can not be used");
+            }
+            return CsrfOutcome::Failed(CsrfError::Store(Box::new(e)));
+        }
+        if !issuer.cipher.prepare(depot) {
+            tracing::error!("csrf cipher is not ready, e.g. its key is missing from the depot");
+            return CsrfOutcome::Failed(CsrfError::CipherNotReady);
+        }
+        let loaded = issuer.load_valid(req, depot).await;
+        let bypassed = depot.get::<bool>(CSRF_BYPASS_KEY).copied().unwrap_or(false);
+        let upgrade = self.websocket_finder.is_some() && is_websocket_upgrade(req);
+        let required = self.required_methods.contains(req.method());
+        let skipped = pre_check == PreCheck::Skip
+            || (default_skipper(req, depot) && !upgrade && !required)
+            || (required && self.bootstrap_grace && loaded.is_empty())
+            || self
+                .skipper
+                .as_ref()
+                .is_some_and(|skipper| skipper.skipped(req, depot));
+        if !skipped && !bypassed {
+            match self.validate(req, depot, &loaded, extra_finders).await {
+                Ok(()) => {
+                    tracing::debug!("cipher verify CSRF token success");
+                    issuer.emit(CsrfEvent::Validated);
+                }
+                Err(reason) if self.monitor_mode => {
+                    log_rejection(self.reject_log_level, reason, true);
+                    issuer.emit(CsrfEvent::Rejected(reason));
+                }
+                Err(reason) => {
+                    log_rejection(self.reject_log_level, reason, false);
+                    issuer.emit(CsrfEvent::Rejected(reason));
+                    return CsrfOutcome::Rejected(reason);
+                }
+            }
+        }
+        match issuer.reuse_or_issue(req, depot, res, loaded).await {
+            Ok(exposed) => CsrfOutcome::Allowed(exposed),
+            Err(e) => {
+                tracing::error!(error = ?e, "salvo csrf issue token failed");
+                CsrfOutcome::Failed(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    #[handler]
+    async fn get_index(depot: &mut Depot) -> String {
+        depot.csrf_token().unwrap().to_owned()
+    }
+    #[handler]
+    async fn post_index() -> &'static str {
+        "POST"
+    }
+
+    #[tokio::test]
+    async fn test_exposes_csrf_request_extensions() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index);
+        let res = TestClient::get("http://127.0.0.1:5801").send(router).await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_adds_csrf_cookie_sets_request_token() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801").send(router).await;
+
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert_ne!(res.take_string().await.unwrap(), "");
+        assert_ne!(res.cookie("salvo.csrf"), None);
+    }
+
+    #[tokio::test]
+    async fn test_validates_token_in_header() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let mut res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", csrf_token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert_eq!(res.take_string().await.unwrap(), "POST");
+    }
+
+    #[tokio::test]
+    async fn test_validates_token_in_custom_header() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-mycsrf-header"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let mut res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-mycsrf-header", csrf_token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert_eq!(res.take_string().await.unwrap(), "POST");
+    }
+
+    #[tokio::test]
+    async fn test_validates_token_in_query() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let mut res = TestClient::post("http://127.0.0.1:5801?a=1&b=2")
+            .add_header("csrf-token", csrf_token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert_eq!(res.take_string().await.unwrap(), "POST");
+    }
+    #[cfg(feature = "hmac-cipher")]
+    #[tokio::test]
+    async fn test_validates_token_in_alternate_query() {
+        let csrf = Csrf::new(
+            HmacCipher::new(*b"01234567012345670123456701234567"),
+            CookieStore::new(),
+            HeaderFinder::new("my-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let mut res = TestClient::post("http://127.0.0.1:5801?a=1&b=2")
+            .add_header("my-csrf-token", csrf_token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert_eq!(res.take_string().await.unwrap(), "POST");
+    }
+
+    #[cfg(feature = "hmac-cipher")]
+    #[tokio::test]
+    async fn test_validates_token_in_form() {
+        let csrf = Csrf::new(
+            HmacCipher::new(*b"01234567012345670123456701234567"),
+            CookieStore::new(),
+            FormFinder::new("csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let mut res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("cookie", cookie.to_string(), true)
+            .form(&[("a", "1"), ("csrf-token", &*csrf_token), ("b", "2")])
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert_eq!(res.take_string().await.unwrap(), "POST");
+    }
+    #[tokio::test]
+    async fn test_validates_token_in_alternate_form() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            FormFinder::new("my-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+        let mut res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("cookie", cookie.to_string(), true)
+            .form(&[("a", "1"), ("my-csrf-token", &*csrf_token), ("b", "2")])
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert_eq!(res.take_string().await.unwrap(), "POST");
+    }
+
+    #[tokio::test]
+    async fn test_csrf_header_pair() {
+        #[handler]
+        async fn get_pair(depot: &mut Depot) -> String {
+            let (name, token) = depot.csrf_header_pair().unwrap();
+            format!("{name}:{token}")
+        }
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            FormFinder::new("csrf-token"),
+        )
+        .add_finder(HeaderFinder::new("x-my-csrf-token"))
+        .add_finder(HeaderFinder::new("x-other-csrf-token"));
+        let router = Router::new().hoop(csrf).get(get_pair);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let pair = res.take_string().await.unwrap();
+        let (name, token) = pair.split_once(':').unwrap();
+        assert_eq!(name, "x-my-csrf-token");
+        assert!(!token.is_empty());
+
+        let cookie = res.cookie("salvo.csrf").unwrap();
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-my-csrf-token", token.to_owned(), true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_ne!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_response_header() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_response_header(HeaderName::from_static("x-csrf-token"));
+        let router = Router::new().hoop(csrf).get(get_index);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801").send(router).await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let header = res
+            .headers()
+            .get("x-csrf-token")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        assert!(!header.is_empty());
+        assert_eq!(header, res.take_string().await.unwrap());
+    }
+
+    #[cfg(feature = "oapi")]
+    #[tokio::test]
+    async fn test_oapi_security_scheme() {
+        use salvo_oapi::{OpenApi, RouterExt, SecurityRequirement};
+
+        #[salvo_oapi::endpoint]
+        async fn post_item() -> &'static str {
+            "POST"
+        }
+
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let scheme = csrf.oapi_security_scheme().unwrap();
+        let router = Router::new()
+            .hoop(csrf)
+            .oapi_security(SecurityRequirement::new("csrf", Vec::<String>::new()))
+            .push(Router::with_path("items").post(post_item));
+        let doc = OpenApi::new("test api", "0.0.1")
+            .add_security_scheme("csrf", scheme)
+            .merge_router(&router);
+        let json = serde_json::to_value(&doc).unwrap();
+
+        assert_eq!(
+            json["components"]["securitySchemes"]["csrf"]["name"],
+            "x-csrf-token"
+        );
+        assert_eq!(
+            json["components"]["securitySchemes"]["csrf"]["in"],
+            "header"
+        );
+        assert!(json["paths"]["/items"]["post"]["security"][0]
+            .get("csrf")
+            .is_some());
+    }
+
+    #[cfg(feature = "oapi")]
+    #[tokio::test]
+    async fn test_oapi_protect_documents_header() {
+        use salvo_oapi::OpenApi;
+
+        #[salvo_oapi::endpoint]
+        async fn create_item() -> &'static str {
+            "POST"
+        }
+
+        let router = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .protect(
+            Router::new().push(Router::with_path("items").post(create_item)),
+            "csrf-token",
+        );
+        let doc = OpenApi::new("test api", "0.0.1").merge_router(&router);
+        let json = serde_json::to_value(&doc).unwrap();
+
+        assert_eq!(
+            json["components"]["securitySchemes"]["csrf"],
+            serde_json::json!({
+                "type": "apiKey",
+                "name": "x-csrf-token",
+                "in": "header",
+                "description": "CSRF token",
+            })
+        );
+        assert!(json["paths"]["/items"]["post"]["security"][0]
+            .get("csrf")
+            .is_some());
+
+        let router = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            FormFinder::new("csrf-token"),
+        )
+        .protect(
+            Router::new().push(Router::with_path("items").post(create_item)),
+            "csrf-token",
+        );
+        let doc = OpenApi::new("test api", "0.0.1").merge_router(&router);
+        assert!(doc.components.security_schemes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_csrf_token_handler() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new()
+            .hoop(csrf)
+            .push(Router::with_path("csrf").get(csrf_token_handler()))
+            .post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/csrf")
+            .add_header("accept", "application/json", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert_eq!(res.content_type().unwrap().subtype(), mime::JSON);
+        let json = res.take_json::<serde_json::Value>().await.unwrap();
+        let token = json["token"].as_str().unwrap().to_owned();
+        assert!(!token.is_empty());
+
+        let cookie = res.cookie("salvo.csrf").unwrap();
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/csrf")
+            .add_header("accept", "text/plain", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert_eq!(res.content_type().unwrap().subtype(), mime::PLAIN);
+        assert!(!res.take_string().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_readable_token_cookie() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-xsrf-token"),
+        )
+        .with_readable_token_cookie("XSRF-TOKEN");
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let token_cookie = res.cookie("XSRF-TOKEN").unwrap().clone();
+        assert_ne!(token_cookie.http_only(), Some(true));
+        assert_eq!(token_cookie.value(), res.take_string().await.unwrap());
+        let secret_cookie = res.cookie("salvo.csrf").unwrap();
+        assert_eq!(secret_cookie.http_only(), Some(true));
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-xsrf-token", token_cookie.value().to_owned(), true)
+            .add_header("cookie", secret_cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_cookie_attributes() {
+        use cookie::SameSite;
+
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new().path("/app").same_site(SameSite::Lax),
+            HeaderFinder::new("x-xsrf-token"),
+        )
+        .with_token_cookie(
+            TokenCookie::new("XSRF-TOKEN")
+                .path("/")
+                .same_site(SameSite::None),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let secret_cookie = res.cookie("salvo.csrf").unwrap();
+        assert_eq!(secret_cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(secret_cookie.path(), Some("/app"));
+        assert_eq!(secret_cookie.http_only(), Some(true));
+        assert_ne!(secret_cookie.secure(), Some(true));
+        let token_cookie = res.cookie("XSRF-TOKEN").unwrap();
+        assert_eq!(token_cookie.same_site(), Some(SameSite::None));
+        assert_eq!(token_cookie.path(), Some("/"));
+        assert_ne!(token_cookie.http_only(), Some(true));
+        assert_eq!(token_cookie.secure(), Some(true));
+
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new().same_site(SameSite::None),
+            HeaderFinder::new("x-xsrf-token"),
+        )
+        .with_readable_token_cookie("XSRF-TOKEN");
+        let router = Router::new().hoop(csrf).get(get_index);
+        let res = TestClient::get("http://127.0.0.1:5801").send(router).await;
+        let secret_cookie = res.cookie("salvo.csrf").unwrap();
+        assert_eq!(secret_cookie.same_site(), Some(SameSite::None));
+        assert_eq!(secret_cookie.secure(), Some(true));
+        let token_cookie = res.cookie("XSRF-TOKEN").unwrap();
+        assert_eq!(token_cookie.same_site(), Some(SameSite::Strict));
+        assert_ne!(token_cookie.secure(), Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_clone_for_multiple_routers() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_response_header(HeaderName::from_static("x-csrf-token"));
+        let router = Router::new()
+            .push(
+                Router::with_path("a")
+                    .hoop(csrf.clone())
+                    .get(get_index)
+                    .post(post_index),
+            )
+            .push(
+                Router::with_path("b")
+                    .hoop(csrf)
+                    .get(get_index)
+                    .post(post_index),
+            );
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/a")
+            .send(&service)
+            .await;
+        assert!(res.headers().contains_key("x-csrf-token"));
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        for path in ["a", "b"] {
+            let res = TestClient::post(format!("http://127.0.0.1:5801/{path}"))
+                .add_header("x-csrf-token", &csrf_token, true)
+                .add_header("cookie", cookie.to_string(), true)
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+            let res = TestClient::post(format!("http://127.0.0.1:5801/{path}"))
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+        }
+    }
+
+    #[cfg(feature = "regex-skipper")]
+    #[tokio::test]
+    async fn test_regex_skipper() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_skipper(RegexSkipper::new("^/webhooks/.*").unwrap());
+        let router = Router::new()
+            .hoop(csrf)
+            .get(get_index)
+            .push(Router::with_path("webhooks/stripe").post(post_index))
+            .push(Router::with_path("api/pay").post(post_index));
+        let service = Service::new(router);
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let res = TestClient::post("http://127.0.0.1:5801/webhooks/stripe")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let res = TestClient::post("http://127.0.0.1:5801/api/pay")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_skip_bearer_auth() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .skip_bearer_auth();
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("authorization", "Bearer abc", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_websocket_upgrade() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .protect_websocket_upgrades(QueryFinder::new());
+        let router = Router::new()
+            .hoop(csrf)
+            .get(get_index)
+            .push(Router::with_path("ws").get(post_index));
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        for (query, status_code) in [
+            (format!("?csrf-token={csrf_token}"), StatusCode::OK),
+            (String::new(), StatusCode::FORBIDDEN),
+            ("?csrf-token=invalid".to_owned(), StatusCode::FORBIDDEN),
+        ] {
+            let res = TestClient::get(format!("http://127.0.0.1:5801/ws{query}"))
+                .add_header("connection", "upgrade", true)
+                .add_header("upgrade", "websocket", true)
+                .add_header("cookie", cookie.to_string(), true)
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), status_code, "{query}");
+        }
+
+        let res = TestClient::get("http://127.0.0.1:5801/ws")
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_bypass_flag() {
+        #[handler]
+        async fn set_bypass(req: &mut Request, depot: &mut Depot) {
+            if req.header::<String>("x-internal").is_some() {
+                depot.insert(CSRF_BYPASS_KEY, true);
+            }
+        }
+
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new()
+            .hoop(set_bypass)
+            .hoop(csrf)
+            .get(get_index)
+            .post(post_index);
+        let service = Service::new(router);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-internal", "1", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert!(res.cookie("salvo.csrf").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_host_prefixed_cookie() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new().with_host_prefix(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        assert!(res.cookie("salvo.csrf").is_none());
+        let cookie = res.cookie("__Host-salvo.csrf").unwrap();
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.domain(), None);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", csrf_token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_signed_and_private_cookie() {
+        use salvo_core::http::cookie::{Cookie, Key};
+
+        let key = Key::generate();
+        for store in [CookieStore::signed(key.clone()), CookieStore::private(key)] {
+            let csrf = Csrf::new(
+                BcryptCipher::new(),
+                store,
+                HeaderFinder::new("x-csrf-token"),
+            );
+            let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+            let service = Service::new(router);
+
+            let mut res = TestClient::get("http://127.0.0.1:5801")
+                .send(&service)
+                .await;
+            let csrf_token = res.take_string().await.unwrap();
+            let cookie = res.cookie("salvo.csrf").unwrap().clone();
+            assert!(!cookie.value().starts_with(&csrf_token));
+
+            let res = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", &csrf_token, true)
+                .add_header("cookie", cookie.to_string(), true)
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+            let mut tampered = cookie.value().to_owned();
+            let first = if tampered.starts_with('A') { "B" } else { "A" };
+            tampered.replace_range(0..1, first);
+            let res = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", &csrf_token, true)
+                .add_header(
+                    "cookie",
+                    Cookie::new("salvo.csrf", tampered).to_string(),
+                    true,
+                )
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+        }
+    }
+
+    #[cfg(feature = "binding")]
+    #[tokio::test]
+    async fn test_binding() {
+        #[handler]
+        async fn set_user(req: &mut Request, depot: &mut Depot) {
+            if let Some(user) = req.header::<String>("x-user") {
+                depot.insert("user", user);
+            }
+        }
+
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_binding([5; 32], |depot| {
+            depot
+                .get::<String>("user")
+                .ok()
+                .map(|user| user.as_bytes().to_vec())
+        });
+        let router = Router::new()
+            .hoop(set_user)
+            .hoop(csrf)
+            .get(get_index)
+            .post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let anonymous_token = res.take_string().await.unwrap();
+        let anonymous_cookie = res.cookie("salvo.csrf").unwrap().to_string();
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("x-user", "alice", true)
+            .send(&service)
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
+
+        for (token, cookie, user, status_code) in [
+            (&csrf_token, &cookie, Some("alice"), StatusCode::OK),
+            (&csrf_token, &cookie, Some("bob"), StatusCode::FORBIDDEN),
+            (&csrf_token, &cookie, None, StatusCode::FORBIDDEN),
+            (&anonymous_token, &anonymous_cookie, None, StatusCode::OK),
+            (
+                &anonymous_token,
+                &anonymous_cookie,
+                Some("alice"),
+                StatusCode::FORBIDDEN,
+            ),
+        ] {
+            let mut req = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", token, true)
+                .add_header("cookie", cookie, true);
+            if let Some(user) = user {
+                req = req.add_header("x-user", user, true);
+            }
+            let res = req.send(&service).await;
+            assert_eq!(res.status_code.unwrap(), status_code, "{user:?}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "must not set a domain")]
+    fn test_host_prefixed_cookie_rejects_domain() {
+        let _ = CookieStore::new().domain("example.com").with_host_prefix();
+    }
+
+    #[test]
+    #[should_panic(expected = "must use path `/`")]
+    fn test_host_prefixed_cookie_rejects_path() {
+        let _ = CookieStore::new().with_host_prefix().path("/admin");
+    }
+
+    #[test]
+    fn test_random_bytes() {
+        let cipher = BcryptCipher::new();
+        let issued: std::collections::HashSet<_> =
+            (0..10_000).map(|_| cipher.random_bytes(32)).collect();
+        assert_eq!(issued.len(), 10_000);
+        assert!(issued.iter().all(|bytes| bytes.len() == 32));
+        assert!(cipher.random_bytes(0).is_empty());
+    }
+
+    /// Compares `random_bytes` with sampling each byte from a new `thread_rng` handle, as it
+    /// did before, run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "timing comparison"]
+    fn test_random_bytes_timing() {
+        use std::hint::black_box;
+        use std::time::Instant;
+
+        use rand::distributions::Standard;
+        use rand::Rng;
+
+        const ROUNDS: usize = 1_000_000;
+        let cipher = BcryptCipher::new();
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            black_box(cipher.random_bytes(32));
+        }
+        let filled = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            black_box(
+                rand::thread_rng()
+                    .sample_iter(Standard)
+                    .take(32)
+                    .collect::<Vec<u8>>(),
+            );
+        }
+        let sampled = start.elapsed();
+
+        println!("{ROUNDS} rounds: filled {filled:?}, sampled per byte {sampled:?}");
+        assert!(filled < sampled);
+    }
+
+    #[test]
+    fn test_generate_and_verify_token() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let (req, depot) = (Request::new(), Depot::new());
+        let (token, proof) = csrf.generate_token(&req, &depot);
+        assert!(csrf.verify_token(&req, &depot, &token, &proof));
+
+        let (other_token, other_proof) = csrf.generate_token(&req, &depot);
+        assert_ne!(token, other_token);
+        assert!(!csrf.verify_token(&req, &depot, &token, &other_proof));
+        assert!(!csrf.verify_token(&req, &depot, "not base64!", &proof));
+    }
+
+    #[cfg(feature = "binding")]
+    #[test]
+    fn test_verify_token_checks_binding_and_length() {
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_binding([5; 32], |depot| {
+            depot
+                .get::<String>("user")
+                .ok()
+                .map(|user| user.as_bytes().to_vec())
+        });
+        let req = Request::new();
+        let depot = |user: Option<&str>| {
+            let mut depot = Depot::new();
+            if let Some(user) = user {
+                depot.insert("user", user.to_owned());
+            }
+            depot
+        };
+        let (token, proof) = csrf.generate_token(&req, &depot(Some("alice")));
+        assert!(proof.contains('~'));
+        assert!(csrf.verify_token(&req, &depot(Some("alice")), &token, &proof));
+        assert!(!csrf.verify_token(&req, &depot(Some("bob")), &token, &proof));
+        assert!(!csrf.verify_token(&req, &depot(None), &token, &proof));
+
+        let csrf = csrf.with_min_token_len(token.len() + 1);
+        assert!(!csrf.verify_token(&req, &depot(Some("alice")), &token, &proof));
+    }
+
+    #[cfg(feature = "aes-gcm-cipher")]
+    #[test]
+    fn test_verify_token_checks_aad() {
+        let csrf = Csrf::new(
+            AesGcmCipher::new([1; 32]),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_aad(|req, _| Some(req.uri().path().as_bytes().to_vec()));
+        let depot = Depot::new();
+        let req = |path: &str| TestClient::get(format!("http://127.0.0.1:5801{path}")).build();
+        let (token, proof) = csrf.generate_token(&req("/a"), &depot);
+        assert!(csrf.verify_token(&req("/a"), &depot, &token, &proof));
+        assert!(!csrf.verify_token(&req("/b"), &depot, &token, &proof));
+    }
+
+    #[test]
+    fn test_verify_raw() {
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let (req, depot) = (Request::new(), Depot::new());
+        let (token, proof) = csrf.generate_token(&req, &depot);
+        let raw_token = URL_SAFE_NO_PAD.decode(&token).unwrap();
+        assert!(csrf.verify_raw(&req, &depot, &raw_token, &proof));
+        let (_, other_proof) = csrf.generate_token(&req, &depot);
+        assert!(!csrf.verify_raw(&req, &depot, &raw_token, &other_proof));
+        assert!(!csrf.verify_raw(&req, &depot, &raw_token[1..], &proof));
+        assert!(!csrf.verify_raw(&req, &depot, token.as_bytes(), &proof));
+    }
+
+    #[cfg(feature = "binding")]
+    #[test]
+    fn test_verify_raw_checks_binding() {
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let req = Request::new();
+        let depot = Depot::new();
+        let csrf = csrf.with_binding([5; 32], |depot| {
+            depot
+                .get::<String>("user")
+                .ok()
+                .map(|user| user.as_bytes().to_vec())
+        });
+        let mut alice = Depot::new();
+        alice.insert("user", "alice".to_owned());
+        let (token, proof) = csrf.generate_token(&req, &alice);
+        let raw_token = URL_SAFE_NO_PAD.decode(&token).unwrap();
+        assert!(csrf.verify_raw(&req, &alice, &raw_token, &proof));
+        assert!(!csrf.verify_raw(&req, &depot, &raw_token, &proof));
+        let csrf = csrf.with_min_token_len(token.len() + 1);
+        assert!(!csrf.verify_raw(&req, &alice, &raw_token, &proof));
+    }
+
+    #[tokio::test]
+    async fn test_store_save_error() {
+        struct FailingStore;
+        impl CsrfStore for FailingStore {
+            type Error = std::io::Error;
+            async fn load<C: CsrfCipher>(
+                &self,
+                _req: &mut Request,
+                _depot: &mut Depot,
+                _cipher: &C,
+            ) -> Option<(String, String)> {
+                None
+            }
+            async fn save(
+                &self,
+                _req: &mut Request,
+                _depot: &mut Depot,
+                _res: &mut Response,
+                _token: &str,
+                _proof: &str,
+            ) -> Result<(), Self::Error> {
+                Err(std::io::Error::other("store unavailable"))
+            }
+        }
+
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            FailingStore,
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index);
+        let res = TestClient::get("http://127.0.0.1:5801").send(router).await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_on_event() {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorder = events.clone();
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .on_event(move |event| recorder.lock().unwrap().push(event));
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let cookie = res.cookie("salvo.csrf").unwrap();
+        let forged = URL_SAFE_NO_PAD.encode([0; 32]);
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", forged, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                CsrfEvent::TokenIssued,
+                CsrfEvent::Rejected(CsrfRejectReason::InvalidToken)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_token_encodings() {
+        for encoding in [
+            TokenEncoding::UrlSafeNoPad,
+            TokenEncoding::Standard,
+            TokenEncoding::Hex,
+        ] {
+            let csrf = Csrf::new(
+                BcryptCipher::new(),
+                CookieStore::new(),
+                HeaderFinder::new("x-csrf-token"),
+            )
+            .with_encoding(encoding);
+            let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+            let service = Service::new(router);
+
+            let mut res = TestClient::get("http://127.0.0.1:5801")
+                .send(&service)
+                .await;
+            let csrf_token = res.take_string().await.unwrap();
+            assert!(encoding.decode(&csrf_token).is_some());
+            let cookie = res.cookie("salvo.csrf").unwrap();
+
+            let res = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", csrf_token, true)
+                .add_header("cookie", cookie.to_string(), true)
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_base64_engine() {
+        use base64::engine::general_purpose::STANDARD;
+
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_base64_engine(&STANDARD);
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        assert!(csrf_token.ends_with('='));
+        let data = STANDARD.decode(&csrf_token).unwrap();
+        assert_eq!(STANDARD.encode(data), csrf_token);
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", &csrf_token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", csrf_token.trim_end_matches('='), true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_validates_percent_encoded_token_in_query() {
+        let csrf = Csrf::new(BcryptCipher::new(), CookieStore::new(), QueryFinder::new())
+            .with_encoding(TokenEncoding::Standard);
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut csrf_token = String::new();
+        let mut cookie = None;
+        // Retry until the standard base64 token contains `+` or `/`.
+        while !csrf_token.contains(['+', '/']) {
+            let mut res = TestClient::get("http://127.0.0.1:5801")
+                .send(&service)
+                .await;
+            csrf_token = res.take_string().await.unwrap();
+            cookie = res.cookie("salvo.csrf").cloned();
+        }
+        let encoded = csrf_token
+            .replace('+', "%2B")
+            .replace('/', "%2F")
+            .replace('=', "%3D");
+
+        let res = TestClient::post(format!("http://127.0.0.1:5801?csrf-token={encoded}"))
+            .add_header("cookie", cookie.unwrap().to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_validates_token_in_path_param() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            PathParamFinder::new("csrf"),
+        );
+        let router = Router::new()
+            .hoop(csrf)
+            .get(get_index)
+            .push(Router::with_path("action/<csrf>/submit").post(post_index));
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post(format!("http://127.0.0.1:5801/action/{csrf_token}/submit"))
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let res = TestClient::post("http://127.0.0.1:5801/action/invalid/submit")
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_site_with_token() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_fetch_site_mode(FetchSiteMode::WithToken);
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        for (fetch_site, status_code) in [
+            (Some("same-origin"), StatusCode::OK),
+            (Some("same-site"), StatusCode::OK),
+            (Some("none"), StatusCode::OK),
+            (Some("cross-site"), StatusCode::FORBIDDEN),
+            (None, StatusCode::OK),
+        ] {
+            let mut req = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", &csrf_token, true)
+                .add_header("cookie", cookie.to_string(), true);
+            if let Some(fetch_site) = fetch_site {
+                req = req.add_header("sec-fetch-site", fetch_site, true);
+            }
+            let res = req.send(&service).await;
+            assert_eq!(res.status_code.unwrap(), status_code, "{fetch_site:?}");
+        }
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("cookie", cookie.to_string(), true)
+            .add_header("sec-fetch-site", "same-origin", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_site_standalone() {
+        for (missing, missing_reason) in [
+            (
+                MissingFetchSite::ValidateToken,
+                CsrfRejectReason::MissingProof,
+            ),
+            (MissingFetchSite::Reject, CsrfRejectReason::MissingFetchSite),
+        ] {
+            let rejects = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let events = rejects.clone();
+            let csrf = Csrf::new(
+                BcryptCipher::new(),
+                CookieStore::new(),
+                HeaderFinder::new("x-csrf-token"),
+            )
+            .with_fetch_site_mode(FetchSiteMode::Standalone)
+            .with_missing_fetch_site(missing)
+            .on_event(move |event| {
+                if let CsrfEvent::Rejected(reason) = event {
+                    events.lock().unwrap().push(reason);
+                }
+            });
+            let router = Router::new().hoop(csrf).post(post_index);
+            let service = Service::new(router);
+
+            for (fetch_site, status_code) in [
+                ("same-origin", StatusCode::OK),
+                ("same-site", StatusCode::OK),
+                ("none", StatusCode::OK),
+                ("cross-site", StatusCode::FORBIDDEN),
+            ] {
+                let res = TestClient::post("http://127.0.0.1:5801")
+                    .add_header("sec-fetch-site", fetch_site, true)
+                    .send(&service)
+                    .await;
+                assert_eq!(res.status_code.unwrap(), status_code, "{fetch_site}");
+            }
+            let res = TestClient::post("http://127.0.0.1:5801")
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+            assert_eq!(
+                *rejects.lock().unwrap(),
+                [CsrfRejectReason::CrossSite, missing_reason]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejects_token_in_wrong_encoding() {
+        let url_safe_csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(Router::new().hoop(url_safe_csrf).get(get_index))
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        for (auto_detect, status_code) in [(false, StatusCode::FORBIDDEN), (true, StatusCode::OK)] {
+            let csrf = Csrf::new(
+                BcryptCipher::new(),
+                CookieStore::new(),
+                HeaderFinder::new("x-csrf-token"),
+            )
+            .with_encoding(TokenEncoding::Hex)
+            .with_encoding_auto_detect(auto_detect);
+            let res = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", csrf_token.clone(), true)
+                .add_header("cookie", cookie.to_string(), true)
+                .send(Router::new().hoop(csrf).post(post_index))
+                .await;
+            assert_eq!(res.status_code.unwrap(), status_code);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejects_short_token() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", "aGVsbG8=", true)
+            .add_header(
+                "cookie",
+                cookie.to_string().split_once('.').unwrap().0,
+                true,
+            )
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_base64_token() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", "aGVsbG8", true)
+            .add_header(
+                "cookie",
+                cookie.to_string().split_once('.').unwrap().0,
+                true,
+            )
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_mismatched_token() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let csrf_token = res.take_string().await.unwrap();
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", csrf_token, true)
+            .add_header(
+                "cookie",
+                cookie.to_string().split_once('.').unwrap().0,
+                true,
+            )
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_rotate_csrf() {
+        #[handler]
+        async fn login(req: &mut Request, depot: &mut Depot, res: &mut Response) {
+            let token = depot.rotate_csrf(req, res).await.unwrap();
+            res.render(token);
+        }
+
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new()
+            .hoop(csrf)
+            .get(get_index)
+            .post(post_index)
+            .push(Router::with_path("login").post(login));
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let old_token = res.take_string().await.unwrap();
+        let old_cookie = res.cookie("salvo.csrf").unwrap().clone();
+
+        let mut res = TestClient::post("http://127.0.0.1:5801/login")
+            .add_header("x-csrf-token", old_token.clone(), true)
+            .add_header("cookie", old_cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let new_token = res.take_string().await.unwrap();
+        let new_cookie = res.cookie("salvo.csrf").unwrap().clone();
+        assert_ne!(new_token, old_token);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", old_token, true)
+            .add_header("cookie", new_cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", new_token, true)
+            .add_header("cookie", new_cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_clear_csrf() {
+        #[handler]
+        async fn logout(req: &mut Request, depot: &mut Depot, res: &mut Response) {
+            depot.clear_csrf(req, res).await.unwrap();
+            assert!(depot.csrf_token().is_none());
+        }
+
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new()
+            .hoop(csrf)
+            .get(get_index)
+            .post(post_index)
+            .push(Router::with_path("logout").post(logout));
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().clone();
+
+        let res = TestClient::post("http://127.0.0.1:5801/logout")
+            .add_header("x-csrf-token", token.clone(), true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let removal = res.cookie("salvo.csrf").unwrap();
+        assert_eq!(removal.value(), "");
+        assert_eq!(removal.max_age(), Some(cookie::time::Duration::ZERO));
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", token, true)
+            .add_header("cookie", removal.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_clear_host_prefixed_cookie() {
+        #[handler]
+        async fn logout(req: &mut Request, depot: &mut Depot, res: &mut Response) {
+            depot.clear_csrf(req, res).await.unwrap();
+        }
+
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            CookieStore::new().with_host_prefix(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new()
+            .hoop(csrf)
+            .get(get_index)
+            .push(Router::with_path("logout").post(logout));
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let token = res.take_string().await.unwrap();
+        let cookie = res.cookie("__Host-salvo.csrf").unwrap().clone();
+
+        let res = TestClient::post("http://127.0.0.1:5801/logout")
+            .add_header("x-csrf-token", token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let removal = res.cookie("__Host-salvo.csrf").unwrap();
+        assert_eq!(removal.value(), "");
+        assert_eq!(removal.secure(), cookie.secure());
+        assert_eq!(removal.secure(), Some(true));
+        assert_eq!(removal.path(), Some("/"));
+        assert_eq!(removal.same_site(), cookie.same_site());
+        assert_eq!(removal.http_only(), cookie.http_only());
+    }
+
+    #[tokio::test]
+    async fn test_rotate_csrf_without_middleware() {
+        let mut req = Request::default();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+        assert!(matches!(
+            depot.rotate_csrf(&mut req, &mut res).await,
+            Err(CsrfError::NotHooped)
+        ));
+    }
+
+    #[test]
+    fn test_debug() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .add_finder(FormFinder::new("csrf-token"));
+        assert_eq!(csrf.finder_count(), 2);
+        assert_eq!(
+            format!("{csrf:?}"),
+            "Csrf { cipher: salvo_csrf::bcrypt_cipher::BcryptCipher, \
+             store: salvo_csrf::cookie_store::CookieStore, \
+             finders: [\"salvo_csrf::finder::HeaderFinder\", \"salvo_csrf::finder::FormFinder\"], \
+             skipper: false, pre_check: false, encoding: UrlSafeNoPad, token_transform: false, auto_detect_encoding: false, \
+             websocket_upgrades: false, required_methods: [], bootstrap_grace: false, monitor_mode: false, \
+             rejection_status: 403, reject_log_level: Level(Warn), min_token_len: None, \
+             fetch_site_mode: Disabled, \
+             missing_fetch_site: ValidateToken, origin_mode: Disabled, require_secure_request: false, \
+             trust_forwarded_proto: false, binding: false, aad: false, event_hook: false, \
+             header_name: Some(\"x-csrf-token\"), response_header: None, vary: None, token_cookie: None }"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_default_finders() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            JsonFinder::new("unused"),
+        )
+        .with_finders(default_finders("x-csrf-token", "csrf-token"));
+        assert_eq!(csrf.finder_count(), 3);
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", token.clone(), true)
+            .add_header("cookie", cookie.clone(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("cookie", cookie.clone(), true)
+            .raw_form(format!("csrf-token={token}"))
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let res = TestClient::post(format!("http://127.0.0.1:5801?csrf-token={token}"))
+            .add_header("cookie", cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_skip_unchanged_save() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Mutex;
+
+        #[derive(Default)]
+        struct CountingStore {
+            pair: Mutex<Option<(String, String)>>,
+            loads: AtomicUsize,
+            saves: AtomicUsize,
+        }
+        impl CsrfStore for CountingStore {
+            type Error = std::convert::Infallible;
+            async fn load<C: CsrfCipher>(
+                &self,
+                _req: &mut Request,
+                _depot: &mut Depot,
+                _cipher: &C,
+            ) -> Option<(String, String)> {
+                self.loads.fetch_add(1, Ordering::SeqCst);
+                self.pair.lock().unwrap().clone()
+            }
+            async fn save(
+                &self,
+                _req: &mut Request,
+                _depot: &mut Depot,
+                _res: &mut Response,
+                token: &str,
+                proof: &str,
+            ) -> Result<(), Self::Error> {
+                self.saves.fetch_add(1, Ordering::SeqCst);
+                *self.pair.lock().unwrap() = Some((token.into(), proof.into()));
+                Ok(())
+            }
+        }
+        struct FixedCipher;
+        impl CsrfCipher for FixedCipher {
+            fn verify(&self, token: &str, proof: &str) -> bool {
+                token == "token" && proof == "proof"
+            }
+            fn generate(&self) -> (String, String) {
+                ("token".into(), "proof".into())
+            }
+        }
+        #[handler]
+        async fn rotate(req: &mut Request, depot: &mut Depot, res: &mut Response) {
+            depot.rotate_csrf(req, res).await.unwrap();
+        }
+
+        for (skip, saves) in [(true, 1), (false, 2)] {
+            let store = Arc::new(CountingStore::default());
+            let csrf = Csrf::new(
+                FixedCipher,
+                store.clone(),
+                HeaderFinder::new("x-csrf-token"),
+            )
+            .with_skip_unchanged_save(skip);
+            let router = Router::new()
+                .hoop(csrf.clone())
+                .get(get_index)
+                .push(Router::with_path("rotate").hoop(csrf).post(rotate));
+            let service = Service::new(router);
+
+            TestClient::get("http://127.0.0.1:5801")
+                .send(&service)
+                .await;
+            let res = TestClient::post("http://127.0.0.1:5801/rotate")
+                .add_header("x-csrf-token", "token", true)
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+            assert_eq!(store.loads.load(Ordering::SeqCst), 2);
+            assert_eq!(store.saves.load(Ordering::SeqCst), saves);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validates_padded_and_repeated_header() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", format!("  {token}  "), true)
+            .add_header("cookie", cookie.clone(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", "stale", false)
+            .add_header("x-csrf-token", token, false)
+            .add_header("cookie", cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_trusted_origins() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_trusted_origins(vec!["https://app.example.com".into()]);
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
+
+        for (origin, status) in [
+            (Some("http://127.0.0.1:5801"), StatusCode::OK),
+            (Some("https://app.example.com/"), StatusCode::OK),
+            (Some("https://evil.example.com"), StatusCode::FORBIDDEN),
+            (Some("null"), StatusCode::FORBIDDEN),
+            (None, StatusCode::OK),
+        ] {
+            let mut req = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", token.clone(), true)
+                .add_header("cookie", cookie.clone(), true);
+            if let Some(origin) = origin {
+                req = req.add_header("origin", origin, true);
+            }
+            let res = req.send(&service).await;
+            assert_eq!(res.status_code.unwrap(), status, "{origin:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trusted_origins_standalone() {
+        let reasons = Arc::new(std::sync::Mutex::new(vec![]));
+        let events = reasons.clone();
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_trusted_origins(vec!["https://app.example.com".into()])
+        .with_origin_mode(OriginMode::Standalone)
+        .with_missing_origin(MissingOrigin::Reject)
+        .on_event(move |event| {
+            if let CsrfEvent::Rejected(reason) = event {
+                events.lock().unwrap().push(reason);
+            }
+        });
+        let router = Router::new().hoop(csrf).post(post_index);
+        let service = Service::new(router);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("origin", "https://app.example.com", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        for origin in [Some("https://evil.example.com"), None] {
+            let mut req = TestClient::post("http://127.0.0.1:5801");
+            if let Some(origin) = origin {
+                req = req.add_header("origin", origin, true);
+            }
+            let res = req.send(&service).await;
+            assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+        }
+        assert_eq!(
+            *reasons.lock().unwrap(),
+            [
+                CsrfRejectReason::UntrustedOrigin,
+                CsrfRejectReason::MissingOrigin
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_depot_key() {
+        #[handler]
+        async fn get_tokens(depot: &mut Depot) -> String {
+            format!(
+                "{} {}",
+                depot.csrf_token_with_key("api.csrf").unwrap(),
+                depot.csrf_token_with_key("form.csrf").unwrap()
+            )
+        }
+
+        let api = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new().name("api.csrf"),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_depot_key("api.csrf");
+        let form = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new().name("form.csrf"),
+            FormFinder::new("csrf-token"),
+        )
+        .with_depot_key("form.csrf");
+        let router = Router::new()
+            .hoop(api)
+            .hoop(form)
+            .get(get_tokens)
+            .post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let tokens = res.take_string().await.unwrap();
+        let (api_token, form_token) = tokens.split_once(' ').unwrap();
+        assert_ne!(api_token, form_token);
+        let cookies = format!(
+            "{}; {}",
+            res.cookie("api.csrf").unwrap().stripped(),
+            res.cookie("form.csrf").unwrap().stripped()
+        );
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", api_token, true)
+            .add_header("cookie", cookies, true)
+            .raw_form(format!("csrf-token={form_token}"))
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_oversized_json_body() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            JsonFinder::new("csrf-token").max_body_size(1024),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let body = format!(
+            r#"{{"csrf-token":"{csrf_token}","padding":"{}"}}"#,
+            "a".repeat(1024 * 1024)
+        );
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("cookie", cookie.to_string(), true)
+            .raw_json(body)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("cookie", cookie.to_string(), true)
+            .raw_json(format!(r#"{{"csrf-token":"{csrf_token}"}}"#))
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_require_on_get() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new()
+            .push(Router::with_path("token").hoop(csrf.clone()).get(get_index))
+            .push(
+                Router::with_path("download")
+                    .hoop(csrf.require_on([Method::GET]))
+                    .get(post_index),
+            );
+        let service = Service::new(router);
+
+        let res = TestClient::get("http://127.0.0.1:5801/download")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/token")
+            .send(&service)
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::get("http://127.0.0.1:5801/download")
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
 
-    #[handler]
-    async fn get_index(depot: &mut Depot) -> String {
-        depot.csrf_token().unwrap().to_owned()
+        let res = TestClient::get("http://127.0.0.1:5801/download")
+            .add_header("x-csrf-token", &csrf_token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
     }
-    #[handler]
-    async fn post_index() -> &'static str {
-        "POST"
+
+    #[tokio::test]
+    async fn test_require_on_bootstrap_grace() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .require_on([Method::GET])
+        .with_bootstrap_grace(true);
+        let router = Router::new().hoop(csrf).get(get_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", &csrf_token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
     }
 
     #[tokio::test]
-    async fn test_exposes_csrf_request_extensions() {
+    async fn test_monitor_mode() {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorder = events.clone();
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .monitor_mode(true)
+        .on_event(move |event| recorder.lock().unwrap().push(event));
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", "forged", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert_eq!(res.take_string().await.unwrap(), "POST");
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                CsrfEvent::Rejected(CsrfRejectReason::MissingProof),
+                CsrfEvent::TokenIssued
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_vary() {
         let csrf = Csrf::new(
             BcryptCipher::new(),
             CookieStore::new(),
             HeaderFinder::new("x-csrf-token"),
         );
-        let router = Router::new().hoop(csrf).get(get_index);
-        let res = TestClient::get("http://127.0.0.1:5801").send(router).await;
+        let router = Router::new()
+            .push(Router::with_path("plain").hoop(csrf.clone()).get(get_index))
+            .push(
+                Router::with_path("header")
+                    .hoop(
+                        csrf.clone()
+                            .with_response_header(HeaderName::from_static("x-csrf-token")),
+                    )
+                    .get(get_index),
+            )
+            .push(
+                Router::with_path("forced")
+                    .hoop(csrf.with_vary(true))
+                    .get(get_index),
+            );
+        let service = Service::new(router);
+
+        let res = TestClient::get("http://127.0.0.1:5801/plain")
+            .send(&service)
+            .await;
+        assert!(res.headers().get(VARY).is_none());
+
+        for path in ["header", "forced"] {
+            let res = TestClient::get(format!("http://127.0.0.1:5801/{path}"))
+                .send(&service)
+                .await;
+            let vary: Vec<_> = res.headers().get_all(VARY).iter().collect();
+            assert_eq!(vary, ["cookie", "x-csrf-token"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validates_repeated_query_param() {
+        let csrf = Csrf::new(BcryptCipher::new(), CookieStore::new(), QueryFinder::new());
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post(format!(
+            "http://127.0.0.1:5801?csrf-token=&csrf-token={csrf_token}"
+        ))
+        .add_header("cookie", cookie.to_string(), true)
+        .send(&service)
+        .await;
         assert_eq!(res.status_code.unwrap(), StatusCode::OK);
     }
 
     #[tokio::test]
-    async fn test_adds_csrf_cookie_sets_request_token() {
+    async fn test_with_defaults() {
+        let csrf = Csrf::with_defaults(BcryptCipher::new(), CookieStore::new());
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", csrf_token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_pre_check() {
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_pre_check(|req, _depot, res| {
+            match req.header::<String>("x-client").as_deref() {
+                Some("trusted") => PreCheck::Skip,
+                Some("bot") => {
+                    res.status_code(StatusCode::TOO_MANY_REQUESTS);
+                    res.render("go away");
+                    PreCheck::Reject
+                }
+                Some("blocked") => PreCheck::Reject,
+                _ => PreCheck::Protect,
+            }
+        });
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-client", "trusted", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", csrf_token, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("x-client", "bot", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.take_string().await.unwrap(), "go away");
+        assert!(res.cookie("salvo.csrf").is_none());
+
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("x-client", "blocked", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_allow_null_origin() {
+        for (allow, null_status) in [(false, StatusCode::FORBIDDEN), (true, StatusCode::OK)] {
+            let csrf = Csrf::new(
+                BcryptCipher::new(),
+                CookieStore::new(),
+                HeaderFinder::new("x-csrf-token"),
+            )
+            .with_trusted_origins(vec!["https://app.example.com".into()])
+            .allow_null_origin(allow);
+            let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+            let service = Service::new(router);
+
+            let mut res = TestClient::get("http://127.0.0.1:5801")
+                .send(&service)
+                .await;
+            let token = res.take_string().await.unwrap();
+            let cookie = res.cookie("salvo.csrf").unwrap().to_string();
+
+            for (origin, token, status) in [
+                ("null", token.as_str(), null_status),
+                ("null", "", StatusCode::FORBIDDEN),
+                (
+                    "https://evil.example.com",
+                    token.as_str(),
+                    StatusCode::FORBIDDEN,
+                ),
+            ] {
+                let res = TestClient::post("http://127.0.0.1:5801")
+                    .add_header("origin", origin, true)
+                    .add_header("x-csrf-token", token, true)
+                    .add_header("cookie", cookie.clone(), true)
+                    .send(&service)
+                    .await;
+                assert_eq!(res.status_code.unwrap(), status, "{allow} {origin}");
+            }
+        }
+
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_origin_mode(OriginMode::Standalone)
+        .allow_null_origin(true);
+        let router = Router::new().hoop(csrf).post(post_index);
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("origin", "null", true)
+            .send(&Service::new(router))
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_reject_log_level() {
         let csrf = Csrf::new(
             BcryptCipher::new(),
             CookieStore::new(),
             HeaderFinder::new("x-csrf-token"),
+        )
+        .with_reject_log_level(Level::ERROR);
+        let router = Router::new()
+            .hoop(csrf)
+            .get(get_index)
+            .push(Router::with_path("form").post(post_index));
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
+        let forged = "Zm9yZ2VkIHRva2VuIGZvciBsb2dzLCBsb25nIGVub3VnaCB0byB2ZXJpZnk";
+        let res = TestClient::post("http://127.0.0.1:5801/form")
+            .add_header("x-csrf-token", forged, true)
+            .add_header("cookie", cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        logs_assert(|lines: &[&str]| {
+            let rejected: Vec<_> = lines
+                .iter()
+                .filter(|line| line.contains("csrf protected request rejected"))
+                .collect();
+            match rejected.as_slice() {
+                [line]
+                    if line.contains("ERROR")
+                        && line.contains("reason=InvalidToken")
+                        && line.contains("method=POST")
+                        && line.contains("path=/form") =>
+                {
+                    Ok(())
+                }
+                _ => Err(format!("unexpected rejection logs: {rejected:?}")),
+            }
+        });
+        assert!(logs_contain("Zm9yZ2..."));
+        assert!(!logs_contain(forged));
+        assert!(!logs_contain(&token));
+    }
+
+    #[test]
+    fn test_default_skipper_methods() {
+        let depot = Depot::new();
+        for (method, skipped) in [
+            (Method::GET, true),
+            (Method::HEAD, true),
+            (Method::OPTIONS, true),
+            (Method::TRACE, true),
+            (Method::POST, false),
+            (Method::PUT, false),
+            (Method::PATCH, false),
+            (Method::DELETE, false),
+            (Method::CONNECT, false),
+            (Method::from_bytes(b"PROPFIND").unwrap(), false),
+        ] {
+            let mut req = TestClient::get("http://127.0.0.1:5801").build();
+            *req.method_mut() = method.clone();
+            assert_eq!(default_skipper(&mut req, &depot), skipped, "{method}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_protect_router() {
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
         );
-        let router = Router::new().hoop(csrf).get(get_index);
+        let router = csrf.protect(
+            Router::new().push(Router::with_path("items").post(post_index)),
+            "csrf-token",
+        );
+        let service = Service::new(router);
 
-        let mut res = TestClient::get("http://127.0.0.1:5801").send(router).await;
+        let mut res = TestClient::get("http://127.0.0.1:5801/csrf-token")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let token = res.take_string().await.unwrap();
+        assert!(!token.is_empty());
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
 
+        let res = TestClient::post("http://127.0.0.1:5801/items")
+            .add_header("cookie", &cookie, true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+
+        let res = TestClient::post("http://127.0.0.1:5801/items")
+            .add_header("x-csrf-token", &token, true)
+            .add_header("cookie", &cookie, true)
+            .send(&service)
+            .await;
         assert_eq!(res.status_code.unwrap(), StatusCode::OK);
-        assert_ne!(res.take_string().await.unwrap(), "");
-        assert_ne!(res.cookie("salvo.csrf"), None);
     }
 
     #[tokio::test]
-    async fn test_validates_token_in_header() {
+    async fn test_signed_cookie_tampering() {
+        use salvo_core::http::cookie::Cookie;
+
+        let store = CookieStore::from_key(&[7; 64]);
+        let cipher = BcryptCipher::new().cost(4);
+        let (token, proof) = cipher.generate();
+        let mut res = Response::new();
+        store.set_value(&Request::new(), &mut res, format!("{token}.{proof}"));
+        let signed = res.cookie("salvo.csrf").unwrap().value().to_owned();
+        assert_ne!(signed, format!("{token}.{proof}"));
+
+        let load = |value: String| {
+            let store = store.clone();
+            let cipher = cipher.clone();
+            async move {
+                let mut req = TestClient::post("http://127.0.0.1:5801")
+                    .add_header("cookie", Cookie::new("salvo.csrf", value).to_string(), true)
+                    .build();
+                store.load(&mut req, &mut Depot::new(), &cipher).await
+            }
+        };
+        assert_eq!(
+            load(signed.clone()).await,
+            Some((token.clone(), proof.clone()))
+        );
+
+        let mut tampered = vec![
+            signed[..signed.len() - 1].to_owned(),
+            signed[1..].to_owned(),
+        ];
+        for index in [0, 10, 43, 44, signed.len() / 2, signed.len() - 1] {
+            let mut bytes = signed.clone().into_bytes();
+            bytes[index] ^= 0x01;
+            tampered.push(String::from_utf8(bytes).unwrap());
+        }
+        tampered.push(format!("{token}.{proof}"));
+        for value in tampered {
+            assert_eq!(load(value.clone()).await, None, "{value}");
+        }
+        assert_eq!(
+            CookieStore::from_key(&[8; 64])
+                .load(
+                    &mut TestClient::post("http://127.0.0.1:5801")
+                        .add_header(
+                            "cookie",
+                            Cookie::new("salvo.csrf", signed).to_string(),
+                            true
+                        )
+                        .build(),
+                    &mut Depot::new(),
+                    &cipher
+                )
+                .await,
+            None
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "key must be at least 64 bytes")]
+    fn test_cookie_store_from_short_key() {
+        CookieStore::from_key(&[7; 32]);
+    }
+
+    #[test]
+    fn test_try_with_namespace_shared_store() {
+        let store = Arc::new(CookieStore::new());
+        let shared = store.clone();
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            store,
+            HeaderFinder::new("x-csrf-token"),
+        );
+        assert!(matches!(
+            csrf.try_with_namespace("admin"),
+            Err(NamespaceError::SharedStore)
+        ));
+        drop(shared);
+    }
+
+    #[tokio::test]
+    async fn test_namespace() {
+        #[handler]
+        async fn get_tokens(depot: &mut Depot) -> String {
+            format!(
+                "{} {}",
+                depot.csrf_token_with_key("admin.salvo.csrf.token").unwrap(),
+                depot.csrf_token_with_key("form.salvo.csrf.token").unwrap()
+            )
+        }
+
+        let csrf = |finder: HeaderFinder, prefix: &str| {
+            Csrf::new(BcryptCipher::new().cost(4), CookieStore::new(), finder)
+                .with_readable_token_cookie("XSRF-TOKEN")
+                .with_namespace(prefix)
+        };
+        let router = Router::new()
+            .hoop(csrf(HeaderFinder::new("x-admin-token"), "admin"))
+            .hoop(csrf(HeaderFinder::new("x-form-token"), "form"))
+            .get(get_tokens)
+            .post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let tokens = res.take_string().await.unwrap();
+        let (admin_token, form_token) = tokens.split_once(' ').unwrap();
+        assert_ne!(admin_token, form_token);
+        for prefix in ["admin", "form"] {
+            assert!(res.cookie(format!("{prefix}.XSRF-TOKEN")).is_some());
+        }
+        assert!(res.cookie("salvo.csrf").is_none());
+        let cookies = format!(
+            "{}; {}",
+            res.cookie("admin.salvo.csrf").unwrap().stripped(),
+            res.cookie("form.salvo.csrf").unwrap().stripped()
+        );
+
+        for (admin, form, status) in [
+            (admin_token, form_token, StatusCode::OK),
+            (form_token, admin_token, StatusCode::FORBIDDEN),
+            (admin_token, admin_token, StatusCode::FORBIDDEN),
+            (form_token, form_token, StatusCode::FORBIDDEN),
+        ] {
+            let res = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-admin-token", admin, true)
+                .add_header("x-form-token", form, true)
+                .add_header("cookie", &cookies, true)
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), status);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_min_token_len() {
+        use std::sync::atomic::AtomicUsize;
+
+        #[derive(Clone)]
+        struct CountingCipher {
+            inner: BcryptCipher,
+            verifies: Arc<AtomicUsize>,
+        }
+        impl CsrfCipher for CountingCipher {
+            fn verify(&self, token: &str, proof: &str) -> bool {
+                self.verifies.fetch_add(1, Ordering::SeqCst);
+                self.inner.verify(token, proof)
+            }
+            fn min_token_len(&self) -> usize {
+                self.inner.min_token_len()
+            }
+            fn generate(&self) -> (String, String) {
+                self.inner.generate()
+            }
+        }
+
+        let cipher = CountingCipher {
+            inner: BcryptCipher::new().cost(4),
+            verifies: Arc::new(AtomicUsize::new(0)),
+        };
+        assert_eq!(cipher.min_token_len(), 43);
+        for (csrf, short_verified) in [
+            (
+                Csrf::new(
+                    cipher.clone(),
+                    CookieStore::new(),
+                    HeaderFinder::new("x-csrf-token"),
+                ),
+                false,
+            ),
+            (
+                Csrf::new(
+                    cipher.clone(),
+                    CookieStore::new(),
+                    HeaderFinder::new("x-csrf-token"),
+                )
+                .with_min_token_len(0),
+                true,
+            ),
+        ] {
+            let (_, proof) = csrf.generate_token(&Request::new(), &Depot::new());
+            let pairs = [("".to_owned(), proof)];
+            let mut req = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", "aGVsbG8", true)
+                .build();
+            cipher.verifies.store(0, Ordering::SeqCst);
+            let reason = csrf
+                .validate(&mut req, &Depot::new(), &pairs, &[])
+                .await
+                .unwrap_err();
+            if short_verified {
+                assert_eq!(reason, CsrfRejectReason::InvalidToken);
+                assert_eq!(cipher.verifies.load(Ordering::SeqCst), 1);
+            } else {
+                assert_eq!(reason, CsrfRejectReason::DecodeFailed);
+                assert_eq!(cipher.verifies.load(Ordering::SeqCst), 0);
+            }
+        }
+
+        let csrf = Csrf::new(
+            cipher.clone(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let (token, proof) = csrf.generate_token(&Request::new(), &Depot::new());
+        let mut req = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", &token, true)
+            .build();
+        cipher.verifies.store(0, Ordering::SeqCst);
+        assert!(csrf
+            .validate(&mut req, &Depot::new(), &[(token, proof)], &[])
+            .await
+            .is_ok());
+        assert_eq!(cipher.verifies.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rotated_header() {
+        #[handler]
+        async fn rotate(req: &mut Request, depot: &mut Depot, res: &mut Response) -> String {
+            depot.rotate_csrf(req, res).await.unwrap()
+        }
+
         let csrf = Csrf::new(
-            BcryptCipher::new(),
+            BcryptCipher::new().cost(4),
             CookieStore::new(),
             HeaderFinder::new("x-csrf-token"),
-        );
-        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        )
+        .with_rotated_header(HeaderName::from_static("x-csrf-rotated"));
+        let router = Router::new()
+            .hoop(csrf)
+            .get(get_index)
+            .push(Router::with_path("rotate").post(rotate));
         let service = Service::new(router);
 
         let mut res = TestClient::get("http://127.0.0.1:5801")
             .send(&service)
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert_eq!(res.headers().get("x-csrf-rotated").unwrap(), "true");
+        let token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
 
-        let csrf_token = res.take_string().await.unwrap();
-        let cookie = res.cookie("salvo.csrf").unwrap();
-
-        let res = TestClient::post("http://127.0.0.1:5801")
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("cookie", &cookie, true)
             .send(&service)
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+        assert!(res.headers().get("x-csrf-rotated").is_none());
+        assert_eq!(res.take_string().await.unwrap(), token);
 
-        let mut res = TestClient::post("http://127.0.0.1:5801")
-            .add_header("x-csrf-token", csrf_token, true)
-            .add_header("cookie", cookie.to_string(), true)
+        let mut res = TestClient::post("http://127.0.0.1:5801/rotate")
+            .add_header("x-csrf-token", &token, true)
+            .add_header("cookie", &cookie, true)
             .send(&service)
             .await;
         assert_eq!(res.status_code.unwrap(), StatusCode::OK);
-        assert_eq!(res.take_string().await.unwrap(), "POST");
+        assert_eq!(res.headers().get("x-csrf-rotated").unwrap(), "true");
+        assert_ne!(res.take_string().await.unwrap(), token);
     }
 
     #[tokio::test]
-    async fn test_validates_token_in_custom_header() {
+    async fn test_protect_function() {
         let csrf = Csrf::new(
-            BcryptCipher::new(),
+            BcryptCipher::new().cost(4),
             CookieStore::new(),
-            HeaderFinder::new("x-mycsrf-header"),
+            HeaderFinder::new("x-csrf-token"),
         );
-        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
-        let service = Service::new(router);
 
-        let mut res = TestClient::get("http://127.0.0.1:5801")
-            .send(&service)
-            .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let mut req = TestClient::get("http://127.0.0.1:5801").build();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+        let outcome = protect(&mut req, &mut depot, &mut res, &csrf).await;
+        let CsrfOutcome::Allowed(token) = outcome else {
+            panic!("unexpected outcome: {outcome:?}");
+        };
+        assert_eq!(depot.csrf_token(), Some(token.as_str()));
+        assert!(res.status_code.is_none());
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
 
-        let csrf_token = res.take_string().await.unwrap();
-        let cookie = res.cookie("salvo.csrf").unwrap();
+        for (header, expected) in [
+            (Some(token.as_str()), None),
+            (None, Some(CsrfRejectReason::MissingToken)),
+            (
+                Some("Zm9yZ2VkIHRva2VuIHdpdGggZW5vdWdoIGNoYXJhY3RlcnMgdG8gdmVyaWZ5"),
+                Some(CsrfRejectReason::InvalidToken),
+            ),
+        ] {
+            let mut req =
+                TestClient::post("http://127.0.0.1:5801").add_header("cookie", &cookie, true);
+            if let Some(header) = header {
+                req = req.add_header("x-csrf-token", header, true);
+            }
+            let mut req = req.build();
+            let mut depot = Depot::new();
+            let mut res = Response::new();
+            let outcome = protect(&mut req, &mut depot, &mut res, &csrf).await;
+            match (outcome, expected) {
+                (CsrfOutcome::Allowed(allowed), None) => assert_eq!(allowed, token),
+                (CsrfOutcome::Rejected(reason), Some(expected)) => {
+                    assert_eq!(reason, expected);
+                    assert!(depot.csrf_token().is_none());
+                }
+                (outcome, _) => panic!("unexpected outcome: {outcome:?}"),
+            }
+            assert!(res.status_code.is_none());
+        }
+    }
 
-        let res = TestClient::post("http://127.0.0.1:5801")
-            .send(&service)
-            .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_same_site_none_requires_secure() {
+        use cookie::SameSite;
 
-        let mut res = TestClient::post("http://127.0.0.1:5801")
-            .add_header("x-mycsrf-header", csrf_token, true)
-            .add_header("cookie", cookie.to_string(), true)
-            .send(&service)
-            .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
-        assert_eq!(res.take_string().await.unwrap(), "POST");
+        let secure = |store: &CookieStore| {
+            let mut res = Response::new();
+            store.set_value(&Request::new(), &mut res, "token.proof".into());
+            res.cookie("salvo.csrf").unwrap().secure()
+        };
+        assert_ne!(secure(&CookieStore::new()), Some(true));
+        assert_eq!(secure(&CookieStore::new().secure(true)), Some(true));
+        assert!(!logs_contain("marked `Secure` anyway"));
+
+        for store in [
+            CookieStore::new().secure(false).same_site(SameSite::None),
+            CookieStore::new().same_site(SameSite::None).secure(false),
+        ] {
+            assert_eq!(secure(&store), Some(true));
+        }
+        logs_assert(|lines: &[&str]| {
+            match lines
+                .iter()
+                .filter(|line| line.contains("WARN") && line.contains("marked `Secure` anyway"))
+                .count()
+            {
+                2 => Ok(()),
+                n => Err(format!("expected two warnings, found {n}")),
+            }
+        });
     }
 
     #[tokio::test]
-    async fn test_validates_token_in_query() {
+    async fn test_extension_finder() {
+        #[derive(Clone)]
+        struct ParsedToken(String);
+        impl AsRef<str> for ParsedToken {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+        #[handler]
+        async fn parse_token(req: &mut Request) {
+            let token = req
+                .header::<String>("x-custom-payload")
+                .and_then(|payload| payload.strip_prefix("token:").map(ToOwned::to_owned));
+            if let Some(token) = token {
+                req.extensions_mut().insert(ParsedToken(token));
+            }
+        }
+
         let csrf = Csrf::new(
-            BcryptCipher::new(),
+            BcryptCipher::new().cost(4),
             CookieStore::new(),
-            HeaderFinder::new("csrf-token"),
+            ExtensionFinder::<ParsedToken>::new(),
         );
-        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let router = Router::new()
+            .hoop(parse_token)
+            .hoop(csrf)
+            .get(get_index)
+            .post(post_index);
         let service = Service::new(router);
 
         let mut res = TestClient::get("http://127.0.0.1:5801")
             .send(&service)
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
 
-        let csrf_token = res.take_string().await.unwrap();
-        let cookie = res.cookie("salvo.csrf").unwrap();
+        for (payload, status) in [
+            (format!("token:{token}"), StatusCode::OK),
+            (token.clone(), StatusCode::FORBIDDEN),
+        ] {
+            let res = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-custom-payload", payload, true)
+                .add_header("cookie", &cookie, true)
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), status);
+        }
+    }
 
-        let res = TestClient::post("http://127.0.0.1:5801")
-            .send(&service)
-            .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    #[cfg(feature = "aes-gcm-cipher")]
+    #[tokio::test]
+    async fn test_aad() {
+        let csrf = Csrf::new(
+            AesGcmCipher::new([0u8; 32]),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_aad(|req, _depot| req.header::<String>("x-form").map(String::into_bytes));
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
 
-        let mut res = TestClient::post("http://127.0.0.1:5801?a=1&b=2")
-            .add_header("csrf-token", csrf_token, true)
-            .add_header("cookie", cookie.to_string(), true)
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("x-form", "X", true)
             .send(&service)
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
-        assert_eq!(res.take_string().await.unwrap(), "POST");
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
+
+        for (form, status_code) in [
+            (Some("X"), StatusCode::OK),
+            (Some("Y"), StatusCode::FORBIDDEN),
+            (None, StatusCode::FORBIDDEN),
+        ] {
+            let mut req = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", &csrf_token, true)
+                .add_header("cookie", &cookie, true);
+            if let Some(form) = form {
+                req = req.add_header("x-form", form, true);
+            }
+            let res = req.send(&service).await;
+            assert_eq!(res.status_code.unwrap(), status_code);
+        }
     }
-    #[cfg(feature = "hmac-cipher")]
+
+    #[test]
+    #[should_panic(expected = "csrf cipher does not support associated data")]
+    fn test_aad_unsupported_cipher() {
+        let _ = Csrf::new(
+            BcryptCipher::new(),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_aad(|_req, _depot| None);
+    }
+
     #[tokio::test]
-    async fn test_validates_token_in_alternate_query() {
+    async fn test_issue_token_on_unusual_method() {
+        use salvo_core::routing::filters::MethodFilter;
+        use salvo_core::test::RequestBuilder;
+
+        struct ReportForm(Csrf<BcryptCipher, CookieStore>);
+        #[async_trait]
+        impl Handler for ReportForm {
+            async fn handle(
+                &self,
+                req: &mut Request,
+                depot: &mut Depot,
+                res: &mut Response,
+                _ctrl: &mut FlowCtrl,
+            ) {
+                let token = self.0.issue_token(req, depot, res).await.unwrap();
+                assert_eq!(depot.csrf_token(), Some(token.as_str()));
+                res.render(token);
+            }
+        }
+
+        let report = Method::from_bytes(b"REPORT").unwrap();
         let csrf = Csrf::new(
-            HmacCipher::new(*b"01234567012345670123456701234567"),
+            BcryptCipher::new().cost(4),
             CookieStore::new(),
-            HeaderFinder::new("my-csrf-token"),
+            HeaderFinder::new("x-csrf-token"),
         );
-        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let router = Router::new()
+            .push(
+                Router::with_path("form")
+                    .filter(MethodFilter::new(report.clone()))
+                    .goal(ReportForm(csrf.clone())),
+            )
+            .push(
+                Router::with_path("protected")
+                    .hoop(csrf)
+                    .push(
+                        Router::new()
+                            .filter(MethodFilter::new(report.clone()))
+                            .goal(get_index),
+                    )
+                    .post(post_index),
+            );
         let service = Service::new(router);
 
-        let mut res = TestClient::get("http://127.0.0.1:5801")
+        let res = RequestBuilder::new("http://127.0.0.1:5801/protected", report.clone())
             .send(&service)
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
-
-        let csrf_token = res.take_string().await.unwrap();
-        let cookie = res.cookie("salvo.csrf").unwrap();
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
 
-        let res = TestClient::post("http://127.0.0.1:5801")
+        let mut res = RequestBuilder::new("http://127.0.0.1:5801/form", report)
             .send(&service)
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
 
-        let mut res = TestClient::post("http://127.0.0.1:5801?a=1&b=2")
-            .add_header("my-csrf-token", csrf_token, true)
-            .add_header("cookie", cookie.to_string(), true)
+        let res = TestClient::post("http://127.0.0.1:5801/protected")
+            .add_header("x-csrf-token", &csrf_token, true)
+            .add_header("cookie", &cookie, true)
             .send(&service)
             .await;
         assert_eq!(res.status_code.unwrap(), StatusCode::OK);
-        assert_eq!(res.take_string().await.unwrap(), "POST");
     }
 
-    #[cfg(feature = "hmac-cipher")]
     #[tokio::test]
-    async fn test_validates_token_in_form() {
+    async fn test_token_transform() {
         let csrf = Csrf::new(
-            HmacCipher::new(*b"01234567012345670123456701234567"),
+            BcryptCipher::new().cost(4),
             CookieStore::new(),
-            FormFinder::new("csrf-token"),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_token_transform(
+            |token| format!("v1.{token}"),
+            |token| token.strip_prefix("v1.").map(ToOwned::to_owned),
         );
         let router = Router::new().hoop(csrf).get(get_index).post(post_index);
         let service = Service::new(router);
@@ -497,30 +5425,102 @@ mod tests {
         let mut res = TestClient::get("http://127.0.0.1:5801")
             .send(&service)
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
-
         let csrf_token = res.take_string().await.unwrap();
-        let cookie = res.cookie("salvo.csrf").unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
+        let raw_token = csrf_token.strip_prefix("v1.").unwrap();
+        assert!(cookie.contains(raw_token));
 
-        let res = TestClient::post("http://127.0.0.1:5801")
-            .send(&service)
-            .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+        for (token, status_code) in [
+            (csrf_token.as_str(), StatusCode::OK),
+            (raw_token, StatusCode::FORBIDDEN),
+            (&*format!("v2.{raw_token}"), StatusCode::FORBIDDEN),
+        ] {
+            let res = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", token, true)
+                .add_header("cookie", &cookie, true)
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), status_code);
+        }
+    }
 
-        let mut res = TestClient::post("http://127.0.0.1:5801")
-            .add_header("cookie", cookie.to_string(), true)
-            .form(&[("a", "1"), ("csrf-token", &*csrf_token), ("b", "2")])
-            .send(&service)
-            .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
-        assert_eq!(res.take_string().await.unwrap(), "POST");
+    #[tokio::test]
+    async fn test_security_headers() {
+        let coop = HeaderName::from_static("cross-origin-opener-policy");
+        let frame_options = HeaderName::from_static("x-frame-options");
+        let router = |csrf: Csrf<BcryptCipher, CookieStore>| {
+            Service::new(Router::new().hoop(csrf).get(get_index))
+        };
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let enabled = router(csrf.clone().with_security_headers([
+            (coop.clone(), HeaderValue::from_static("same-origin")),
+            (frame_options.clone(), HeaderValue::from_static("DENY")),
+        ]));
+        let disabled = router(csrf);
+
+        for (service, headers, expected) in [
+            (&enabled, vec![("sec-fetch-mode", "navigate")], true),
+            (&enabled, vec![("accept", "text/html,*/*;q=0.8")], true),
+            (&enabled, vec![("sec-fetch-mode", "cors")], false),
+            (
+                &enabled,
+                vec![("sec-fetch-mode", "cors"), ("accept", "text/html")],
+                false,
+            ),
+            (&enabled, vec![("accept", "application/json")], false),
+            (&disabled, vec![("sec-fetch-mode", "navigate")], false),
+        ] {
+            let mut req = TestClient::get("http://127.0.0.1:5801");
+            for (name, value) in &headers {
+                req = req.add_header(*name, *value, true);
+            }
+            let res = req.send(service).await;
+            assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+            assert_eq!(res.headers().get(&coop).is_some(), expected, "{headers:?}");
+            assert_eq!(
+                res.headers().get(&frame_options).is_some(),
+                expected,
+                "{headers:?}"
+            );
+        }
     }
+
+    #[cfg(feature = "hmac-cipher")]
     #[tokio::test]
-    async fn test_validates_token_in_alternate_form() {
+    async fn test_malformed_token_reason() {
+        let cipher = HmacCipher::new([0u8; 32]);
         let csrf = Csrf::new(
-            BcryptCipher::new(),
+            cipher.clone(),
             CookieStore::new(),
-            FormFinder::new("my-csrf-token"),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let (token, proof) = cipher.generate();
+        let (other_token, _) = cipher.generate();
+        let short_token = URL_SAFE_NO_PAD.encode([0u8; 16]);
+        for (token, reason) in [
+            (&token, None),
+            (&other_token, Some(CsrfRejectReason::InvalidToken)),
+            (&short_token, Some(CsrfRejectReason::MalformedToken)),
+        ] {
+            let mut req = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", token, true)
+                .build();
+            let pairs = [(String::new(), proof.clone())];
+            let result = csrf.validate(&mut req, &Depot::new(), &pairs, &[]).await;
+            assert_eq!(result.err(), reason);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_form_finder_query_fallback() {
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            CookieStore::new(),
+            FormFinder::new("csrf-token").with_query_fallback("csrf-token"),
         );
         let router = Router::new().hoop(csrf).get(get_index).post(post_index);
         let service = Service::new(router);
@@ -528,128 +5528,272 @@ mod tests {
         let mut res = TestClient::get("http://127.0.0.1:5801")
             .send(&service)
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
-
         let csrf_token = res.take_string().await.unwrap();
-        let cookie = res.cookie("salvo.csrf").unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
 
-        let res = TestClient::post("http://127.0.0.1:5801")
-            .send(&service)
-            .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
-        let mut res = TestClient::post("http://127.0.0.1:5801")
-            .add_header("cookie", cookie.to_string(), true)
-            .form(&[("a", "1"), ("my-csrf-token", &*csrf_token), ("b", "2")])
+        let res = TestClient::post(format!("http://127.0.0.1:5801?csrf-token={csrf_token}"))
+            .json(&serde_json::json!({"name": "salvo"}))
+            .add_header("cookie", &cookie, true)
             .send(&service)
             .await;
         assert_eq!(res.status_code.unwrap(), StatusCode::OK);
-        assert_eq!(res.take_string().await.unwrap(), "POST");
     }
 
     #[tokio::test]
-    async fn test_rejects_short_token() {
+    async fn test_require_secure_request() {
         let csrf = Csrf::new(
-            BcryptCipher::new(),
+            BcryptCipher::new().cost(4),
             CookieStore::new(),
             HeaderFinder::new("x-csrf-token"),
-        );
-        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        )
+        .require_secure_request(true);
+        let router = Router::new()
+            .hoop(csrf.clone())
+            .get(get_index)
+            .post(post_index);
         let service = Service::new(router);
 
-        let res = TestClient::get("http://127.0.0.1:5801")
+        let mut res = TestClient::get("https://127.0.0.1:5801")
             .send(&service)
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap().to_string();
 
-        let cookie = res.cookie("salvo.csrf").unwrap();
+        for (url, status) in [
+            ("https://127.0.0.1:5801", StatusCode::OK),
+            ("http://127.0.0.1:5801", StatusCode::FORBIDDEN),
+        ] {
+            let res = TestClient::post(url)
+                .add_header("x-csrf-token", &csrf_token, true)
+                .add_header("cookie", &cookie, true)
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), status, "{url}");
+        }
 
-        let res = TestClient::post("http://127.0.0.1:5801")
+        let proxied = Service::new(
+            Router::new()
+                .hoop(csrf.trust_forwarded_proto(true))
+                .post(post_index),
+        );
+        for (service, proto, status) in [
+            (&proxied, "https", StatusCode::OK),
+            (&proxied, "http, https", StatusCode::FORBIDDEN),
+            (&service, "https", StatusCode::FORBIDDEN),
+        ] {
+            let res = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-forwarded-proto", proto, true)
+                .add_header("x-csrf-token", &csrf_token, true)
+                .add_header("cookie", &cookie, true)
+                .send(service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), status, "{proto}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shared_store() {
+        use std::sync::Mutex;
+
+        #[derive(Default)]
+        struct MemoryStore(Mutex<Option<(String, String)>>);
+        impl CsrfStore for MemoryStore {
+            type Error = std::convert::Infallible;
+            async fn load<C: CsrfCipher>(
+                &self,
+                _req: &mut Request,
+                _depot: &mut Depot,
+                _cipher: &C,
+            ) -> Option<(String, String)> {
+                self.0.lock().unwrap().clone()
+            }
+            async fn save(
+                &self,
+                _req: &mut Request,
+                _depot: &mut Depot,
+                _res: &mut Response,
+                token: &str,
+                proof: &str,
+            ) -> Result<(), Self::Error> {
+                *self.0.lock().unwrap() = Some((token.into(), proof.into()));
+                Ok(())
+            }
+        }
+
+        let store = Arc::new(MemoryStore::default());
+        let header_csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            store.clone(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let query_csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            store.clone(),
+            QueryFinder::new(),
+        );
+        let router = Router::new()
+            .push(
+                Router::with_path("header")
+                    .hoop(header_csrf)
+                    .get(get_index)
+                    .post(post_index),
+            )
+            .push(Router::with_path("query").hoop(query_csrf).post(post_index));
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801/header")
             .send(&service)
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+        let csrf_token = res.take_string().await.unwrap();
+        assert!(store.0.lock().unwrap().is_some());
 
-        let res = TestClient::post("http://127.0.0.1:5801")
-            .add_header("x-csrf-token", "aGVsbG8=", true)
-            .add_header(
-                "cookie",
-                cookie.to_string().split_once('.').unwrap().0,
-                true,
-            )
+        let res = TestClient::post(format!(
+            "http://127.0.0.1:5801/query?csrf-token={csrf_token}"
+        ))
+        .send(&service)
+        .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let res = TestClient::post("http://127.0.0.1:5801/header")
+            .add_header("x-csrf-token", &csrf_token, true)
             .send(&service)
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
     }
 
     #[tokio::test]
-    async fn test_rejects_invalid_base64_token() {
+    async fn test_protect_with_namespace() {
         let csrf = Csrf::new(
-            BcryptCipher::new(),
+            BcryptCipher::new().cost(4),
             CookieStore::new(),
             HeaderFinder::new("x-csrf-token"),
+        )
+        .with_namespace("admin");
+        let router = csrf.protect(
+            Router::new().push(Router::with_path("items").post(post_index)),
+            "csrf-token",
         );
-        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
         let service = Service::new(router);
 
-        let res = TestClient::get("http://127.0.0.1:5801")
+        let mut res = TestClient::get("http://127.0.0.1:5801/csrf-token")
             .send(&service)
             .await;
         assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let token = res.take_string().await.unwrap();
+        let cookie = res.cookie("admin.salvo.csrf").unwrap().to_string();
 
-        let cookie = res.cookie("salvo.csrf").unwrap();
-
-        let res = TestClient::post("http://127.0.0.1:5801")
-            .send(&service)
-            .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
-
-        let res = TestClient::post("http://127.0.0.1:5801")
-            .add_header("x-csrf-token", "aGVsbG8", true)
-            .add_header(
-                "cookie",
-                cookie.to_string().split_once('.').unwrap().0,
-                true,
-            )
+        let res = TestClient::post("http://127.0.0.1:5801/items")
+            .add_header("x-csrf-token", &token, true)
+            .add_header("cookie", &cookie, true)
             .send(&service)
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
     }
 
     #[tokio::test]
-    async fn test_rejects_mismatched_token() {
-        let csrf = Csrf::new(
-            BcryptCipher::new(),
-            CookieStore::new(),
-            HeaderFinder::new("x-csrf-token"),
-        );
-        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+    async fn test_namespace_rotate_outer() {
+        #[handler]
+        async fn get_tokens(depot: &mut Depot) -> String {
+            let (outer_name, outer) = depot.csrf_header_pair_with_namespace("outer").unwrap();
+            let (inner_name, inner) = depot.csrf_header_pair_with_namespace("inner").unwrap();
+            assert_eq!((outer_name, inner_name), ("x-outer-token", "x-inner-token"));
+            format!("{outer} {inner}")
+        }
+        #[handler]
+        async fn rotate_outer(req: &mut Request, depot: &mut Depot, res: &mut Response) -> String {
+            let rotated = depot
+                .rotate_csrf_with_namespace("outer", req, res)
+                .await
+                .unwrap();
+            assert_eq!(
+                depot.csrf_header_pair_with_namespace("outer").unwrap().1,
+                &rotated
+            );
+            rotated
+        }
+
+        let csrf = |name: &str, prefix: &str| {
+            Csrf::new(
+                BcryptCipher::new().cost(4),
+                CookieStore::new(),
+                HeaderFinder::new(name),
+            )
+            .with_namespace(prefix)
+        };
+        let router = Router::new()
+            .hoop(csrf("x-outer-token", "outer"))
+            .hoop(csrf("x-inner-token", "inner"))
+            .get(get_tokens)
+            .push(Router::with_path("rotate").post(rotate_outer))
+            .post(post_index);
         let service = Service::new(router);
 
         let mut res = TestClient::get("http://127.0.0.1:5801")
             .send(&service)
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
-        let csrf_token = res.take_string().await.unwrap();
+        let tokens = res.take_string().await.unwrap();
+        let (outer, inner) = tokens.split_once(' ').unwrap();
+        let inner_cookie = res
+            .cookie("inner.salvo.csrf")
+            .unwrap()
+            .stripped()
+            .to_string();
+        let outer_cookie = res
+            .cookie("outer.salvo.csrf")
+            .unwrap()
+            .stripped()
+            .to_string();
 
-        let res = TestClient::get("http://127.0.0.1:5801")
+        let mut res = TestClient::post("http://127.0.0.1:5801/rotate")
+            .add_header("x-outer-token", outer, true)
+            .add_header("x-inner-token", inner, true)
+            .add_header("cookie", format!("{outer_cookie}; {inner_cookie}"), true)
             .send(&service)
             .await;
         assert_eq!(res.status_code.unwrap(), StatusCode::OK);
-        let cookie = res.cookie("salvo.csrf").unwrap();
+        let rotated = res.take_string().await.unwrap();
+        assert_ne!(rotated, outer);
+        let rotated_cookie = res
+            .cookie("outer.salvo.csrf")
+            .unwrap()
+            .stripped()
+            .to_string();
+        assert_ne!(rotated_cookie, outer_cookie);
+        assert_eq!(
+            res.cookie("inner.salvo.csrf")
+                .unwrap()
+                .stripped()
+                .to_string(),
+            inner_cookie
+        );
 
         let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-outer-token", &rotated, true)
+            .add_header("x-inner-token", inner, true)
+            .add_header("cookie", format!("{rotated_cookie}; {inner_cookie}"), true)
             .send(&service)
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+    }
 
-        let res = TestClient::post("http://127.0.0.1:5801")
-            .add_header("x-csrf-token", csrf_token, true)
-            .add_header(
-                "cookie",
-                cookie.to_string().split_once('.').unwrap().0,
-                true,
-            )
-            .send(&service)
+    #[tokio::test]
+    async fn test_csrf_header_pair_with_depot_key() {
+        #[handler]
+        async fn get_pair(depot: &mut Depot) -> String {
+            let (name, token) = depot.csrf_header_pair().unwrap();
+            assert_eq!(depot.csrf_token_with_key("api.csrf"), Some(token.as_str()));
+            format!("{name}:{token}")
+        }
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_depot_key("api.csrf");
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(Router::new().hoop(csrf).get(get_pair))
             .await;
-        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        let pair = res.take_string().await.unwrap();
+        assert!(pair.starts_with("x-csrf-token:"));
     }
 }
@@ -0,0 +1,184 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
+use salvo_core::Depot;
+
+use super::{CsrfCipher, CsrfVerifyError};
+
+/// A cipher wrapper masking the tokens of another cipher with a random one-time pad.
+///
+/// A masked token is the pad followed by the token XORed with it, so the same token can be
+/// sent in a different form in every response, which mitigates compression attacks like
+/// BREACH. Re-mask the token before rendering it with [`remask_token`]. [`verify`] unmasks
+/// the token itself, so finders return the masked token unchanged.
+///
+/// Tokens of the wrapped cipher must be url-safe base64 without padding, as with all the
+/// ciphers of this crate.
+///
+/// [`verify`]: CsrfCipher::verify
+#[derive(Clone, Debug)]
+pub struct MaskedCipher<C> {
+    inner: C,
+}
+
+impl<C: CsrfCipher> MaskedCipher<C> {
+    /// Create a new `MaskedCipher` masking the tokens of `inner`.
+    #[inline]
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+}
+
+fn mask(token: &[u8], rng: &mut dyn RngCore) -> String {
+    let mut masked = vec![0; token.len() * 2];
+    let (pad, body) = masked.split_at_mut(token.len());
+    rng.fill_bytes(pad);
+    for ((byte, pad), token) in body.iter_mut().zip(pad.iter()).zip(token) {
+        *byte = pad ^ token;
+    }
+    URL_SAFE_NO_PAD.encode(masked)
+}
+
+fn unmask(masked: &str) -> Option<Vec<u8>> {
+    let masked = URL_SAFE_NO_PAD.decode(masked).ok()?;
+    if masked.is_empty() || masked.len() % 2 != 0 {
+        return None;
+    }
+    let (pad, body) = masked.split_at(masked.len() / 2);
+    Some(pad.iter().zip(body).map(|(pad, byte)| pad ^ byte).collect())
+}
+
+/// Masks a token issued by a [`MaskedCipher`] with a new pad, e.g. before each render.
+///
+/// Returns `None` if `token` is not a masked token.
+pub fn remask_token(token: &str) -> Option<String> {
+    unmask(token).map(|token| mask(&token, &mut rand::thread_rng()))
+}
+
+impl<C: CsrfCipher> CsrfCipher for MaskedCipher<C> {
+    fn prepare(&self, depot: &Depot) -> bool {
+        self.inner.prepare(depot)
+    }
+    fn verify(&self, token: &str, proof: &str) -> bool {
+        match unmask(token) {
+            Some(token) => self.inner.verify(&URL_SAFE_NO_PAD.encode(token), proof),
+            None => false,
+        }
+    }
+    fn verify_detailed(&self, token: &str, proof: &str) -> Result<(), CsrfVerifyError> {
+        match unmask(token) {
+            Some(token) => self
+                .inner
+                .verify_detailed(&URL_SAFE_NO_PAD.encode(token), proof),
+            None => Err(CsrfVerifyError::Malformed),
+        }
+    }
+    fn min_token_len(&self) -> usize {
+        let len = self.inner.min_token_len() * 3 / 4;
+        base64::encoded_len(len * 2, false).unwrap_or(usize::MAX)
+    }
+    fn generate(&self) -> (String, String) {
+        self.generate_with_rng(&mut rand::thread_rng())
+    }
+    fn generate_with_rng(&self, rng: &mut dyn RngCore) -> (String, String) {
+        let (token, proof) = self.inner.generate_with_rng(rng);
+        let token = URL_SAFE_NO_PAD
+            .decode(&token)
+            .expect("tokens of the masked cipher must be url-safe base64");
+        (mask(&token, rng), proof)
+    }
+    fn supports_aad(&self) -> bool {
+        self.inner.supports_aad()
+    }
+    fn verify_with_aad(&self, token: &str, proof: &str, aad: &[u8]) -> bool {
+        match unmask(token) {
+            Some(token) => self
+                .inner
+                .verify_with_aad(&URL_SAFE_NO_PAD.encode(token), proof, aad),
+            None => false,
+        }
+    }
+    fn generate_with_aad(&self, aad: &[u8]) -> (String, String) {
+        let (token, proof) = self.inner.generate_with_aad(aad);
+        let token = URL_SAFE_NO_PAD
+            .decode(&token)
+            .expect("tokens of the masked cipher must be url-safe base64");
+        (mask(&token, &mut rand::thread_rng()), proof)
+    }
+}
+
+#[cfg(all(test, feature = "cookie-store", feature = "bcrypt-cipher"))]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+    use crate::{BcryptCipher, CookieStore, Csrf, CsrfDepotExt, HeaderFinder};
+
+    #[test]
+    fn test_masked_cipher() {
+        let cipher = MaskedCipher::new(BcryptCipher::new().cost(4));
+        let (token, proof) = cipher.generate();
+        assert!(cipher.verify(&token, &proof));
+
+        let remasked = remask_token(&token).unwrap();
+        assert_ne!(remasked, token);
+        assert!(cipher.verify(&remasked, &proof));
+
+        let unmasked = URL_SAFE_NO_PAD.encode(unmask(&token).unwrap());
+        assert!(BcryptCipher::new().verify(&unmasked, &proof));
+        assert!(!cipher.verify(&unmasked[1..], &proof));
+        assert!(!cipher.verify("", &proof));
+        assert_eq!(remask_token("invalid base64!"), None);
+    }
+
+    #[tokio::test]
+    async fn test_masked_token_in_header() {
+        #[handler]
+        async fn get_index(depot: &mut Depot) -> String {
+            remask_token(depot.csrf_token().unwrap()).unwrap()
+        }
+        #[handler]
+        async fn post_index() -> &'static str {
+            "POST"
+        }
+
+        let csrf = Csrf::new(
+            MaskedCipher::new(BcryptCipher::new().cost(4)),
+            CookieStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        let csrf_token = res.take_string().await.unwrap();
+        let cookie = res.cookie("salvo.csrf").unwrap();
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        let other_token = res.take_string().await.unwrap();
+        assert_ne!(csrf_token, other_token);
+
+        for token in [csrf_token, other_token] {
+            let res = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", token, true)
+                .add_header("cookie", cookie.to_string(), true)
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        }
+
+        let (forged, _) = MaskedCipher::new(BcryptCipher::new().cost(4)).generate();
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", forged, true)
+            .add_header("cookie", cookie.to_string(), true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+}
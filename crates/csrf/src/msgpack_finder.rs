@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use salvo_core::{async_trait, Request};
+use serde_json::Value;
+
+use crate::finder::read_body;
+use crate::CsrfTokenFinder;
+
+/// Find token from a MessagePack request body, e.g. `{"csrf-token": "..."}` encoded as
+/// MessagePack.
+///
+/// Only bodies with the `application/msgpack`, `application/x-msgpack` or
+/// `application/vnd.msgpack` content type are read. Bodies larger than
+/// [`max_body_size`](Self::max_body_size) are not read, so the token is not found and the
+/// request is rejected. The body stays available to later handlers.
+#[derive(Clone, Debug)]
+pub struct MsgpackFinder {
+    field_name: String,
+    max_body_size: usize,
+}
+impl MsgpackFinder {
+    /// Create new `MsgpackFinder`.
+    #[inline]
+    pub fn new(field_name: impl Into<String>) -> Self {
+        Self {
+            field_name: field_name.into(),
+            max_body_size: 16 * 1024,
+        }
+    }
+
+    /// Sets the maximum size in bytes of the body read to find the token, defaults to 16 KiB.
+    #[inline]
+    pub fn max_body_size(mut self, size: usize) -> Self {
+        self.max_body_size = size;
+        self
+    }
+}
+#[async_trait]
+impl CsrfTokenFinder for MsgpackFinder {
+    async fn find_token(&self, req: &mut Request) -> Option<String> {
+        let ctype = req.content_type()?;
+        if !matches!(
+            ctype.subtype().as_str(),
+            "msgpack" | "x-msgpack" | "vnd.msgpack"
+        ) {
+            return None;
+        }
+        let payload = read_body(req, self.max_body_size).await?;
+        let data = rmp_serde::from_slice::<HashMap<String, Value>>(payload).ok()?;
+        data.get(&self.field_name)?.as_str().map(ToOwned::to_owned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::http::header::CONTENT_TYPE;
+    use salvo_core::test::TestClient;
+
+    use super::*;
+
+    fn msgpack_body(fields: &[(&str, &str)]) -> Vec<u8> {
+        let data: HashMap<_, _> = fields.iter().copied().collect();
+        rmp_serde::to_vec_named(&data).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_msgpack_finder() {
+        let finder = MsgpackFinder::new("csrf-token");
+        let mut req = TestClient::post("http://test.com")
+            .add_header(CONTENT_TYPE, "application/msgpack", true)
+            .body(msgpack_body(&[("csrf-token", "test_token"), ("a", "1")]))
+            .build();
+        let token = finder.find_token(&mut req).await;
+        assert_eq!(token, Some("test_token".to_string()));
+
+        let mut req = TestClient::post("http://test.com")
+            .add_header(CONTENT_TYPE, "application/x-msgpack", true)
+            .body(msgpack_body(&[("a", "1")]))
+            .build();
+        assert_eq!(finder.find_token(&mut req).await, None);
+
+        let mut req = TestClient::post("http://test.com")
+            .add_header(CONTENT_TYPE, "application/json", true)
+            .body(msgpack_body(&[("csrf-token", "test_token")]))
+            .build();
+        assert_eq!(finder.find_token(&mut req).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_msgpack_finder_max_body_size() {
+        let finder = MsgpackFinder::new("csrf-token").max_body_size(64);
+        let padding = "a".repeat(1024);
+        let mut req = TestClient::post("http://test.com")
+            .add_header(CONTENT_TYPE, "application/msgpack", true)
+            .body(msgpack_body(&[
+                ("csrf-token", "test_token"),
+                ("padding", &padding),
+            ]))
+            .build();
+        assert_eq!(finder.find_token(&mut req).await, None);
+    }
+}
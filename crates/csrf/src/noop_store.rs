@@ -0,0 +1,103 @@
+use std::convert::Infallible;
+
+use salvo_core::{Depot, Request, Response};
+
+use super::{CsrfCipher, CsrfStore};
+
+/// A `CsrfStore` that persists nothing and always loads one configured token and proof.
+///
+/// Every client shares the same token, so this only makes sense with self-contained ciphers
+/// whose proof can be derived from a server side key, like `HmacCipher`, and where a per
+/// client token is not needed, e.g. internal services or tests. Issued tokens are never saved.
+#[derive(Clone, Debug)]
+pub struct NoopStore {
+    token: String,
+    proof: String,
+}
+
+impl NoopStore {
+    /// Create a new `NoopStore` loading `token` and `proof`, e.g. from [`CsrfCipher::generate`].
+    #[inline]
+    pub fn new(token: impl Into<String>, proof: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+            proof: proof.into(),
+        }
+    }
+}
+
+impl CsrfStore for NoopStore {
+    type Error = Infallible;
+    async fn load<C: CsrfCipher>(
+        &self,
+        _req: &mut Request,
+        _depot: &mut Depot,
+        cipher: &C,
+    ) -> Option<(String, String)> {
+        if cipher.verify(&self.token, &self.proof) {
+            Some((self.token.clone(), self.proof.clone()))
+        } else {
+            tracing::error!("configured csrf token does not match its proof");
+            None
+        }
+    }
+    async fn save(
+        &self,
+        _req: &mut Request,
+        _depot: &mut Depot,
+        _res: &mut Response,
+        _token: &str,
+        _proof: &str,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "hmac-cipher"))]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+    use crate::{Csrf, CsrfDepotExt, HeaderFinder, HmacCipher};
+
+    #[handler]
+    async fn get_index(depot: &mut Depot) -> String {
+        depot.csrf_token().unwrap().to_owned()
+    }
+    #[handler]
+    async fn post_index() -> &'static str {
+        "POST"
+    }
+
+    #[tokio::test]
+    async fn test_noop_store() {
+        let cipher = HmacCipher::new(*b"01234567012345670123456701234567");
+        let (token, proof) = cipher.generate();
+        let csrf = Csrf::new(
+            cipher,
+            NoopStore::new(token.clone(), proof),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), token);
+        assert!(res.cookies().iter().next().is_none());
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", token, true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", "invalid", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,88 @@
+use salvo_core::http::header::{HOST, ORIGIN};
+use salvo_core::Request;
+
+use super::fetch_site::FetchSiteCheck;
+use super::CsrfRejectReason;
+
+/// How [`Csrf`](crate::Csrf) uses the `Origin` request header on protected requests.
+///
+/// An origin is trusted when it is the origin of the request itself, or when it is in the
+/// list passed to [`Csrf::with_trusted_origins`](crate::Csrf::with_trusted_origins). Other
+/// origins, including `null` unless listed or allowed with
+/// [`Csrf::allow_null_origin`](crate::Csrf::allow_null_origin), are rejected whatever the
+/// token.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+#[non_exhaustive]
+pub enum OriginMode {
+    /// Ignore the header, only tokens are validated.
+    #[default]
+    Disabled,
+    /// Reject untrusted origins, then validate the token as usual.
+    WithToken,
+    /// Accept trusted origins without validating the token.
+    Standalone,
+}
+
+/// What [`Csrf`](crate::Csrf) does when the `Origin` header is absent, e.g. for same origin
+/// `GET` forms in older browsers or non-browser clients.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+#[non_exhaustive]
+pub enum MissingOrigin {
+    /// Validate the token as usual.
+    #[default]
+    ValidateToken,
+    /// Reject the request.
+    Reject,
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OriginPolicy {
+    pub(crate) mode: OriginMode,
+    pub(crate) trusted: Vec<String>,
+    pub(crate) missing: MissingOrigin,
+    pub(crate) allow_null: bool,
+}
+
+fn normalize(origin: &str) -> &str {
+    origin.trim().trim_end_matches('/')
+}
+
+/// The origin of the request itself, from its scheme and `Host` header.
+pub(crate) fn request_origin(req: &Request) -> Option<String> {
+    let scheme = req.uri().scheme_str().unwrap_or(req.scheme().as_str());
+    let host = req
+        .headers()
+        .get(HOST)
+        .and_then(|host| host.to_str().ok())
+        .or_else(|| req.uri().authority().map(|authority| authority.as_str()))?;
+    Some(format!("{scheme}://{host}"))
+}
+
+pub(crate) fn check_origin(req: &Request, policy: &OriginPolicy) -> FetchSiteCheck {
+    if policy.mode == OriginMode::Disabled {
+        return FetchSiteCheck::ValidateToken;
+    }
+    let Some(origin) = req.headers().get(ORIGIN).and_then(|v| v.to_str().ok()) else {
+        return match policy.missing {
+            MissingOrigin::ValidateToken => FetchSiteCheck::ValidateToken,
+            MissingOrigin::Reject => FetchSiteCheck::Reject(CsrfRejectReason::MissingOrigin),
+        };
+    };
+    let origin = normalize(origin);
+    if policy.allow_null && origin.eq_ignore_ascii_case("null") {
+        return FetchSiteCheck::ValidateToken;
+    }
+    let same_origin = request_origin(req).is_some_and(|own| own.eq_ignore_ascii_case(origin));
+    let trusted = same_origin
+        || policy
+            .trusted
+            .iter()
+            .any(|trusted| normalize(trusted).eq_ignore_ascii_case(origin));
+    if !trusted {
+        FetchSiteCheck::Reject(CsrfRejectReason::UntrustedOrigin)
+    } else if policy.mode == OriginMode::Standalone {
+        FetchSiteCheck::Accept
+    } else {
+        FetchSiteCheck::ValidateToken
+    }
+}
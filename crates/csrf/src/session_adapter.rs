@@ -0,0 +1,246 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use salvo_core::{Depot, Request, Response};
+
+use super::{CsrfCipher, CsrfStore, NamespaceError};
+
+/// The minimal interface a session type must provide to be used with [`SessionAdapter`].
+pub trait CsrfSession: Send + Sync + 'static {
+    /// Get the value stored under `key`.
+    fn get(&self, key: &str) -> Option<String>;
+    /// Store `value` under `key`, replacing any previous value.
+    fn set(&mut self, key: &str, value: String);
+    /// Remove the value stored under `key`.
+    fn remove(&mut self, key: &str);
+}
+
+/// Error type for [`SessionAdapter`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum SessionAdapterError {
+    /// The session accessor found no session in the depot.
+    #[error("no session found in depot, is the session handler hooped before `Csrf`?")]
+    SessionMissing,
+}
+
+/// A `CsrfStore` that stores the CSRF proof in a session of any session crate.
+///
+/// Unlike [`SessionStore`](crate::SessionStore), which works with `salvo-session`, the
+/// session is obtained from the depot with an accessor closure, so any session type
+/// implementing [`CsrfSession`] can be used, e.g. one put in the depot by a `tower-sessions`
+/// layer.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use salvo_csrf::{CsrfSession, SessionAdapter};
+///
+/// #[derive(Default)]
+/// struct MySession(HashMap<String, String>);
+/// impl CsrfSession for MySession {
+///     fn get(&self, key: &str) -> Option<String> {
+///         self.0.get(key).cloned()
+///     }
+///     fn set(&mut self, key: &str, value: String) {
+///         self.0.insert(key.into(), value);
+///     }
+///     fn remove(&mut self, key: &str) {
+///         self.0.remove(key);
+///     }
+/// }
+///
+/// let store = SessionAdapter::new(|depot| depot.obtain_mut::<MySession>().ok());
+/// ```
+pub struct SessionAdapter<S, F> {
+    accessor: F,
+    key: String,
+    _session: PhantomData<fn() -> S>,
+}
+
+impl<S, F> SessionAdapter<S, F>
+where
+    S: CsrfSession,
+    F: Fn(&mut Depot) -> Option<&mut S> + Send + Sync + 'static,
+{
+    /// Create a new `SessionAdapter` getting the session from the depot with `accessor`.
+    #[inline]
+    pub fn new(accessor: F) -> Self {
+        Self {
+            accessor,
+            key: "salvo.csrf".into(),
+            _session: PhantomData,
+        }
+    }
+
+    /// Sets the session key the proof is stored under, defaults to `salvo.csrf`.
+    #[inline]
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.key = key.into();
+        self
+    }
+}
+
+impl<S, F: Clone> Clone for SessionAdapter<S, F> {
+    fn clone(&self) -> Self {
+        Self {
+            accessor: self.accessor.clone(),
+            key: self.key.clone(),
+            _session: PhantomData,
+        }
+    }
+}
+
+impl<S, F> fmt::Debug for SessionAdapter<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionAdapter")
+            .field("session", &std::any::type_name::<S>())
+            .field("key", &self.key)
+            .finish()
+    }
+}
+
+impl<S, F> CsrfStore for SessionAdapter<S, F>
+where
+    S: CsrfSession,
+    F: Fn(&mut Depot) -> Option<&mut S> + Send + Sync + 'static,
+{
+    type Error = SessionAdapterError;
+    async fn load<C: CsrfCipher>(
+        &self,
+        _req: &mut Request,
+        depot: &mut Depot,
+        _cipher: &C,
+    ) -> Option<(String, String)> {
+        let Some(session) = (self.accessor)(depot) else {
+            tracing::error!("{}", SessionAdapterError::SessionMissing);
+            return None;
+        };
+        let pair = session.get(&self.key)?;
+        pair.split_once('.')
+            .map(|(token, proof)| (token.into(), proof.into()))
+    }
+    fn namespace(&mut self, prefix: &str) -> Result<(), NamespaceError> {
+        self.key = format!("{prefix}.{}", self.key);
+        Ok(())
+    }
+    fn check(&self, depot: &mut Depot) -> Result<(), Self::Error> {
+        match (self.accessor)(depot) {
+            Some(_) => Ok(()),
+            None => Err(SessionAdapterError::SessionMissing),
+        }
+    }
+    async fn save(
+        &self,
+        _req: &mut Request,
+        depot: &mut Depot,
+        _res: &mut Response,
+        token: &str,
+        proof: &str,
+    ) -> Result<(), Self::Error> {
+        let session = (self.accessor)(depot).ok_or(SessionAdapterError::SessionMissing)?;
+        session.set(&self.key, format!("{token}.{proof}"));
+        Ok(())
+    }
+    async fn clear(
+        &self,
+        _req: &mut Request,
+        depot: &mut Depot,
+        _res: &mut Response,
+    ) -> Result<(), Self::Error> {
+        let session = (self.accessor)(depot).ok_or(SessionAdapterError::SessionMissing)?;
+        session.remove(&self.key);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "bcrypt-cipher"))]
+mod tests {
+    use std::collections::HashMap;
+
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+    use crate::{BcryptCipher, Csrf, CsrfDepotExt, HeaderFinder};
+
+    #[derive(Default)]
+    struct MockSession(HashMap<String, String>);
+    impl CsrfSession for MockSession {
+        fn get(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+        fn set(&mut self, key: &str, value: String) {
+            self.0.insert(key.into(), value);
+        }
+        fn remove(&mut self, key: &str) {
+            self.0.remove(key);
+        }
+    }
+
+    fn store() -> SessionAdapter<MockSession, impl Fn(&mut Depot) -> Option<&mut MockSession>> {
+        SessionAdapter::new(|depot| depot.obtain_mut::<MockSession>().ok())
+    }
+
+    #[tokio::test]
+    async fn test_session_adapter_round_trip() {
+        let store = store().with_key("my.csrf");
+        let cipher = BcryptCipher::new();
+        let mut req = Request::new();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+        depot.inject(MockSession::default());
+
+        store
+            .save(&mut req, &mut depot, &mut res, "token", "proof")
+            .await
+            .unwrap();
+        let session = depot.obtain::<MockSession>().unwrap();
+        assert_eq!(session.0["my.csrf"], "token.proof");
+
+        let loaded = store.load(&mut req, &mut depot, &cipher).await;
+        assert_eq!(loaded, Some(("token".into(), "proof".into())));
+
+        store.clear(&mut req, &mut depot, &mut res).await.unwrap();
+        assert_eq!(store.load(&mut req, &mut depot, &cipher).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_session_adapter_without_session() {
+        let store = store();
+        let mut req = Request::new();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+
+        let loaded = store.load(&mut req, &mut depot, &BcryptCipher::new()).await;
+        assert_eq!(loaded, None);
+        let result = store
+            .save(&mut req, &mut depot, &mut res, "token", "proof")
+            .await;
+        assert!(matches!(result, Err(SessionAdapterError::SessionMissing)));
+    }
+
+    #[tokio::test]
+    async fn test_session_adapter_middleware() {
+        #[handler]
+        async fn session(depot: &mut Depot) {
+            depot.inject(MockSession::default());
+        }
+        #[handler]
+        async fn get_index(depot: &mut Depot) -> String {
+            depot.csrf_token().unwrap().to_owned()
+        }
+
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            store(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(session).hoop(csrf).get(get_index);
+        let mut res = TestClient::get("http://127.0.0.1:5801").send(router).await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert!(!res.take_string().await.unwrap().is_empty());
+    }
+}
@@ -1,12 +1,33 @@
-use salvo_core::{Depot, Error, Request, Response};
-use salvo_session::SessionDepotExt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use super::{CsrfCipher, CsrfStore};
+use salvo_core::{Depot, Request, Response};
+use salvo_session::{Session, SessionDepotExt};
+
+use super::{CsrfCipher, CsrfStore, NamespaceError};
+
+/// Error type for [`SessionStore`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum SessionStoreError {
+    /// No session was found in the depot, the session handler is not hooped before `Csrf`.
+    #[error(
+        "no session found in depot, the session handler must be hooped before `Csrf`, \
+         e.g. `Router::new().hoop(session_handler).hoop(csrf)`"
+    )]
+    SessionMissing,
+    /// The proof could not be serialized into the session.
+    #[error("failed to serialize csrf proof into session: {0}")]
+    Serialize(#[from] serde_json::Error),
+}
 
 /// A `CsrfStore` implementation that stores the CSRF proof in a session.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct SessionStore {
     name: String,
+    ttl: Option<Duration>,
+    history: usize,
+    rotation: Option<(Duration, Duration)>,
+    clock: fn() -> u64,
 }
 impl Default for SessionStore {
     fn default() -> Self {
@@ -19,17 +40,204 @@ impl SessionStore {
     pub fn new() -> Self {
         Self {
             name: "salvo.csrf".into(),
+            ttl: None,
+            history: 1,
+            rotation: None,
+            clock: now_millis,
         }
     }
+
+    /// Sets the session key the proof is stored under.
+    ///
+    /// Use distinct keys when running multiple independent `Csrf` instances.
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.name = key.into();
+        self
+    }
+
+    /// Sets the ttl of the stored proof, an expired proof is treated as absent.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Keeps the last `history` issued proofs, tokens matching any of them are accepted.
+    ///
+    /// This lets pages opened before a new token was issued keep working. Defaults to 1,
+    /// only the current proof is accepted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `history` is 0.
+    pub fn with_history(mut self, history: usize) -> Self {
+        assert!(history > 0, "history must be at least 1");
+        self.history = history;
+        self
+    }
+
+    /// Rotates the proof once it was issued `interval` ago, regardless of requests changing it.
+    ///
+    /// `Csrf` issues the new token on the next allowed request, binding and saving it like any
+    /// other, so it goes through decorators like `EncryptedStore`. No background task is
+    /// involved. Tokens
+    /// of the previous proof are still accepted for `overlap` after it was replaced, by
+    /// rotation or [`rotate_csrf`](crate::CsrfDepotExt::rotate_csrf), so pages opened just
+    /// before keep working.
+    pub fn with_rotation(mut self, interval: Duration, overlap: Duration) -> Self {
+        self.rotation = Some((interval, overlap));
+        self
+    }
+
+    #[cfg(test)]
+    fn with_clock(mut self, clock: fn() -> u64) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn expires_key(&self) -> String {
+        format!("{}.expires_at", self.name)
+    }
+
+    fn history_key(&self) -> String {
+        format!("{}.history", self.name)
+    }
+
+    fn issued_key(&self) -> String {
+        format!("{}.issued_at", self.name)
+    }
+
+    fn previous_key(&self) -> String {
+        format!("{}.previous", self.name)
+    }
+
+    fn previous_expires_key(&self) -> String {
+        format!("{}.previous_expires_at", self.name)
+    }
+
+    fn write(
+        &self,
+        session: &mut Session,
+        token: &str,
+        proof: &str,
+    ) -> Result<(), SessionStoreError> {
+        let now = (self.clock)();
+        let previous = session.get::<String>(&self.name);
+        if self.history > 1 {
+            let mut history = session
+                .get::<Vec<String>>(&self.history_key())
+                .unwrap_or_default();
+            if let Some(previous) = &previous {
+                history.insert(0, previous.clone());
+            }
+            history.truncate(self.history - 1);
+            session.insert(&self.history_key(), history)?;
+        } else {
+            session.remove(&self.history_key());
+        }
+        match (self.rotation, previous) {
+            (Some((_, overlap)), Some(previous)) => {
+                session.insert(&self.previous_key(), previous)?;
+                session.insert(
+                    &self.previous_expires_key(),
+                    now + overlap.as_millis() as u64,
+                )?;
+            }
+            _ => {
+                session.remove(&self.previous_key());
+                session.remove(&self.previous_expires_key());
+            }
+        }
+        session.insert(&self.name, format!("{token}.{proof}"))?;
+        session.insert(&self.issued_key(), now)?;
+        if let Some(ttl) = self.ttl {
+            session.insert(&self.expires_key(), now + ttl.as_millis() as u64)?;
+        } else {
+            session.remove(&self.expires_key());
+        }
+        Ok(())
+    }
+}
+
+fn split_pair(pair: &str) -> Option<(String, String)> {
+    pair.split_once('.').map(|(t, p)| (t.into(), p.into()))
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
 }
 
 impl CsrfStore for SessionStore {
-    type Error = Error;
-    async fn load<C: CsrfCipher>(&self, _req: &mut Request, depot: &mut Depot, _cipher: &C) -> Option<(String, String)> {
-        depot
-            .session()
-            .and_then(|s| s.get::<String>(&self.name))
-            .and_then(|s| s.split_once('.').map(|(t, p)| (t.into(), p.into())))
+    type Error = SessionStoreError;
+    async fn load<C: CsrfCipher>(
+        &self,
+        _req: &mut Request,
+        depot: &mut Depot,
+        _cipher: &C,
+    ) -> Option<(String, String)> {
+        let Some(session) = depot.session() else {
+            tracing::error!("{}", SessionStoreError::SessionMissing);
+            return None;
+        };
+        if let Some(expires_at) = session.get::<u64>(&self.expires_key()) {
+            if expires_at <= (self.clock)() {
+                return None;
+            }
+        }
+        session
+            .get::<String>(&self.name)
+            .and_then(|pair| split_pair(&pair))
+    }
+    fn check(&self, depot: &mut Depot) -> Result<(), Self::Error> {
+        match depot.session() {
+            Some(_) => Ok(()),
+            None => Err(SessionStoreError::SessionMissing),
+        }
+    }
+    fn namespace(&mut self, prefix: &str) -> Result<(), NamespaceError> {
+        self.name = format!("{prefix}.{}", self.name);
+        Ok(())
+    }
+    fn rotation_due(&self, _req: &Request, depot: &Depot) -> bool {
+        let (Some((interval, _)), Some(session)) = (self.rotation, depot.session()) else {
+            return false;
+        };
+        let issued_at = session.get::<u64>(&self.issued_key()).unwrap_or_default();
+        session.get::<String>(&self.name).is_some()
+            && issued_at + interval.as_millis() as u64 <= (self.clock)()
+    }
+    async fn load_all<C: CsrfCipher>(
+        &self,
+        req: &mut Request,
+        depot: &mut Depot,
+        cipher: &C,
+    ) -> Vec<(String, String)> {
+        let Some(current) = self.load(req, depot, cipher).await else {
+            return vec![];
+        };
+        let Some(session) = depot.session() else {
+            return vec![current];
+        };
+        let history = session
+            .get::<Vec<String>>(&self.history_key())
+            .unwrap_or_default();
+        let previous = session
+            .get::<u64>(&self.previous_expires_key())
+            .filter(|expires_at| *expires_at > (self.clock)())
+            .and_then(|_| session.get::<String>(&self.previous_key()))
+            .and_then(|pair| split_pair(&pair));
+        let mut pairs: Vec<_> = std::iter::once(current)
+            .chain(history.iter().filter_map(|pair| split_pair(pair)))
+            .take(self.history)
+            .collect();
+        if let Some(previous) = previous {
+            if !pairs.contains(&previous) {
+                pairs.push(previous);
+            }
+        }
+        pairs
     }
     async fn save(
         &self,
@@ -39,10 +247,487 @@ impl CsrfStore for SessionStore {
         token: &str,
         proof: &str,
     ) -> Result<(), Self::Error> {
-        depot
+        let session = depot
             .session_mut()
-            .expect("session must be exist")
-            .insert(&self.name, format!("{token}.{proof}"))?;
+            .ok_or(SessionStoreError::SessionMissing)?;
+        self.write(session, token, proof)
+    }
+    async fn clear(
+        &self,
+        _req: &mut Request,
+        depot: &mut Depot,
+        _res: &mut Response,
+    ) -> Result<(), Self::Error> {
+        let session = depot
+            .session_mut()
+            .ok_or(SessionStoreError::SessionMissing)?;
+        session.remove(&self.name);
+        session.remove(&self.expires_key());
+        session.remove(&self.history_key());
+        session.remove(&self.issued_key());
+        session.remove(&self.previous_key());
+        session.remove(&self.previous_expires_key());
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::BcryptCipher;
+
+    #[tokio::test]
+    async fn test_session_store_custom_key() {
+        let store = SessionStore::new().with_key("my.csrf");
+        let cipher = BcryptCipher::new();
+        let mut req = Request::new();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+        depot.set_session(Session::new());
+
+        store
+            .save(&mut req, &mut depot, &mut res, "token", "proof")
+            .await
+            .unwrap();
+        let session = depot.session().unwrap();
+        assert_eq!(session.get::<String>("my.csrf").unwrap(), "token.proof");
+        assert!(session.get::<String>("salvo.csrf").is_none());
+
+        let loaded = store.load(&mut req, &mut depot, &cipher).await;
+        assert_eq!(loaded, Some(("token".into(), "proof".into())));
+        let loaded = SessionStore::new()
+            .load(&mut req, &mut depot, &cipher)
+            .await;
+        assert_eq!(loaded, None);
+    }
+
+    #[tokio::test]
+    async fn test_session_store_without_session() {
+        let store = SessionStore::new();
+        let mut req = Request::new();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+
+        let loaded = store.load(&mut req, &mut depot, &BcryptCipher::new()).await;
+        assert_eq!(loaded, None);
+        let result = store
+            .save(&mut req, &mut depot, &mut res, "token", "proof")
+            .await;
+        assert!(matches!(result, Err(SessionStoreError::SessionMissing)));
+    }
+
+    #[tokio::test]
+    async fn test_csrf_without_session_handler() {
+        use salvo_core::prelude::*;
+        use salvo_core::test::TestClient;
+
+        use crate::{Csrf, HeaderFinder};
+
+        #[handler]
+        async fn get_index() -> &'static str {
+            "GET"
+        }
+
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            SessionStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index);
+        let res = TestClient::get("http://127.0.0.1:5801").send(router).await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_csrf_hooped_before_session_handler() {
+        use salvo_core::prelude::*;
+        use salvo_core::test::TestClient;
+        use salvo_session::{MemoryStore, SessionHandler};
+
+        use crate::{Csrf, HeaderFinder};
+
+        #[handler]
+        async fn index() -> &'static str {
+            "OK"
+        }
+
+        let session_handler = SessionHandler::builder(
+            MemoryStore::new(),
+            b"secretabsecretabsecretabsecretabsecretabsecretabsecretabsecretab",
+        )
+        .build()
+        .unwrap();
+        let csrf = Csrf::new(
+            BcryptCipher::new(),
+            SessionStore::new(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new()
+            .hoop(csrf)
+            .hoop(session_handler)
+            .get(index)
+            .post(index);
+        let service = Service::new(router);
+
+        for _ in 0..2 {
+            let res = TestClient::post("http://127.0.0.1:5801")
+                .send(&service)
+                .await;
+            assert_eq!(res.status_code.unwrap(), StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        let res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        logs_assert(|lines: &[&str]| {
+            let errors = lines
+                .iter()
+                .filter(|line| line.contains("ERROR"))
+                .filter(|line| line.contains("must be hooped before `Csrf`"))
+                .count();
+            match errors {
+                1 => Ok(()),
+                n => Err(format!("expected one ordering error, found {n}")),
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn test_session_store_history() {
+        use salvo_core::test::TestClient;
+
+        use crate::{Csrf, HeaderFinder};
+
+        let store = SessionStore::new().with_history(3);
+        let cipher = BcryptCipher::new().cost(4);
+        let mut req = Request::new();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+        depot.set_session(Session::new());
+
+        let mut tokens = vec![];
+        for _ in 0..4 {
+            let (token, proof) = cipher.generate();
+            store
+                .save(&mut req, &mut depot, &mut res, &token, &proof)
+                .await
+                .unwrap();
+            tokens.push(token);
+        }
+        let pairs = store.load_all(&mut req, &mut depot, &cipher).await;
+        let loaded: Vec<_> = pairs.iter().map(|(token, _)| token).collect();
+        assert_eq!(loaded, [&tokens[3], &tokens[2], &tokens[1]]);
+
+        let csrf = Csrf::new(cipher, store, HeaderFinder::new("x-csrf-token"));
+        let mut results = vec![];
+        for token in &tokens {
+            let mut req = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", token, true)
+                .build();
+            results.push(
+                csrf.validate(&mut req, &Depot::new(), &pairs, &[])
+                    .await
+                    .is_ok(),
+            );
+        }
+        assert_eq!(results, [false, true, true, true]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_session_store_history_concurrent() {
+        use std::sync::Arc;
+
+        use salvo_core::prelude::*;
+        use salvo_core::test::{ResponseExt, TestClient};
+        use salvo_session::{MemoryStore, SessionHandler};
+
+        use crate::{Csrf, CsrfDepotExt, HeaderFinder};
+
+        #[handler]
+        async fn get_index(depot: &mut Depot) -> String {
+            depot.csrf_token().unwrap().to_owned()
+        }
+        #[handler]
+        async fn rotate(req: &mut Request, depot: &mut Depot, res: &mut Response) -> String {
+            depot.rotate_csrf(req, res).await.unwrap()
+        }
+        #[handler]
+        async fn post_index() -> &'static str {
+            "POST"
+        }
+
+        let session_handler = SessionHandler::builder(
+            MemoryStore::new(),
+            b"secretabsecretabsecretabsecretabsecretabsecretabsecretabsecretab",
+        )
+        .build()
+        .unwrap();
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            SessionStore::new().with_history(3),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new()
+            .hoop(session_handler)
+            .hoop(csrf)
+            .get(get_index)
+            .post(post_index)
+            .push(Router::with_path("rotate").get(rotate));
+        let service = Arc::new(Service::new(router));
+
+        // Every client issues its tokens while the others do the same against the shared
+        // session backend.
+        let clients: Vec<_> = (0..4)
+            .map(|_| {
+                let service = service.clone();
+                tokio::spawn(async move {
+                    let mut res = TestClient::get("http://127.0.0.1:5801")
+                        .send(&*service)
+                        .await;
+                    let mut tokens = vec![res.take_string().await.unwrap()];
+                    let cookie = res.cookie("salvo.session.id").unwrap().to_string();
+                    for _ in 0..3 {
+                        let mut res = TestClient::get("http://127.0.0.1:5801/rotate")
+                            .add_header("cookie", &cookie, true)
+                            .send(&*service)
+                            .await;
+                        tokens.push(res.take_string().await.unwrap());
+                    }
+                    (cookie, tokens)
+                })
+            })
+            .collect();
+        let mut sessions = vec![];
+        for client in clients {
+            sessions.push(client.await.unwrap());
+        }
+
+        let checks: Vec<_> = sessions
+            .iter()
+            .enumerate()
+            .map(|(i, (cookie, _))| {
+                let service = service.clone();
+                let cookie = cookie.clone();
+                let other_token = sessions[(i + 1) % sessions.len()].1[3].clone();
+                let tokens = sessions[i].1.clone();
+                tokio::spawn(async move {
+                    let mut accepted = vec![];
+                    for token in tokens.iter().chain([&other_token]) {
+                        let res = TestClient::post("http://127.0.0.1:5801")
+                            .add_header("x-csrf-token", token, true)
+                            .add_header("cookie", &cookie, true)
+                            .send(&*service)
+                            .await;
+                        accepted.push(res.status_code.unwrap() == StatusCode::OK);
+                    }
+                    accepted
+                })
+            })
+            .collect();
+        for check in checks {
+            // The three most recent tokens of the session validate, the oldest one and the
+            // tokens of other sessions do not.
+            assert_eq!(check.await.unwrap(), [false, true, true, true, false]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_store_clear() {
+        let store = SessionStore::new().with_history(2);
+        let cipher = BcryptCipher::new().cost(4);
+        let mut req = Request::new();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+        depot.set_session(Session::new());
+
+        for _ in 0..2 {
+            let (token, proof) = cipher.generate();
+            store
+                .save(&mut req, &mut depot, &mut res, &token, &proof)
+                .await
+                .unwrap();
+        }
+        store.clear(&mut req, &mut depot, &mut res).await.unwrap();
+        assert!(store
+            .load_all(&mut req, &mut depot, &cipher)
+            .await
+            .is_empty());
+        assert!(depot
+            .session()
+            .unwrap()
+            .get::<Vec<String>>("salvo.csrf.history")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_session_store_expired_proof() {
+        let store = SessionStore::new().with_ttl(Duration::from_millis(1));
+        let cipher = BcryptCipher::new();
+        let mut req = Request::new();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+        depot.set_session(Session::new());
+
+        store
+            .save(&mut req, &mut depot, &mut res, "token", "proof")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(store.load(&mut req, &mut depot, &cipher).await, None);
+
+        let store = SessionStore::new().with_ttl(Duration::from_secs(60));
+        store
+            .save(&mut req, &mut depot, &mut res, "token", "proof")
+            .await
+            .unwrap();
+        assert_eq!(
+            store.load(&mut req, &mut depot, &cipher).await,
+            Some(("token".into(), "proof".into()))
+        );
+    }
+
+    thread_local! {
+        static NOW: Cell<u64> = const { Cell::new(0) };
+    }
+
+    fn test_clock() -> u64 {
+        NOW.with(Cell::get)
+    }
+
+    #[tokio::test]
+    async fn test_session_store_rotation() {
+        use salvo_core::test::TestClient;
+
+        use crate::{Csrf, HeaderFinder};
+
+        let store = SessionStore::new()
+            .with_rotation(Duration::from_secs(60), Duration::from_secs(10))
+            .with_clock(test_clock);
+        let cipher = BcryptCipher::new().cost(4);
+        let mut req = Request::new();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+        depot.set_session(Session::new());
+
+        NOW.with(|now| now.set(1_000_000));
+        let (token, proof) = cipher.generate();
+        store
+            .save(&mut req, &mut depot, &mut res, &token, &proof)
+            .await
+            .unwrap();
+
+        let csrf = Csrf::new(
+            cipher.clone(),
+            store.clone(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+
+        NOW.with(|now| now.set(1_059_999));
+        assert!(!store.rotation_due(&req, &depot));
+        csrf.issue_token(&mut req, &mut depot, &mut res)
+            .await
+            .unwrap();
+        let pairs = store.load_all(&mut req, &mut depot, &cipher).await;
+        assert_eq!(pairs, [(token.clone(), proof.clone())]);
+
+        NOW.with(|now| now.set(1_060_000));
+        assert!(store.rotation_due(&req, &depot));
+        let pairs = store.load_all(&mut req, &mut depot, &cipher).await;
+        assert_eq!(pairs, [(token.clone(), proof.clone())]);
+        csrf.issue_token(&mut req, &mut depot, &mut res)
+            .await
+            .unwrap();
+        assert!(!store.rotation_due(&req, &depot));
+        let pairs = store.load_all(&mut req, &mut depot, &cipher).await;
+        assert_eq!(pairs.len(), 2);
+        let rotated = pairs[0].0.clone();
+        assert_ne!(rotated, token);
+        assert_eq!(pairs[1], (token.clone(), proof));
+        async fn accepted(
+            csrf: &Csrf<BcryptCipher, SessionStore>,
+            pairs: &[(String, String)],
+            token: &str,
+        ) -> bool {
+            let mut req = TestClient::post("http://127.0.0.1:5801")
+                .add_header("x-csrf-token", token, true)
+                .build();
+            csrf.validate(&mut req, &Depot::new(), pairs, &[])
+                .await
+                .is_ok()
+        }
+        NOW.with(|now| now.set(1_069_999));
+        let pairs = store.load_all(&mut req, &mut depot, &cipher).await;
+        assert_eq!(pairs[0].0, rotated);
+        assert!(accepted(&csrf, &pairs, &token).await);
+        assert!(accepted(&csrf, &pairs, &rotated).await);
+
+        NOW.with(|now| now.set(1_070_000));
+        let pairs = store.load_all(&mut req, &mut depot, &cipher).await;
+        assert_eq!(pairs.len(), 1);
+        assert!(!accepted(&csrf, &pairs, &token).await);
+        assert!(accepted(&csrf, &pairs, &rotated).await);
+    }
+
+    #[cfg(all(feature = "encrypted-store", feature = "binding"))]
+    #[tokio::test]
+    async fn test_session_store_rotation_is_bound_and_encrypted() {
+        use crate::{Csrf, EncryptedStore, HeaderFinder};
+
+        let store = EncryptedStore::new(
+            SessionStore::new()
+                .with_rotation(Duration::from_secs(60), Duration::from_secs(10))
+                .with_clock(test_clock),
+            [7; 32],
+        );
+        let csrf = Csrf::new(
+            BcryptCipher::new().cost(4),
+            store,
+            HeaderFinder::new("x-csrf-token"),
+        )
+        .with_binding([8; 32], |_| Some(b"alice".to_vec()));
+        let mut req = Request::new();
+        let mut res = Response::new();
+        let mut depot = Depot::new();
+        depot.set_session(Session::new());
+
+        NOW.with(|now| now.set(1_000_000));
+        let token = csrf
+            .issue_token(&mut req, &mut depot, &mut res)
+            .await
+            .unwrap();
+
+        NOW.with(|now| now.set(1_060_000));
+        let session = depot.session().unwrap().clone();
+        let mut depot = Depot::new();
+        depot.set_session(session);
+        let rotated = csrf
+            .issue_token(&mut req, &mut depot, &mut res)
+            .await
+            .unwrap();
+        assert_ne!(rotated, token);
+        let (_, sealed) = split_pair(
+            &depot
+                .session()
+                .unwrap()
+                .get::<String>("salvo.csrf")
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(!sealed.starts_with("$2") && !sealed.contains('~'));
+
+        // The rotated proof passes the binding check, so it is reused instead of replaced.
+        NOW.with(|now| now.set(1_060_001));
+        let session = depot.session().unwrap().clone();
+        let mut depot = Depot::new();
+        depot.set_session(session);
+        let reused = csrf
+            .issue_token(&mut req, &mut depot, &mut res)
+            .await
+            .unwrap();
+        assert_eq!(reused, rotated);
+    }
+}
@@ -0,0 +1,193 @@
+#[cfg(feature = "regex-skipper")]
+use regex::RegexSet;
+use salvo_core::handler::Skipper;
+use salvo_core::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use salvo_core::{Depot, Request};
+
+/// A [`Skipper`] that skips requests whose path matches any of the given patterns, e.g. to
+/// exempt webhook endpoints from CSRF validation.
+#[cfg(feature = "regex-skipper")]
+#[derive(Clone, Debug)]
+pub struct RegexSkipper {
+    patterns: RegexSet,
+    invert: bool,
+}
+
+#[cfg(feature = "regex-skipper")]
+impl RegexSkipper {
+    /// Create a new `RegexSkipper` from a single pattern.
+    #[inline]
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        Self::with_patterns([pattern])
+    }
+
+    /// Create a new `RegexSkipper` which skips when any of the patterns matches.
+    #[inline]
+    pub fn with_patterns<I, P>(patterns: I) -> Result<Self, regex::Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<str>,
+    {
+        Ok(Self {
+            patterns: RegexSet::new(patterns)?,
+            invert: false,
+        })
+    }
+
+    /// Skips requests whose path matches none of the patterns instead.
+    #[inline]
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+}
+
+#[cfg(feature = "regex-skipper")]
+impl Skipper for RegexSkipper {
+    fn skipped(&self, req: &mut Request, _depot: &Depot) -> bool {
+        self.patterns.is_match(req.uri().path()) != self.invert
+    }
+}
+
+/// A [`Skipper`] for API clients that are not susceptible to classic CSRF.
+///
+/// It skips requests with an `Authorization` header using the configured scheme, `Bearer` by
+/// default, since browsers never attach such headers on their own. Optionally it also skips
+/// requests whose content type can not be sent by a cross site form.
+#[derive(Clone, Debug)]
+pub struct BearerAuthSkipper {
+    scheme: String,
+    skip_non_form_content: bool,
+}
+impl Default for BearerAuthSkipper {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BearerAuthSkipper {
+    /// Create a new `BearerAuthSkipper` for the `Bearer` scheme.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            scheme: "Bearer".into(),
+            skip_non_form_content: false,
+        }
+    }
+
+    /// Sets the authorization scheme, compared case-insensitively.
+    #[inline]
+    pub fn with_scheme(mut self, scheme: impl Into<String>) -> Self {
+        self.scheme = scheme.into();
+        self
+    }
+
+    /// Also skips requests with a content type other than
+    /// `application/x-www-form-urlencoded`, `multipart/form-data` and `text/plain`, which are
+    /// the only ones a cross site form can send without a CORS preflight.
+    #[inline]
+    pub fn skip_non_form_content(mut self, skip: bool) -> Self {
+        self.skip_non_form_content = skip;
+        self
+    }
+
+    fn has_scheme(&self, req: &Request) -> bool {
+        req.headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))
+            .is_some_and(|(scheme, _)| scheme.eq_ignore_ascii_case(&self.scheme))
+    }
+
+    fn has_non_form_content(req: &Request) -> bool {
+        req.headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(';').next().unwrap_or_default().trim())
+            .is_some_and(|mime| {
+                ![
+                    "application/x-www-form-urlencoded",
+                    "multipart/form-data",
+                    "text/plain",
+                ]
+                .iter()
+                .any(|form| mime.eq_ignore_ascii_case(form))
+            })
+    }
+}
+
+impl Skipper for BearerAuthSkipper {
+    fn skipped(&self, req: &mut Request, _depot: &Depot) -> bool {
+        self.has_scheme(req) || (self.skip_non_form_content && Self::has_non_form_content(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::test::TestClient;
+
+    use super::*;
+
+    #[cfg(feature = "regex-skipper")]
+    #[test]
+    fn test_regex_skipper() {
+        let depot = Depot::new();
+        let skipper = RegexSkipper::with_patterns(["^/webhooks/.*", "^/health$"]).unwrap();
+        for (path, skipped) in [
+            ("/webhooks/stripe", true),
+            ("/health", true),
+            ("/api/pay", false),
+            ("/api/webhooks/stripe", false),
+        ] {
+            let mut req = TestClient::post(format!("http://127.0.0.1:5801{path}")).build();
+            assert_eq!(skipper.skipped(&mut req, &depot), skipped, "{path}");
+            let skipper = skipper.clone().invert(true);
+            assert_eq!(skipper.skipped(&mut req, &depot), !skipped, "{path}");
+        }
+        assert!(RegexSkipper::new("(").is_err());
+    }
+
+    #[test]
+    fn test_bearer_auth_skipper() {
+        let depot = Depot::new();
+        let skipper = BearerAuthSkipper::new();
+        for (authorization, skipped) in [
+            (Some("Bearer abc"), true),
+            (Some("bearer abc"), true),
+            (Some("Basic YWxhZGRpbjpvcGVuc2VzYW1l"), false),
+            (Some("Bearer"), false),
+            (None, false),
+        ] {
+            let mut req = TestClient::post("http://127.0.0.1:5801");
+            if let Some(authorization) = authorization {
+                req = req.add_header("authorization", authorization, true);
+            }
+            let mut req = req.build();
+            assert_eq!(
+                skipper.skipped(&mut req, &depot),
+                skipped,
+                "{authorization:?}"
+            );
+        }
+
+        let skipper = BearerAuthSkipper::new().with_scheme("Token");
+        let mut req = TestClient::post("http://127.0.0.1:5801")
+            .add_header("authorization", "Token abc", true)
+            .build();
+        assert!(skipper.skipped(&mut req, &depot));
+
+        let skipper = BearerAuthSkipper::new().skip_non_form_content(true);
+        for (content_type, skipped) in [
+            ("application/json", true),
+            ("application/x-www-form-urlencoded", false),
+            ("multipart/form-data; boundary=x", false),
+            ("text/plain;charset=utf-8", false),
+        ] {
+            let mut req = TestClient::post("http://127.0.0.1:5801")
+                .add_header("content-type", content_type, true)
+                .build();
+            assert_eq!(skipper.skipped(&mut req, &depot), skipped, "{content_type}");
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use std::time::Duration;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use cookie::{Cookie, SameSite};
+use rand::RngCore;
+use salvo_core::http::uri::Scheme;
+use salvo_core::{Depot, Request, Response};
+use sled::Tree;
+
+use super::sweep::{now_millis, Sweeper, DEFAULT_TTL};
+use super::{CsrfCipher, CsrfStore, NamespaceError};
+
+/// Error type for [`SledStore`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum SledStoreError {
+    /// The sled database failed.
+    #[error("sled error: {0}")]
+    Sled(#[from] sled::Error),
+}
+
+/// A `CsrfStore` implementation that stores the CSRF proof in a [`sled`] tree.
+///
+/// Proofs are keyed by a random id sent to the client in a cookie, so they survive restarts
+/// of single binary deployments without a session or an external database. They expire after
+/// the [ttl](Self::with_ttl), and expired proofs are removed while saving, at most once per
+/// [sweep interval](Self::with_sweep_interval).
+///
+/// Loading and saving a proof read and write the tree on the async worker, sled serves these
+/// single key operations from its cache. The sweep scans the whole tree and runs on the
+/// blocking thread pool.
+#[derive(Clone, Debug)]
+pub struct SledStore {
+    tree: Tree,
+    cookie_name: String,
+    ttl: Duration,
+    sweeper: Sweeper,
+}
+
+impl SledStore {
+    /// Create a new `SledStore` storing proofs in `tree`.
+    pub fn new(tree: Tree) -> Self {
+        Self {
+            tree,
+            cookie_name: "salvo.csrf.id".into(),
+            ttl: DEFAULT_TTL,
+            sweeper: Sweeper::new(),
+        }
+    }
+
+    /// Sets the name of the cookie holding the id, defaults to `salvo.csrf.id`.
+    pub fn cookie_name(mut self, name: impl Into<String>) -> Self {
+        self.cookie_name = name.into();
+        self
+    }
+
+    /// Sets the ttl of the stored proof, an expired proof is treated as absent. Defaults to
+    /// one day.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets the minimum interval between two removals of the expired proofs, defaults to one
+    /// minute.
+    pub fn with_sweep_interval(mut self, interval: Duration) -> Self {
+        self.sweeper.interval = interval;
+        self
+    }
+
+    /// Removes the expired proofs from the tree.
+    ///
+    /// This blocks while scanning the whole tree, call it from a blocking context, e.g. with
+    /// `tokio::task::spawn_blocking`.
+    pub fn delete_expired(&self) -> Result<(), SledStoreError> {
+        let now = now_millis();
+        for entry in self.tree.iter() {
+            let (id, entry) = entry?;
+            // `Option::is_none_or` needs Rust 1.82.
+            #[allow(clippy::unnecessary_map_or)]
+            if decode_entry(&entry).map_or(true, |(expires_at, _, _)| expires_at <= now) {
+                self.tree.remove(id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The id of the current client, preferring one already set on the response.
+    fn id(&self, req: &Request, res: &Response) -> Option<String> {
+        res.cookie(&self.cookie_name)
+            .or_else(|| req.cookie(&self.cookie_name))
+            .map(|cookie| cookie.value().to_owned())
+            .filter(|id| !id.is_empty())
+    }
+}
+
+/// Entries are the expiry in milliseconds followed by `token.proof`.
+fn encode_entry(expires_at: u64, token: &str, proof: &str) -> Vec<u8> {
+    let mut entry = expires_at.to_be_bytes().to_vec();
+    entry.extend_from_slice(format!("{token}.{proof}").as_bytes());
+    entry
+}
+
+fn decode_entry(entry: &[u8]) -> Option<(u64, String, String)> {
+    let (expires_at, pair) = entry.split_at_checked(8)?;
+    let expires_at = u64::from_be_bytes(expires_at.try_into().ok()?);
+    let (token, proof) = std::str::from_utf8(pair).ok()?.split_once('.')?;
+    Some((expires_at, token.into(), proof.into()))
+}
+
+impl CsrfStore for SledStore {
+    type Error = SledStoreError;
+    async fn load<C: CsrfCipher>(
+        &self,
+        req: &mut Request,
+        _depot: &mut Depot,
+        _cipher: &C,
+    ) -> Option<(String, String)> {
+        let id = req.cookie(&self.cookie_name)?.value().to_owned();
+        let entry = match self.tree.get(&id) {
+            Ok(entry) => entry?,
+            Err(e) => {
+                tracing::error!(error = ?e, "failed to load csrf proof from sled");
+                return None;
+            }
+        };
+        let (expires_at, token, proof) = decode_entry(&entry)?;
+        if expires_at <= now_millis() {
+            if let Err(e) = self.tree.remove(&id) {
+                tracing::error!(error = ?e, "failed to remove expired csrf proof from sled");
+            }
+            return None;
+        }
+        Some((token, proof))
+    }
+    fn namespace(&mut self, prefix: &str) -> Result<(), NamespaceError> {
+        self.cookie_name = format!("{prefix}.{}", self.cookie_name);
+        Ok(())
+    }
+    async fn save(
+        &self,
+        req: &mut Request,
+        _depot: &mut Depot,
+        res: &mut Response,
+        token: &str,
+        proof: &str,
+    ) -> Result<(), Self::Error> {
+        let id = match self.id(req, res) {
+            Some(id) => id,
+            None => {
+                let mut id = [0; 16];
+                rand::thread_rng().fill_bytes(&mut id);
+                let id = URL_SAFE_NO_PAD.encode(id);
+                let cookie = Cookie::build((self.cookie_name.clone(), id.clone()))
+                    .http_only(true)
+                    .same_site(SameSite::Strict)
+                    .path("/")
+                    .secure(req.uri().scheme() == Some(&Scheme::HTTPS))
+                    .build();
+                res.add_cookie(cookie);
+                id
+            }
+        };
+        let expires_at = now_millis() + self.ttl.as_millis() as u64;
+        self.tree
+            .insert(id, encode_entry(expires_at, token, proof))?;
+        if self.sweeper.due() {
+            let store = self.clone();
+            match tokio::task::spawn_blocking(move || store.delete_expired()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    tracing::error!(error = ?e, "failed to remove expired csrf proofs from sled");
+                }
+                Err(e) => {
+                    tracing::error!(error = ?e, "csrf proof sweep task failed");
+                }
+            }
+        }
+        Ok(())
+    }
+    async fn clear(
+        &self,
+        req: &mut Request,
+        _depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<(), Self::Error> {
+        if let Some(id) = self.id(req, res) {
+            self.tree.remove(id)?;
+        }
+        let mut cookie = Cookie::build((self.cookie_name.clone(), ""))
+            .path("/")
+            .build();
+        cookie.make_removal();
+        res.add_cookie(cookie);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::test::TestClient;
+
+    use super::*;
+    use crate::BcryptCipher;
+
+    fn tree() -> Tree {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        db.open_tree("csrf").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sled_store_round_trip() {
+        let store = SledStore::new(tree());
+        let cipher = BcryptCipher::new();
+        let mut req = Request::new();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+
+        store
+            .save(&mut req, &mut depot, &mut res, "token", "proof")
+            .await
+            .unwrap();
+        let id = res.cookie("salvo.csrf.id").unwrap().to_string();
+        assert_eq!(store.tree.len(), 1);
+
+        let mut req = TestClient::get("http://127.0.0.1:5801")
+            .add_header("cookie", id, true)
+            .build();
+        let loaded = store.load(&mut req, &mut depot, &cipher).await;
+        assert_eq!(loaded, Some(("token".into(), "proof".into())));
+
+        let mut req = Request::new();
+        assert_eq!(store.load(&mut req, &mut depot, &cipher).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_sled_store_expired_proof() {
+        let store = SledStore::new(tree()).with_ttl(Duration::from_millis(1));
+        let cipher = BcryptCipher::new();
+        let mut req = Request::new();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+
+        store
+            .save(&mut req, &mut depot, &mut res, "token", "proof")
+            .await
+            .unwrap();
+        let id = res.cookie("salvo.csrf.id").unwrap().to_string();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        let mut req = TestClient::get("http://127.0.0.1:5801")
+            .add_header("cookie", id, true)
+            .build();
+        assert_eq!(store.load(&mut req, &mut depot, &cipher).await, None);
+        assert!(store.tree.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sled_store_sweeps_expired_proofs() {
+        let store = SledStore::new(tree())
+            .with_ttl(Duration::from_millis(1))
+            .with_sweep_interval(Duration::ZERO);
+        let mut depot = Depot::new();
+        store
+            .save(
+                &mut Request::new(),
+                &mut depot,
+                &mut Response::new(),
+                "token",
+                "proof",
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        let store = store.with_ttl(Duration::from_secs(60));
+        store
+            .save(
+                &mut Request::new(),
+                &mut depot,
+                &mut Response::new(),
+                "token2",
+                "proof2",
+            )
+            .await
+            .unwrap();
+        assert_eq!(store.tree.len(), 1);
+        let (_, entry) = store.tree.first().unwrap().unwrap();
+        assert_eq!(decode_entry(&entry).unwrap().1, "token2");
+    }
+
+    #[tokio::test]
+    async fn test_sled_store_default_ttl() {
+        let store = SledStore::new(tree());
+        let before = now_millis();
+        store
+            .save(
+                &mut Request::new(),
+                &mut Depot::new(),
+                &mut Response::new(),
+                "token",
+                "proof",
+            )
+            .await
+            .unwrap();
+        let (_, entry) = store.tree.first().unwrap().unwrap();
+        let (expires_at, _, _) = decode_entry(&entry).unwrap();
+        assert!(expires_at >= before + DEFAULT_TTL.as_millis() as u64);
+        assert!(expires_at <= now_millis() + DEFAULT_TTL.as_millis() as u64);
+    }
+}
@@ -0,0 +1,468 @@
+use std::fmt;
+use std::time::Duration;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use cookie::{Cookie, SameSite};
+use rand::RngCore;
+use salvo_core::http::uri::Scheme;
+use salvo_core::{Depot, Request, Response};
+use sqlx::{ColumnIndex, Database, Decode, Encode, Executor, IntoArguments, Pool, Row, Type};
+
+use super::sweep::{Sweeper, DEFAULT_TTL};
+use super::{CsrfCipher, CsrfStore, NamespaceError};
+
+/// Error type for [`SqlxStore`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum SqlxStoreError {
+    /// The database query failed.
+    #[error("sqlx error: {0}")]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// A `CsrfStore` implementation that stores the CSRF proof in a SQL table with [`sqlx`].
+///
+/// Proofs are keyed by a random id sent to the client in a cookie. PostgreSQL, MySQL and
+/// SQLite pools are supported, enable the driver in your own `sqlx` dependency. Create the
+/// table before use, e.g.:
+///
+/// ```sql
+/// CREATE TABLE salvo_csrf (
+///     id VARCHAR(64) PRIMARY KEY,
+///     secret TEXT NOT NULL,
+///     expires_at BIGINT NOT NULL
+/// );
+/// ```
+///
+/// `expires_at` is in milliseconds since the Unix epoch, proofs expire after the
+/// [ttl](Self::with_ttl). Expired rows are ignored, and deleted while saving, at most once per
+/// [sweep interval](Self::with_sweep_interval), or with [`delete_expired`](Self::delete_expired).
+pub struct SqlxStore<DB: Database> {
+    pool: Pool<DB>,
+    table: String,
+    id_column: String,
+    secret_column: String,
+    expires_column: String,
+    cookie_name: String,
+    ttl: Duration,
+    sweeper: Sweeper,
+}
+
+impl<DB: Database> Clone for SqlxStore<DB> {
+    fn clone(&self) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            table: self.table.clone(),
+            id_column: self.id_column.clone(),
+            secret_column: self.secret_column.clone(),
+            expires_column: self.expires_column.clone(),
+            cookie_name: self.cookie_name.clone(),
+            ttl: self.ttl,
+            sweeper: self.sweeper.clone(),
+        }
+    }
+}
+
+impl<DB: Database> fmt::Debug for SqlxStore<DB> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SqlxStore")
+            .field("database", &DB::NAME)
+            .field("table", &self.table)
+            .field("id_column", &self.id_column)
+            .field("secret_column", &self.secret_column)
+            .field("expires_column", &self.expires_column)
+            .field("cookie_name", &self.cookie_name)
+            .field("ttl", &self.ttl)
+            .field("sweep_interval", &self.sweeper.interval)
+            .finish()
+    }
+}
+
+impl<DB: Database> SqlxStore<DB> {
+    /// Create a new `SqlxStore` storing proofs in the `salvo_csrf` table of `pool`.
+    pub fn new(pool: Pool<DB>) -> Self {
+        Self {
+            pool,
+            table: "salvo_csrf".into(),
+            id_column: "id".into(),
+            secret_column: "secret".into(),
+            expires_column: "expires_at".into(),
+            cookie_name: "salvo.csrf.id".into(),
+            ttl: DEFAULT_TTL,
+            sweeper: Sweeper::new(),
+        }
+    }
+
+    /// Sets the table name, defaults to `salvo_csrf`.
+    ///
+    /// The name is written into the queries as is, it must not come from untrusted input.
+    pub fn with_table(mut self, table: impl Into<String>) -> Self {
+        self.table = table.into();
+        self
+    }
+
+    /// Sets the names of the id, secret and expiry columns, defaults to `id`, `secret` and
+    /// `expires_at`.
+    ///
+    /// The names are written into the queries as is, they must not come from untrusted input.
+    pub fn with_columns(
+        mut self,
+        id: impl Into<String>,
+        secret: impl Into<String>,
+        expires_at: impl Into<String>,
+    ) -> Self {
+        self.id_column = id.into();
+        self.secret_column = secret.into();
+        self.expires_column = expires_at.into();
+        self
+    }
+
+    /// Sets the name of the cookie holding the id, defaults to `salvo.csrf.id`.
+    pub fn cookie_name(mut self, name: impl Into<String>) -> Self {
+        self.cookie_name = name.into();
+        self
+    }
+
+    /// Sets the ttl of the stored proof, an expired proof is treated as absent. Defaults to
+    /// one day.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets the minimum interval between two deletions of the expired rows, defaults to one
+    /// minute.
+    pub fn with_sweep_interval(mut self, interval: Duration) -> Self {
+        self.sweeper.interval = interval;
+        self
+    }
+
+    /// The `n`th bind parameter, PostgreSQL numbers them.
+    fn param(n: usize) -> String {
+        if DB::NAME == "PostgreSQL" {
+            format!("${n}")
+        } else {
+            "?".into()
+        }
+    }
+
+    fn select_sql(&self) -> String {
+        format!(
+            "SELECT {secret} FROM {table} WHERE {id} = {p1} AND {expires} > {p2}",
+            secret = self.secret_column,
+            table = self.table,
+            id = self.id_column,
+            expires = self.expires_column,
+            p1 = Self::param(1),
+            p2 = Self::param(2),
+        )
+    }
+
+    fn upsert_sql(&self) -> String {
+        let (id, secret, expires) = (&self.id_column, &self.secret_column, &self.expires_column);
+        let update = if DB::NAME == "MySQL" {
+            format!("ON DUPLICATE KEY UPDATE {secret} = VALUES({secret}), {expires} = VALUES({expires})")
+        } else {
+            format!(
+                "ON CONFLICT ({id}) DO UPDATE SET {secret} = excluded.{secret}, {expires} = excluded.{expires}"
+            )
+        };
+        format!(
+            "INSERT INTO {table} ({id}, {secret}, {expires}) VALUES ({p1}, {p2}, {p3}) {update}",
+            table = self.table,
+            p1 = Self::param(1),
+            p2 = Self::param(2),
+            p3 = Self::param(3),
+        )
+    }
+
+    fn delete_sql(&self) -> String {
+        format!(
+            "DELETE FROM {} WHERE {} = {}",
+            self.table,
+            self.id_column,
+            Self::param(1)
+        )
+    }
+
+    /// The id of the current client, preferring one already set on the response.
+    fn id(&self, req: &Request, res: &Response) -> Option<String> {
+        res.cookie(&self.cookie_name)
+            .or_else(|| req.cookie(&self.cookie_name))
+            .map(|cookie| cookie.value().to_owned())
+            .filter(|id| !id.is_empty())
+    }
+}
+
+impl<DB> SqlxStore<DB>
+where
+    DB: Database,
+    for<'c> &'c Pool<DB>: Executor<'c, Database = DB>,
+    for<'q> <DB as Database>::Arguments<'q>: IntoArguments<'q, DB>,
+    for<'q> i64: Encode<'q, DB> + Type<DB>,
+{
+    /// Removes the expired proofs from the table.
+    pub async fn delete_expired(&self) -> Result<(), SqlxStoreError> {
+        let sql = format!(
+            "DELETE FROM {table} WHERE {expires} <= {p1}",
+            table = self.table,
+            expires = self.expires_column,
+            p1 = Self::param(1),
+        );
+        sqlx::query(&sql)
+            .bind(now_millis())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+fn now_millis() -> i64 {
+    super::sweep::now_millis() as i64
+}
+
+impl<DB> CsrfStore for SqlxStore<DB>
+where
+    DB: Database,
+    for<'c> &'c Pool<DB>: Executor<'c, Database = DB>,
+    for<'q> <DB as Database>::Arguments<'q>: IntoArguments<'q, DB>,
+    for<'q> String: Encode<'q, DB> + Decode<'q, DB> + Type<DB>,
+    for<'q> i64: Encode<'q, DB> + Type<DB>,
+    usize: ColumnIndex<DB::Row>,
+{
+    type Error = SqlxStoreError;
+    async fn load<C: CsrfCipher>(
+        &self,
+        req: &mut Request,
+        _depot: &mut Depot,
+        _cipher: &C,
+    ) -> Option<(String, String)> {
+        let id = req.cookie(&self.cookie_name)?.value().to_owned();
+        let row = sqlx::query(&self.select_sql())
+            .bind(id)
+            .bind(now_millis())
+            .fetch_optional(&self.pool)
+            .await;
+        let secret = match row {
+            Ok(row) => row?.try_get::<String, _>(0).ok()?,
+            Err(e) => {
+                tracing::error!(error = ?e, "failed to load csrf proof from database");
+                return None;
+            }
+        };
+        secret
+            .split_once('.')
+            .map(|(token, proof)| (token.into(), proof.into()))
+    }
+    fn namespace(&mut self, prefix: &str) -> Result<(), NamespaceError> {
+        self.cookie_name = format!("{prefix}.{}", self.cookie_name);
+        Ok(())
+    }
+    async fn save(
+        &self,
+        req: &mut Request,
+        _depot: &mut Depot,
+        res: &mut Response,
+        token: &str,
+        proof: &str,
+    ) -> Result<(), Self::Error> {
+        let id = match self.id(req, res) {
+            Some(id) => id,
+            None => {
+                let mut id = [0; 16];
+                rand::thread_rng().fill_bytes(&mut id);
+                let id = URL_SAFE_NO_PAD.encode(id);
+                let cookie = Cookie::build((self.cookie_name.clone(), id.clone()))
+                    .http_only(true)
+                    .same_site(SameSite::Strict)
+                    .path("/")
+                    .secure(req.uri().scheme() == Some(&Scheme::HTTPS))
+                    .build();
+                res.add_cookie(cookie);
+                id
+            }
+        };
+        let expires_at = now_millis() + self.ttl.as_millis() as i64;
+        sqlx::query(&self.upsert_sql())
+            .bind(id)
+            .bind(format!("{token}.{proof}"))
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+        if self.sweeper.due() {
+            if let Err(e) = self.delete_expired().await {
+                tracing::error!(error = ?e, "failed to delete expired csrf proofs from database");
+            }
+        }
+        Ok(())
+    }
+    async fn clear(
+        &self,
+        req: &mut Request,
+        _depot: &mut Depot,
+        res: &mut Response,
+    ) -> Result<(), Self::Error> {
+        if let Some(id) = self.id(req, res) {
+            sqlx::query(&self.delete_sql())
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+        }
+        let mut cookie = Cookie::build((self.cookie_name.clone(), ""))
+            .path("/")
+            .build();
+        cookie.make_removal();
+        res.add_cookie(cookie);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::test::TestClient;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use sqlx::Sqlite;
+
+    use super::*;
+    use crate::BcryptCipher;
+
+    async fn store() -> SqlxStore<Sqlite> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query(
+            "CREATE TABLE csrf (cid TEXT PRIMARY KEY, value TEXT NOT NULL, expires BIGINT NOT NULL)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        SqlxStore::new(pool)
+            .with_table("csrf")
+            .with_columns("cid", "value", "expires")
+    }
+
+    fn request_with_id(res: &Response) -> Request {
+        let id = res.cookie("salvo.csrf.id").unwrap().to_string();
+        TestClient::get("http://127.0.0.1:5801")
+            .add_header("cookie", id, true)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_sqlx_store_round_trip() {
+        let store = store().await;
+        let cipher = BcryptCipher::new();
+        let mut req = Request::new();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+
+        store
+            .save(&mut req, &mut depot, &mut res, "token", "proof")
+            .await
+            .unwrap();
+        store
+            .save(&mut req, &mut depot, &mut res, "token2", "proof2")
+            .await
+            .unwrap();
+
+        let mut req = request_with_id(&res);
+        let loaded = store.load(&mut req, &mut depot, &cipher).await;
+        assert_eq!(loaded, Some(("token2".into(), "proof2".into())));
+
+        store.clear(&mut req, &mut depot, &mut res).await.unwrap();
+        let mut req = request_with_id(&res);
+        assert_eq!(store.load(&mut req, &mut depot, &cipher).await, None);
+
+        let mut req = Request::new();
+        assert_eq!(store.load(&mut req, &mut depot, &cipher).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_sqlx_store_expired_proof() {
+        let store = store().await.with_ttl(Duration::from_millis(1));
+        let cipher = BcryptCipher::new();
+        let mut req = Request::new();
+        let mut depot = Depot::new();
+        let mut res = Response::new();
+
+        store
+            .save(&mut req, &mut depot, &mut res, "token", "proof")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        let mut req = request_with_id(&res);
+        assert_eq!(store.load(&mut req, &mut depot, &cipher).await, None);
+        store.delete_expired().await.unwrap();
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM csrf")
+            .fetch_one(&store.pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_sqlx_store_sweeps_expired_rows() {
+        let store = store()
+            .await
+            .with_ttl(Duration::from_millis(1))
+            .with_sweep_interval(Duration::ZERO);
+        let mut depot = Depot::new();
+        store
+            .save(
+                &mut Request::new(),
+                &mut depot,
+                &mut Response::new(),
+                "token",
+                "proof",
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        let before = now_millis();
+        let store = store.with_ttl(Duration::from_secs(60));
+        store
+            .save(
+                &mut Request::new(),
+                &mut depot,
+                &mut Response::new(),
+                "token2",
+                "proof2",
+            )
+            .await
+            .unwrap();
+        let rows: Vec<(String, i64)> = sqlx::query_as("SELECT value, expires FROM csrf")
+            .fetch_all(&store.pool)
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].0, "token2.proof2");
+        assert!(rows[0].1 >= before + 60_000);
+    }
+
+    #[tokio::test]
+    async fn test_sqlx_store_default_ttl() {
+        let store = store().await;
+        let before = now_millis();
+        store
+            .save(
+                &mut Request::new(),
+                &mut Depot::new(),
+                &mut Response::new(),
+                "token",
+                "proof",
+            )
+            .await
+            .unwrap();
+        let (expires_at,): (i64,) = sqlx::query_as("SELECT expires FROM csrf")
+            .fetch_one(&store.pool)
+            .await
+            .unwrap();
+        let ttl = DEFAULT_TTL.as_millis() as i64;
+        assert!(expires_at >= before + ttl && expires_at <= now_millis() + ttl);
+    }
+}
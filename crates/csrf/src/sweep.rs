@@ -0,0 +1,44 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Default ttl of the proofs kept by the database stores.
+pub(crate) const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default interval between two sweeps of expired proofs.
+pub(crate) const DEFAULT_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Decides when a store removes its expired proofs, at most once per interval across the
+/// clones of the store.
+#[derive(Clone, Debug)]
+pub(crate) struct Sweeper {
+    pub(crate) interval: Duration,
+    last: Arc<AtomicU64>,
+}
+
+impl Sweeper {
+    pub(crate) fn new() -> Self {
+        Self {
+            interval: DEFAULT_SWEEP_INTERVAL,
+            last: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Returns whether a sweep is due, only one of concurrent callers gets `true`.
+    pub(crate) fn due(&self) -> bool {
+        let now = now_millis();
+        let last = self.last.load(Ordering::Relaxed);
+        now >= last.saturating_add(self.interval.as_millis() as u64)
+            && self
+                .last
+                .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+    }
+}
@@ -0,0 +1,123 @@
+use std::convert::Infallible;
+
+use salvo_core::{Depot, Request, Response};
+
+use super::{CsrfCipher, CsrfStore};
+
+/// A `CsrfCipher` that accepts every token, for testing handlers behind [`Csrf`](crate::Csrf).
+///
+/// **Never use this in production**, it disables CSRF protection entirely.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopCipher;
+
+impl NoopCipher {
+    /// Create a new `NoopCipher`.
+    #[inline]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl CsrfCipher for NoopCipher {
+    fn verify(&self, _token: &str, _proof: &str) -> bool {
+        true
+    }
+    fn generate(&self) -> (String, String) {
+        ("noop-token".into(), "noop-proof".into())
+    }
+}
+
+/// A `CsrfStore` that always loads the same proof and ignores saves, for testing handlers
+/// behind [`Csrf`](crate::Csrf).
+///
+/// **Never use this in production**, every client shares the same proof.
+#[derive(Clone, Debug)]
+pub struct StaticSecretStore {
+    token: String,
+    proof: String,
+}
+impl Default for StaticSecretStore {
+    #[inline]
+    fn default() -> Self {
+        Self::new("static-token", "static-proof")
+    }
+}
+
+impl StaticSecretStore {
+    /// Create a new `StaticSecretStore` that always loads the given token and proof.
+    #[inline]
+    pub fn new(token: impl Into<String>, proof: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+            proof: proof.into(),
+        }
+    }
+}
+
+impl CsrfStore for StaticSecretStore {
+    type Error = Infallible;
+    async fn load<C: CsrfCipher>(
+        &self,
+        _req: &mut Request,
+        _depot: &mut Depot,
+        _cipher: &C,
+    ) -> Option<(String, String)> {
+        Some((self.token.clone(), self.proof.clone()))
+    }
+    async fn save(
+        &self,
+        _req: &mut Request,
+        _depot: &mut Depot,
+        _res: &mut Response,
+        _token: &str,
+        _proof: &str,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use salvo_core::prelude::*;
+    use salvo_core::test::{ResponseExt, TestClient};
+
+    use super::*;
+    use crate::{Csrf, CsrfDepotExt, HeaderFinder};
+
+    #[handler]
+    async fn get_index(depot: &mut Depot) -> String {
+        depot.csrf_token().unwrap().to_owned()
+    }
+    #[handler]
+    async fn post_index() -> &'static str {
+        "POST"
+    }
+
+    #[tokio::test]
+    async fn test_noop_cipher_and_static_store() {
+        let csrf = Csrf::new(
+            NoopCipher::new(),
+            StaticSecretStore::default(),
+            HeaderFinder::new("x-csrf-token"),
+        );
+        let router = Router::new().hoop(csrf).get(get_index).post(post_index);
+        let service = Service::new(router);
+
+        let mut res = TestClient::get("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.take_string().await.unwrap(), "static-token");
+
+        let mut res = TestClient::post("http://127.0.0.1:5801")
+            .add_header("x-csrf-token", "anything", true)
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::OK);
+        assert_eq!(res.take_string().await.unwrap(), "POST");
+
+        let res = TestClient::post("http://127.0.0.1:5801")
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code.unwrap(), StatusCode::FORBIDDEN);
+    }
+}
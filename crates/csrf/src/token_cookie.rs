@@ -0,0 +1,84 @@
+use cookie::{Cookie, SameSite};
+
+/// Attributes of the cookie the current token is written to, readable from JavaScript, see
+/// [`Csrf::with_token_cookie`](crate::Csrf::with_token_cookie).
+///
+/// They are configured independently from the cookie of [`CookieStore`](crate::CookieStore),
+/// which holds the proof. The cookie defaults to `SameSite=Strict` and path `/`. With
+/// `SameSite=None`, the cookie is always marked `Secure`, as browsers reject it otherwise.
+#[derive(Clone, Debug)]
+pub struct TokenCookie {
+    name: String,
+    path: String,
+    domain: Option<String>,
+    same_site: SameSite,
+}
+
+impl TokenCookie {
+    /// Create a new `TokenCookie` named `name`, e.g. `XSRF-TOKEN`.
+    #[inline]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            path: "/".into(),
+            domain: None,
+            same_site: SameSite::Strict,
+        }
+    }
+
+    /// Sets cookie path.
+    #[inline]
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Sets cookie domain.
+    #[inline]
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// Sets the `SameSite` attribute of the cookie.
+    #[inline]
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    /// Get cookie name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn prefixed(mut self, prefix: &str) -> Self {
+        self.name = format!("{prefix}.{}", self.name);
+        self
+    }
+
+    pub(crate) fn build(&self, value: String, https: bool) -> Cookie<'static> {
+        let mut cookie = Cookie::build((self.name.clone(), value))
+            .http_only(false)
+            .same_site(self.same_site)
+            .path(self.path.clone())
+            .secure(https || self.same_site == SameSite::None)
+            .build();
+        if let Some(domain) = &self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        cookie
+    }
+
+    pub(crate) fn removal(&self) -> Cookie<'static> {
+        let mut cookie = Cookie::build((self.name.clone(), ""))
+            .path(self.path.clone())
+            .build();
+        if let Some(domain) = &self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        cookie.make_removal();
+        cookie
+    }
+}
@@ -0,0 +1,144 @@
+use aead::generic_array::GenericArray;
+use aead::KeyInit;
+use chacha20poly1305::XChaCha20Poly1305;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::ZeroizeOnDrop;
+
+use super::{aead_proof, CsrfCipher, CsrfVerifyError};
+
+const NONCE_LEN: usize = 24;
+
+/// XCcpCipher is a CSRF protection implementation that uses [`XChaCha20Poly1305`](https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-xchacha).
+///
+/// Its 192-bit random nonce makes nonce collisions negligible, even when issuing a very
+/// large number of tokens with the same key.
+///
+/// The key material is wiped from memory when the cipher is dropped.
+#[derive(Clone)]
+pub struct XCcpCipher {
+    aead: XChaCha20Poly1305,
+    token_size: usize,
+}
+
+// `chacha20poly1305` always wipes its key on drop, the `xccp-cipher` feature needs no extra
+// `zeroize` feature for it.
+impl ZeroizeOnDrop for XCcpCipher {}
+
+impl XCcpCipher {
+    /// Given an aead key, return an `XCcpCipher` instance.
+    #[inline]
+    pub fn new(aead_key: [u8; 32]) -> Self {
+        Self {
+            aead: XChaCha20Poly1305::new(&GenericArray::from(aead_key)),
+            token_size: 32,
+        }
+    }
+
+    /// Create an `XCcpCipher` with a key derived from `passphrase` and `salt` with HKDF-SHA256.
+    ///
+    /// The passphrase must be a long random secret, e.g. read from an environment variable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `passphrase` is shorter than 32 bytes.
+    #[inline]
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Self {
+        Self::new(crate::kdf::derive_key(
+            passphrase,
+            salt,
+            b"salvo-csrf xccp-cipher",
+        ))
+    }
+
+    /// Sets the length of the token.
+    #[inline]
+    pub fn token_size(mut self, token_size: usize) -> Self {
+        assert!(token_size >= 8, "length must be larger than 8");
+        self.token_size = token_size;
+        self
+    }
+}
+
+impl CsrfCipher for XCcpCipher {
+    fn verify(&self, token: &str, proof: &str) -> bool {
+        self.verify_with_aad(token, proof, b"")
+    }
+    fn supports_aad(&self) -> bool {
+        true
+    }
+    fn verify_with_aad(&self, token: &str, proof: &str, aad: &[u8]) -> bool {
+        aead_proof::open(&self.aead, NONCE_LEN, token, proof, aad).is_ok()
+    }
+    fn verify_detailed(&self, token: &str, proof: &str) -> Result<(), CsrfVerifyError> {
+        aead_proof::open(&self.aead, NONCE_LEN, token, proof, b"")
+    }
+    fn generate(&self) -> (String, String) {
+        aead_proof::seal(&self.aead, NONCE_LEN, &mut OsRng, self.token_size, b"")
+    }
+    fn generate_with_rng(&self, rng: &mut dyn RngCore) -> (String, String) {
+        aead_proof::seal(&self.aead, NONCE_LEN, rng, self.token_size, b"")
+    }
+    fn generate_with_aad(&self, aad: &[u8]) -> (String, String) {
+        aead_proof::seal(&self.aead, NONCE_LEN, &mut OsRng, self.token_size, aad)
+    }
+    fn random_bytes(&self, len: usize) -> Vec<u8> {
+        self.random_bytes_with_rng(&mut OsRng, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+
+    use super::*;
+    use crate::aead_proof::TAG_LEN;
+
+    #[test]
+    fn test_xccp_cipher() {
+        let cipher = XCcpCipher::new(*b"01234567012345670123456701234567");
+
+        let (token, proof) = cipher.generate();
+        assert!(cipher.verify(&token, &proof));
+        assert_eq!(
+            URL_SAFE_NO_PAD.decode(&proof).unwrap().len(),
+            NONCE_LEN + 32 + TAG_LEN
+        );
+
+        let invalid_token = URL_SAFE_NO_PAD.encode(vec![0u8; 32]);
+        assert!(!cipher.verify(&invalid_token, &proof));
+    }
+
+    #[test]
+    fn test_xccp_cipher_wrong_key() {
+        let cipher = XCcpCipher::new(*b"01234567012345670123456701234567");
+        let other = XCcpCipher::new(*b"76543210765432107654321076543210");
+
+        let (token, proof) = cipher.generate();
+        assert!(!other.verify(&token, &proof));
+    }
+
+    #[test]
+    fn test_custom_token_size() {
+        let cipher = XCcpCipher::new([0u8; 32]).token_size(64);
+        let (token, proof) = cipher.generate();
+        assert_eq!(URL_SAFE_NO_PAD.decode(&token).unwrap().len(), 64);
+        assert!(cipher.verify(&token, &proof));
+    }
+
+    #[test]
+    #[should_panic(expected = "length must be larger than 8")]
+    fn test_too_short_token_size() {
+        XCcpCipher::new([0u8; 32]).token_size(4);
+    }
+
+    #[test]
+    fn test_from_passphrase() {
+        let passphrase = "0123456789abcdef0123456789abcdef";
+        let cipher = XCcpCipher::from_passphrase(passphrase, b"salt");
+        let (token, proof) = cipher.generate();
+        assert!(XCcpCipher::from_passphrase(passphrase, b"salt").verify(&token, &proof));
+        assert!(!XCcpCipher::from_passphrase(passphrase, b"pepper").verify(&token, &proof));
+    }
+}
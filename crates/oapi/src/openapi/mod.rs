@@ -514,6 +514,12 @@ impl OpenApi {
                     }
                 }
                 self.components.append(&mut components);
+                self.components.security_schemes.extend(
+                    node.metadata
+                        .security_schemes
+                        .iter()
+                        .map(|(name, scheme)| (name.clone(), scheme.clone())),
+                );
             }
         }
         for child in &mut node.children {
@@ -743,7 +749,7 @@ mod tests {
         extract::*,
         security::{ApiKey, ApiKeyValue, Http, HttpAuthScheme},
         server::Server,
-        ToSchema,
+        RouterExt, ToSchema,
     };
 
     use salvo_core::{http::ResBody, prelude::*};
@@ -1161,6 +1167,36 @@ mod tests {
             .into_router("/openapi/doc");
     }
 
+    #[test]
+    fn test_router_security_scheme() {
+        #[salvo_oapi::endpoint]
+        async fn create_item() -> &'static str {
+            "created"
+        }
+
+        let router = Router::new()
+            .oapi_security_scheme(
+                "api_key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key"))),
+            )
+            .push(Router::with_path("items").post(create_item));
+        let doc = OpenApi::new("item api", "0.1.0").merge_router(&router);
+        let json = serde_json::to_value(&doc).unwrap();
+
+        assert_eq!(
+            json["components"]["securitySchemes"]["api_key"],
+            json!({"type": "apiKey", "name": "x-api-key", "in": "header"})
+        );
+        assert!(OpenApi::new("empty api", "0.1.0")
+            .merge_router(&Router::new().oapi_security_scheme(
+                "api_key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key"))),
+            ))
+            .components
+            .security_schemes
+            .is_empty());
+    }
+
     #[test]
     fn test_openapi_to_pretty_json() -> Result<(), serde_json::Error> {
         let raw_json = r#"{
@@ -1,11 +1,11 @@
 use std::any::TypeId;
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::{LazyLock, RwLock};
 
 use regex::Regex;
 use salvo_core::Router;
 
-use crate::{path::PathItemType, SecurityRequirement};
+use crate::{path::PathItemType, security::SecurityScheme, SecurityRequirement};
 
 #[derive(Debug, Default)]
 pub(crate) struct NormNode {
@@ -33,6 +33,9 @@ impl NormNode {
             node.metadata
                 .securities
                 .extend(metadata.securities.iter().cloned());
+            node.metadata
+                .security_schemes
+                .extend(metadata.security_schemes.clone());
         }
 
         let regex = Regex::new(r#"<([^/:>]+)(:[^>]*)?>"#).expect("invalid regex");
@@ -94,6 +97,12 @@ pub trait RouterExt {
     where
         I: IntoIterator<Item = SecurityRequirement>;
 
+    /// Add security scheme to the router.
+    ///
+    /// The scheme is added to the components of the document when an endpoint in the router
+    /// or it's descents is merged, so middlewares can describe the scheme they require.
+    fn oapi_security_scheme(self, name: impl Into<String>, scheme: SecurityScheme) -> Self;
+
     /// Add tag to the router.
     ///
     /// All endpoints in the router and it's descents will inherit this tag.
@@ -128,6 +137,14 @@ impl RouterExt for Router {
         metadata.securities.extend(iter);
         self
     }
+    fn oapi_security_scheme(self, name: impl Into<String>, scheme: SecurityScheme) -> Self {
+        let mut guard = METADATA_REGISTRY
+            .write()
+            .expect("failed to lock METADATA_REGISTRY for write");
+        let metadata = guard.entry(self.id).or_default();
+        metadata.security_schemes.insert(name.into(), scheme);
+        self
+    }
     fn oapi_tag(self, tag: impl Into<String>) -> Self {
         let mut guard = METADATA_REGISTRY
             .write()
@@ -155,4 +172,5 @@ impl RouterExt for Router {
 pub(crate) struct Metadata {
     pub(crate) tags: BTreeSet<String>,
     pub(crate) securities: Vec<SecurityRequirement>,
+    pub(crate) security_schemes: BTreeMap<String, SecurityScheme>,
 }